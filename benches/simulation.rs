@@ -0,0 +1,186 @@
+//! Per-tick cost of the simulation with hundreds to thousands of cuboids.
+//!
+//! The gameplay plugins run headless, see [`env::headless_app`]. Each benchmark
+//! keeps the arena filled with the given number of cuboids and measures a single system, or
+//! the rapier step, after every update of the app. Criterion stores the results in
+//! `target/criterion` and reports changes against the previous run.
+
+use bevy::{
+    ecs::system::System,
+    prelude::*,
+    utils::{Duration, Instant},
+};
+use bevy_rapier2d::physics::PhysicsStages;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use rscuboids::{
+    config::SpawnerConfig,
+    console::ConsoleCommand,
+    cuboids::{self, Cuboid},
+    env,
+    level::{CurrentLevel, Level},
+    ship::{self, GodMode},
+    state::GameState,
+    trap, GameConfig,
+};
+
+/// Number of cuboids kept in the arena.
+const CUBOIDS: [usize; 4] = [250, 500, 1000, 2000];
+/// Spawners along the top of the arena.
+const SPAWNERS: usize = 16;
+const SEED: u64 = 42;
+
+/// Duration of the physics stages in the last update.
+#[derive(Debug, Default)]
+struct StepTime {
+    start: Option<Instant>,
+    last: Duration,
+}
+
+fn start_step(mut step: ResMut<StepTime>) {
+    step.start = Some(Instant::now());
+}
+
+fn stop_step(mut step: ResMut<StepTime>) {
+    if let Some(start) = step.start.take() {
+        step.last = start.elapsed();
+    }
+}
+
+fn level() -> Level {
+    let spawners = (0..SPAWNERS)
+        .map(|index| {
+            let x = -100.0 + 200.0 * index as f32 / (SPAWNERS - 1) as f32;
+            SpawnerConfig {
+                angle_range: Some(240..300),
+                ..SpawnerConfig::new(Vec2::new(x, 70.0), 0.5)
+            }
+        })
+        .collect();
+    Level {
+        name: "Benchmark".to_string(),
+        spawners,
+        ..Level::default()
+    }
+}
+
+/// App with the gameplay plugins, which entered a run.
+fn headless_app() -> App {
+    let mut app = env::headless_app(GameConfig::default());
+    app.insert_resource(CurrentLevel(level()))
+        .insert_resource(GodMode(true))
+        .init_resource::<StepTime>()
+        .add_system_to_stage(PhysicsStages::SyncTransforms, start_step)
+        .add_system_to_stage(PhysicsStages::Writeback, stop_step);
+
+    app.update();
+    app.world
+        .get_resource_mut::<State<GameState>>()
+        .expect("States are added by the state plugin")
+        .set(GameState::NewRun)
+        .expect("Run can be started from the main menu");
+    // Enter the new run and start it
+    app.update();
+    app.update();
+    app
+}
+
+/// Spawns cuboids with the `spawn` console command, until there are `count` of them.
+fn fill_arena(app: &mut App, count: usize, rng: &mut StdRng) {
+    let existing = app
+        .world
+        .query_filtered::<(), With<Cuboid>>()
+        .iter(&app.world)
+        .count();
+    let mut commands = app
+        .world
+        .get_resource_mut::<Events<ConsoleCommand>>()
+        .expect("Console commands are registered by the cuboids plugin");
+    for _ in existing..count {
+        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+        let speed = rng.gen_range(10.0..50.0);
+        let values = [
+            rng.gen_range(1..4u8) as f32,
+            rng.gen_range(-100.0..100.0),
+            rng.gen_range(-90.0..90.0),
+            speed * angle.cos(),
+            speed * angle.sin(),
+        ];
+        let mut args = vec!["cuboid".to_string()];
+        args.extend(values.iter().map(|value| value.to_string()));
+        commands.send(ConsoleCommand {
+            name: "spawn".to_string(),
+            args,
+        });
+    }
+    // Cuboids are spawned by the next update
+    app.update();
+}
+
+/// Measures `system` run after each update of the app with `count` cuboids.
+fn bench_system<Param>(
+    c: &mut Criterion,
+    name: &str,
+    system: impl IntoSystem<(), (), Param> + Clone,
+) {
+    let mut group = c.benchmark_group(name);
+    for count in CUBOIDS {
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, &count| {
+            let mut app = headless_app();
+            let mut rng = StdRng::seed_from_u64(SEED);
+            let mut system = IntoSystem::into_system(system.clone());
+            system.initialize(&mut app.world);
+            b.iter_custom(|iterations| {
+                let mut total = Duration::ZERO;
+                for _ in 0..iterations {
+                    fill_arena(&mut app, count, &mut rng);
+                    let start = Instant::now();
+                    system.run((), &mut app.world);
+                    system.apply_buffers(&mut app.world);
+                    total += start.elapsed();
+                }
+                total
+            });
+        });
+    }
+    group.finish();
+}
+
+fn spawner_system(c: &mut Criterion) {
+    bench_system(c, "spawner_system", cuboids::spawner_system);
+}
+
+fn collisions(c: &mut Criterion) {
+    bench_system(c, "collisions", ship::collisions);
+}
+
+fn traps(c: &mut Criterion) {
+    bench_system(c, "traps", trap::traps);
+}
+
+fn rapier_step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rapier_step");
+    for count in CUBOIDS {
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, &count| {
+            let mut app = headless_app();
+            let mut rng = StdRng::seed_from_u64(SEED);
+            b.iter_custom(|iterations| {
+                let mut total = Duration::ZERO;
+                for _ in 0..iterations {
+                    fill_arena(&mut app, count, &mut rng);
+                    total += app
+                        .world
+                        .get_resource::<StepTime>()
+                        .expect("Step time is measured every update")
+                        .last;
+                }
+                total
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, spawner_system, collisions, traps, rapier_step);
+criterion_main!(benches);
@@ -0,0 +1,577 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{
+    config::GameConfig,
+    console::{ConsoleAppExt, ConsoleCommand, ConsoleOutput},
+    controller::Autopilot,
+    cuboids::{CuboidDestroyed, DestructionCause, Wave},
+    menu::{MenuStyle, ScaledText},
+    mode::CurrentMode,
+    settings::Settings,
+    ship::{GodMode, ShipHit, ShipSystem},
+    state::GameState,
+    storage,
+    theme::Theme,
+    weapon::{ShotFired, ShotMissed, WeaponSystem},
+};
+
+const TOAST_TITLE_FONT_SIZE: f32 = 16.0;
+const TOAST_FONT_SIZE: f32 = 24.0;
+/// Vertical distance between stacked toasts, before the text scale.
+const TOAST_SPACING: f32 = 70.0;
+
+#[derive(Debug, Clone)]
+pub struct AchievementsConfig {
+    /// Cuboids to destroy for [`Achievement::Destroyer`].
+    pub destroyer_goal: u32,
+    /// Waves to survive without losing a life for [`Achievement::Untouchable`].
+    pub untouchable_waves: u32,
+    /// Seconds an unlock toast is shown.
+    pub toast_duration: f32,
+}
+
+impl Default for AchievementsConfig {
+    fn default() -> Self {
+        Self {
+            destroyer_goal: 1000,
+            untouchable_waves: 10,
+            toast_duration: 4.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Achievement {
+    /// First cuboid destroyed by a shot.
+    FirstKill,
+    /// Cuboids destroyed by shots over all runs.
+    Destroyer,
+    /// Waves survived in a single run without losing a life.
+    Untouchable,
+    /// Wave cleared without a missed shot, in place of a boss fight, as the game has no bosses.
+    Flawless,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 4] = [
+        Achievement::FirstKill,
+        Achievement::Destroyer,
+        Achievement::Untouchable,
+        Achievement::Flawless,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Achievement::FirstKill => "First Kill",
+            Achievement::Destroyer => "Destroyer",
+            Achievement::Untouchable => "Untouchable",
+            Achievement::Flawless => "Flawless",
+        }
+    }
+
+    pub fn description(&self, config: &AchievementsConfig) -> String {
+        match self {
+            Achievement::FirstKill => "Destroy a cuboid".to_string(),
+            Achievement::Destroyer => format!("Destroy {} cuboids", config.destroyer_goal),
+            Achievement::Untouchable => format!(
+                "Survive {} waves without losing a life",
+                config.untouchable_waves
+            ),
+            Achievement::Flawless => "Clear a wave without missing a shot".to_string(),
+        }
+    }
+
+    /// Progress needed for the unlock.
+    pub fn goal(&self, config: &AchievementsConfig) -> u32 {
+        match self {
+            Achievement::FirstKill | Achievement::Flawless => 1,
+            Achievement::Destroyer => config.destroyer_goal,
+            Achievement::Untouchable => config.untouchable_waves,
+        }
+    }
+
+    pub fn progress(&self, stats: &LifetimeStats) -> u32 {
+        match self {
+            Achievement::FirstKill | Achievement::Destroyer => stats.cuboids_destroyed(),
+            Achievement::Untouchable => stats.best_untouched_waves,
+            Achievement::Flawless => stats.flawless_waves,
+        }
+    }
+}
+
+/// Statistics over all runs played by the player, without the bot's runs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LifetimeStats {
+    pub runs: u32,
+    /// Seconds spent in game, without time spent in menus.
+    pub play_time: f64,
+    pub shots_fired: u32,
+    pub shots_hit: u32,
+    /// Number of cuboids destroyed by shots by their size.
+    pub destroyed: BTreeMap<u8, u32>,
+    /// Most waves survived in a run without losing a life.
+    pub best_untouched_waves: u32,
+    /// Waves cleared without a missed shot.
+    pub flawless_waves: u32,
+}
+
+impl LifetimeStats {
+    pub fn cuboids_destroyed(&self) -> u32 {
+        self.destroyed.values().sum()
+    }
+}
+
+/// Lifetime statistics and unlocked achievements, saved together.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Achievements {
+    pub stats: LifetimeStats,
+    pub unlocked: BTreeSet<Achievement>,
+}
+
+impl Achievements {
+    const STORAGE_NAME: &'static str = "achievements";
+
+    pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+
+    pub fn load(config: &GameConfig) -> Self {
+        storage::load(&config.storage, Self::STORAGE_NAME).unwrap_or_default()
+    }
+
+    pub fn save(&self, config: &GameConfig) {
+        storage::save(&config.storage, Self::STORAGE_NAME, self);
+    }
+}
+
+/// Sent, when an achievement is unlocked.
+#[derive(Debug, Clone)]
+pub struct AchievementUnlocked(pub Achievement);
+
+/// Progress of the current run towards the per-run achievements.
+#[derive(Debug, Default)]
+struct RunProgress {
+    /// Wave seen in the last frame, 0 before the first frame of the run.
+    wave: u32,
+    /// Ship lost a life, or could not lose any.
+    damaged: bool,
+    wave_hits: u32,
+    wave_missed: bool,
+}
+
+/// Gameplay of a single frame of the run.
+#[derive(Debug, Clone, Default)]
+struct Frame {
+    /// Seconds since the last frame.
+    delta: f64,
+    wave: u32,
+    fired: u32,
+    missed: bool,
+    /// Sizes of the cuboids destroyed by shots.
+    kills: Vec<u8>,
+    /// Ship lost a life.
+    hit: bool,
+    /// Ship cannot lose lives, e.g. in god mode or zen.
+    invulnerable: bool,
+    /// Bot flies the ship.
+    autopilot: bool,
+}
+
+impl RunProgress {
+    /// Counts the `frame` towards the lifetime `stats`, clearing a wave when the next one starts.
+    fn update(&mut self, frame: &Frame, stats: &mut LifetimeStats) {
+        if frame.wave > self.wave {
+            // First wave starts with the run and is not cleared
+            if self.wave > 0 {
+                if !self.damaged {
+                    stats.best_untouched_waves = stats.best_untouched_waves.max(self.wave);
+                }
+                if !self.wave_missed && self.wave_hits > 0 {
+                    stats.flawless_waves += 1;
+                }
+            }
+            self.wave = frame.wave;
+            self.wave_hits = 0;
+            self.wave_missed = false;
+        }
+
+        // Bot's demo and autopilot are not the player's achievements
+        if frame.autopilot {
+            self.damaged = true;
+            self.wave_missed = true;
+            return;
+        }
+
+        // Waves without damage count only, when the ship can lose lives
+        self.damaged |= frame.hit || frame.invulnerable;
+        self.wave_missed |= frame.missed;
+        self.wave_hits += frame.kills.len() as u32;
+        stats.play_time += frame.delta;
+        stats.shots_fired += frame.fired;
+        stats.shots_hit += frame.kills.len() as u32;
+        for size in frame.kills.iter().copied() {
+            *stats.destroyed.entry(size).or_default() += 1;
+        }
+    }
+}
+
+fn load_achievements(mut commands: Commands, config: Res<GameConfig>) {
+    commands.insert_resource(Achievements::load(&config));
+}
+
+fn save_achievements(config: Res<GameConfig>, achievements: Res<Achievements>) {
+    achievements.save(&config);
+}
+
+fn start_run(
+    autopilot: Res<Autopilot>,
+    mut progress: ResMut<RunProgress>,
+    mut achievements: ResMut<Achievements>,
+) {
+    *progress = RunProgress::default();
+    if !autopilot.0 {
+        achievements.stats.runs += 1;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn track_stats(
+    time: Res<Time>,
+    autopilot: Res<Autopilot>,
+    god_mode: Res<GodMode>,
+    mode: Res<CurrentMode>,
+    wave: Res<Wave>,
+    mut shots: EventReader<ShotFired>,
+    mut misses: EventReader<ShotMissed>,
+    mut destroyed: EventReader<CuboidDestroyed>,
+    mut hits: EventReader<ShipHit>,
+    mut progress: ResMut<RunProgress>,
+    mut achievements: ResMut<Achievements>,
+) {
+    // Events are read even when not counted, so they are not counted later
+    let frame = Frame {
+        delta: time.delta_seconds_f64(),
+        wave: wave.number,
+        fired: shots.iter().count() as u32,
+        missed: misses.iter().count() > 0,
+        kills: destroyed
+            .iter()
+            .filter(|event| event.cause == DestructionCause::Shot)
+            .map(|event| event.size)
+            .collect(),
+        hit: hits.iter().count() > 0,
+        invulnerable: god_mode.0 || !mode.0.damages_ship(),
+        autopilot: autopilot.0,
+    };
+    progress.update(&frame, &mut achievements.stats);
+}
+
+fn unlock_achievements(
+    config: Res<GameConfig>,
+    mut achievements: ResMut<Achievements>,
+    mut unlocked: EventWriter<AchievementUnlocked>,
+) {
+    let reached = Achievement::ALL
+        .into_iter()
+        .filter(|achievement| {
+            !achievements.is_unlocked(*achievement)
+                && achievement.progress(&achievements.stats)
+                    >= achievement.goal(&config.achievements)
+        })
+        .collect::<Vec<_>>();
+    if reached.is_empty() {
+        return;
+    }
+    for achievement in reached {
+        info!("Achievement unlocked: {}", achievement.name());
+        achievements.unlocked.insert(achievement);
+        unlocked.send(AchievementUnlocked(achievement));
+    }
+    // Unlocks are kept, even if the game is closed during the run
+    achievements.save(&config);
+}
+
+/// Notification of an unlocked achievement, removed after its timer.
+#[derive(Debug, Component)]
+struct Toast(Timer);
+
+fn show_toasts(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    mut unlocked: EventReader<AchievementUnlocked>,
+    toasts: Query<&Toast>,
+) {
+    let mut shown = toasts.iter().count();
+    for AchievementUnlocked(achievement) in unlocked.iter() {
+        let style = MenuStyle::new(&asset_server, &config, &settings, &theme);
+        commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        bottom: Val::Px(10.0 + shown as f32 * TOAST_SPACING * style.text_scale),
+                        right: Val::Px(10.0),
+                        ..Default::default()
+                    },
+                    flex_direction: FlexDirection::ColumnReverse,
+                    align_items: AlignItems::FlexEnd,
+                    ..Default::default()
+                },
+                color: UiColor(style.overlay_color),
+                ..Default::default()
+            })
+            .insert(Toast(Timer::from_seconds(
+                config.achievements.toast_duration,
+                false,
+            )))
+            .with_children(|parent| {
+                parent
+                    .spawn_bundle(style.text(
+                        "Achievement unlocked",
+                        TOAST_TITLE_FONT_SIZE,
+                        style.text_color,
+                    ))
+                    .insert(ScaledText {
+                        size: TOAST_TITLE_FONT_SIZE,
+                    });
+                parent
+                    .spawn_bundle(style.text(
+                        achievement.name(),
+                        TOAST_FONT_SIZE,
+                        style.highlight_color,
+                    ))
+                    .insert(ScaledText {
+                        size: TOAST_FONT_SIZE,
+                    });
+            });
+        shown += 1;
+    }
+}
+
+/// Removes the toasts after their duration in real time, so they also disappear in menus.
+fn expire_toasts(mut commands: Commands, time: Res<Time>, mut toasts: Query<(Entity, &mut Toast)>) {
+    for (entity, mut toast) in toasts.iter_mut() {
+        if toast.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn console_commands(
+    mut entered: EventReader<ConsoleCommand>,
+    mut output: EventWriter<ConsoleOutput>,
+    config: Res<GameConfig>,
+    achievements: Res<Achievements>,
+) {
+    for _ in entered.iter().filter(|command| command.name == "stats") {
+        let stats = &achievements.stats;
+        let destroyed = stats
+            .destroyed
+            .iter()
+            .map(|(size, count)| format!("{}: {}", size, count))
+            .collect::<Vec<_>>()
+            .join(", ");
+        output.send(ConsoleOutput(format!(
+            "{} runs, {:.0} minutes played, {} shots fired, {} hit",
+            stats.runs,
+            stats.play_time / 60.0,
+            stats.shots_fired,
+            stats.shots_hit
+        )));
+        output.send(ConsoleOutput(format!("Destroyed by size: {}", destroyed)));
+        for achievement in Achievement::ALL {
+            let config = &config.achievements;
+            let progress = achievement.progress(stats).min(achievement.goal(config));
+            let mark = if achievements.is_unlocked(achievement) {
+                "x"
+            } else {
+                " "
+            };
+            output.send(ConsoleOutput(format!(
+                "[{}] {} - {} ({}/{})",
+                mark,
+                achievement.name(),
+                achievement.description(config),
+                progress,
+                achievement.goal(config)
+            )));
+        }
+    }
+}
+
+/// Counts the lifetime statistics of the frame.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct AchievementsSystem;
+
+/// Counts [`LifetimeStats`] and unlocks [`Achievement`]s from the gameplay events.
+#[derive(Default)]
+pub struct AchievementsPlugin;
+
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<Settings>()
+            .init_resource::<Theme>()
+            .init_resource::<CurrentMode>()
+            .init_resource::<Wave>()
+            .init_resource::<Autopilot>()
+            .init_resource::<GodMode>()
+            .init_resource::<Achievements>()
+            .init_resource::<RunProgress>()
+            .add_event::<AchievementUnlocked>()
+            .add_console_command("stats", "", "Shows lifetime statistics and achievements")
+            .add_startup_system(load_achievements)
+            .add_system(console_commands)
+            .add_system(show_toasts)
+            .add_system(expire_toasts)
+            .add_system_set(SystemSet::on_enter(GameState::NewRun).with_system(start_run))
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .after(ShipSystem)
+                    .after(WeaponSystem)
+                    .with_system(track_stats.label(AchievementsSystem))
+                    .with_system(unlock_achievements.after(AchievementsSystem)),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::InGame).with_system(save_achievements));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn destroyed_cuboids_are_summed_over_sizes() {
+        let stats = LifetimeStats {
+            destroyed: BTreeMap::from([(1, 4), (2, 3), (5, 1)]),
+            ..Default::default()
+        };
+        assert_eq!(stats.cuboids_destroyed(), 8);
+        assert_eq!(LifetimeStats::default().cuboids_destroyed(), 0);
+    }
+
+    #[test]
+    fn progress_reaches_the_configured_goals() {
+        let config = AchievementsConfig {
+            destroyer_goal: 5,
+            untouchable_waves: 3,
+            ..Default::default()
+        };
+        let stats = LifetimeStats {
+            destroyed: BTreeMap::from([(1, 2), (3, 2)]),
+            best_untouched_waves: 3,
+            ..Default::default()
+        };
+        let reached =
+            |achievement: Achievement| achievement.progress(&stats) >= achievement.goal(&config);
+        assert!(reached(Achievement::FirstKill));
+        assert!(!reached(Achievement::Destroyer));
+        assert!(reached(Achievement::Untouchable));
+        assert!(!reached(Achievement::Flawless));
+        assert_eq!(Achievement::Destroyer.progress(&stats), 4);
+        assert_eq!(
+            Achievement::Destroyer.description(&config),
+            "Destroy 5 cuboids"
+        );
+    }
+
+    /// Stats after playing the `frames` of a run.
+    fn play(frames: &[Frame]) -> LifetimeStats {
+        let mut progress = RunProgress::default();
+        let mut stats = LifetimeStats::default();
+        for frame in frames {
+            progress.update(frame, &mut stats);
+        }
+        stats
+    }
+
+    fn wave(wave: u32) -> Frame {
+        Frame {
+            wave,
+            ..Default::default()
+        }
+    }
+
+    fn kill(wave: u32, size: u8) -> Frame {
+        Frame {
+            fired: 1,
+            kills: vec![size],
+            ..self::wave(wave)
+        }
+    }
+
+    #[test]
+    fn waves_are_untouched_until_the_ship_is_hit() {
+        let hit = Frame {
+            hit: true,
+            ..wave(3)
+        };
+        let stats = play(&[wave(1), wave(2), wave(3)]);
+        assert_eq!(stats.best_untouched_waves, 2);
+        let stats = play(&[wave(1), wave(2), wave(3), hit, wave(4), wave(5)]);
+        assert_eq!(stats.best_untouched_waves, 2);
+        let hit = Frame {
+            hit: true,
+            ..wave(1)
+        };
+        let stats = play(&[hit, wave(2), wave(3)]);
+        assert_eq!(stats.best_untouched_waves, 0);
+    }
+
+    #[test]
+    fn flawless_waves_need_hits_and_no_misses() {
+        let missed = Frame {
+            missed: true,
+            ..kill(2, 1)
+        };
+        let stats = play(&[kill(1, 1), wave(2), missed, wave(3), wave(4)]);
+        assert_eq!(stats.flawless_waves, 1);
+    }
+
+    #[test]
+    fn invulnerable_ships_do_not_survive_untouched() {
+        let invulnerable = |wave: u32| Frame {
+            invulnerable: true,
+            ..kill(wave, 2)
+        };
+        let stats = play(&[invulnerable(1), invulnerable(2), invulnerable(3)]);
+        assert_eq!(stats.best_untouched_waves, 0);
+        assert_eq!(stats.flawless_waves, 2);
+        assert_eq!(stats.cuboids_destroyed(), 3);
+    }
+
+    #[test]
+    fn autopilot_is_not_counted() {
+        let autopilot = Frame {
+            autopilot: true,
+            ..kill(1, 3)
+        };
+        let stats = play(&[autopilot, kill(1, 1), wave(2)]);
+        assert_eq!(stats.shots_hit, 1);
+        assert_eq!(stats.destroyed, BTreeMap::from([(1, 1)]));
+        assert_eq!(stats.best_untouched_waves, 0);
+        assert_eq!(stats.flawless_waves, 0);
+    }
+
+    #[test]
+    fn frames_are_added_to_the_totals() {
+        let frame = Frame {
+            delta: 0.5,
+            fired: 3,
+            kills: vec![1, 4],
+            ..wave(1)
+        };
+        let stats = play(&[frame.clone(), frame]);
+        assert_eq!(stats.play_time, 1.0);
+        assert_eq!(stats.shots_fired, 6);
+        assert_eq!(stats.shots_hit, 4);
+        assert_eq!(stats.destroyed, BTreeMap::from([(1, 2), (4, 2)]));
+    }
+}
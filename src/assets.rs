@@ -1,58 +1,209 @@
-use bevy::prelude::*;
-use std::collections::HashMap;
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use bevy_rapier2d::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+use std::collections::{HashMap, VecDeque};
 
-pub const CUBOID_MESH_SIZE: f32 = 3.75;
+use super::config::{CuboidsConfig, GameConfig};
+use super::settings::Settings;
+use super::shapes::CuboidShape;
+use super::theme::{Theme, ThemeMaterial, ThemeSystem};
+
+/// Mesh of an irregular cuboid with its collider.
+pub struct CuboidMesh {
+    pub mesh: Handle<Mesh>,
+    pub collider: ColliderShape,
+}
+
+/// Shapes of the spawned cuboids, generated for their size and seed on the first use.
+#[derive(Default)]
+pub struct CuboidShapes {
+    cache: HashMap<(u8, u64), CuboidMesh>,
+    /// Keys of the cache from the oldest, which is removed first.
+    order: VecDeque<(u8, u64)>,
+}
+
+impl CuboidShapes {
+    /// Shape of the cuboid of the `size`, generated from the `seed`.
+    ///
+    /// Meshes of removed shapes are freed, once no cuboid uses them.
+    pub fn get(
+        &mut self,
+        meshes: &mut Assets<Mesh>,
+        config: &CuboidsConfig,
+        size: u8,
+        seed: u64,
+    ) -> &CuboidMesh {
+        let key = (size, seed);
+        if !self.cache.contains_key(&key) {
+            if self.order.len() >= config.shape.cache_size.max(1) {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.cache.remove(&oldest);
+                }
+            }
+            let extent = 0.5 * config.mesh_size * size as f32;
+            let mut rng = StdRng::seed_from_u64(seed);
+            let shape = CuboidShape::generate(&mut rng, extent, &config.shape);
+            let mesh = CuboidMesh {
+                mesh: meshes.add(shape.mesh),
+                collider: shape.collider,
+            };
+            self.cache.insert(key, mesh);
+            self.order.push_back(key);
+        }
+        &self.cache[&key]
+    }
+}
 
 pub struct Meshes {
-    /// Map from cuboid size to mesh
-    pub cuboid: HashMap<u8, Handle<Mesh>>,
+    /// Irregular meshes of the shards of destroyed cuboids
+    pub shards: Vec<Handle<Mesh>>,
     /// Ship mesh
     pub ship: Handle<Mesh>,
+    /// Bullet mesh
+    pub bullet: Handle<Mesh>,
 }
 
 pub struct Materials {
     /// Map from cuboid size to material
     pub cuboid: HashMap<u8, Handle<StandardMaterial>>,
+    /// Map from cuboid size to its pattern, shown with the size patterns setting
+    pub cuboid_pattern: HashMap<u8, Handle<Image>>,
     /// Ship material
     pub ship: Handle<StandardMaterial>,
+    /// Bullet material
+    pub bullet: Handle<StandardMaterial>,
+    /// Glowing material of sparks
+    pub spark: Handle<StandardMaterial>,
+    /// Material of ship's thruster trail
+    pub thruster: Handle<StandardMaterial>,
+}
+
+/// Edge length of the pattern textures in pixels.
+const PATTERN_SIZE: usize = 64;
+const PATTERN_BORDER: usize = 4;
+
+/// Generates texture with a dark outline and `size - 1` diagonal stripes.
+fn size_pattern(size: u8) -> Image {
+    const LIGHT: [u8; 4] = [255, 255, 255, 255];
+    const DARK: [u8; 4] = [64, 64, 64, 255];
+    let stripes = size.saturating_sub(1) as usize;
+    let period = 2 * PATTERN_SIZE / (stripes + 1);
+    let mut data = Vec::with_capacity(PATTERN_SIZE * PATTERN_SIZE * 4);
+    for y in 0..PATTERN_SIZE {
+        for x in 0..PATTERN_SIZE {
+            let border = x.min(y).min(PATTERN_SIZE - 1 - x).min(PATTERN_SIZE - 1 - y);
+            // Stripes are spread evenly along the diagonal
+            let diagonal = (x + y) as i32;
+            let on_stripe = (1..=stripes).any(|k| (diagonal - (k * period) as i32).abs() <= 2);
+            let pixel = if border < PATTERN_BORDER || on_stripe {
+                DARK
+            } else {
+                LIGHT
+            };
+            data.extend_from_slice(&pixel);
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: PATTERN_SIZE as u32,
+            height: PATTERN_SIZE as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
 }
 
 fn generate_assets(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    config: Res<GameConfig>,
+    theme: Res<Theme>,
 ) {
+    let mut rng = StdRng::seed_from_u64(config.cuboids.shape.seed);
+    // Shards have the size of the smallest cuboids
+    let extent = 0.5 * config.cuboids.mesh_size * config.cuboids.sizes.start as f32;
     commands.insert_resource(Meshes {
-        cuboid: (1..10)
-            .map(|s| {
-                (
-                    s,
-                    meshes.add(Mesh::from(shape::Cube {
-                        size: CUBOID_MESH_SIZE * s as f32,
-                    })),
-                )
+        shards: (0..config.cuboids.shape.shards.max(1))
+            .map(|_| {
+                let shape = CuboidShape::generate(&mut rng, extent, &config.cuboids.shape);
+                meshes.add(shape.mesh)
             })
-            .collect::<HashMap<_, _>>(),
-        ship: meshes.add(Mesh::from(shape::Cube { size: 8.0 })),
+            .collect(),
+        ship: meshes.add(Mesh::from(shape::Cube {
+            size: config.ship.size,
+        })),
+        bullet: meshes.add(Mesh::from(shape::Cube {
+            size: config.weapon.bullet_size,
+        })),
     });
     commands.insert_resource(Materials {
-        cuboid: (1..10)
-            .map(|s| {
-                (
-                    s,
-                    materials.add(StandardMaterial::from(Color::rgb(0.65, 0.6, 0.6))),
-                )
-            })
+        cuboid: config
+            .cuboids
+            .sizes
+            .clone()
+            .map(|s| (s, materials.add(theme.cuboid(s).material())))
             .collect::<HashMap<_, _>>(),
-        ship: materials.add(StandardMaterial::from(Color::rgb(0.85, 0.85, 0.1))),
+        cuboid_pattern: config
+            .cuboids
+            .sizes
+            .clone()
+            .map(|s| (s, images.add(size_pattern(s))))
+            .collect::<HashMap<_, _>>(),
+        ship: materials.add(theme.ship.material()),
+        bullet: materials.add(theme.bullet.material()),
+        spark: materials.add(theme.spark.material()),
+        thruster: materials.add(theme.thruster.material()),
     });
 }
 
+/// Recolours the shared materials, so all entities switch to the new theme.
+fn apply_theme(
+    theme: Res<Theme>,
+    settings: Res<Settings>,
+    handles: Res<Materials>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !theme.is_changed() && !settings.is_changed() {
+        return;
+    }
+    let mut apply = |handle: &Handle<StandardMaterial>, colors: &ThemeMaterial| {
+        if let Some(material) = materials.get_mut(handle) {
+            colors.apply(material);
+        }
+    };
+    for (size, handle) in handles.cuboid.iter() {
+        apply(handle, theme.cuboid(*size));
+        if let Some(material) = materials.get_mut(handle) {
+            material.base_color_texture = if settings.accessibility.size_patterns {
+                handles.cuboid_pattern.get(size).cloned()
+            } else {
+                None
+            };
+        }
+    }
+    apply(&handles.ship, &theme.ship);
+    apply(&handles.bullet, &theme.bullet);
+    apply(&handles.spark, &theme.spark);
+    apply(&handles.thruster, &theme.thruster);
+}
+
 #[derive(Default)]
 pub struct AssetsPlugin;
 
 impl Plugin for AssetsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(generate_assets);
+        app.init_resource::<GameConfig>()
+            .init_resource::<Theme>()
+            .init_resource::<Settings>()
+            .init_resource::<CuboidShapes>()
+            .add_startup_system(generate_assets)
+            .add_system(apply_theme.after(ThemeSystem));
     }
 }
@@ -0,0 +1,290 @@
+use bevy::{input::InputSystem, prelude::*};
+
+use super::{
+    config::GameConfig,
+    controller::Autopilot,
+    menu::{MenuStyle, ScaledText},
+    mode::{CurrentMode, GameMode, ModeSystem},
+    mutators::{Mutator, Mutators},
+    scoring::HighScoreTables,
+    settings::Settings,
+    ship::{Ship, ShipDestroyedSystem, ShipSystem},
+    state::{CurrentRun, GameState},
+    theme::Theme,
+};
+
+const OVERLAY_FONT_SIZE: f32 = 32.0;
+/// Entries of the high scores table shown in the demo.
+const SHOWN_HIGH_SCORES: usize = 5;
+/// Seconds, for which "PRESS START" is shown and then hidden.
+const BLINK_INTERVAL: f32 = 0.5;
+
+#[derive(Debug, Clone)]
+pub struct AttractConfig {
+    /// Seconds without input in the main menu, after which the demo starts.
+    pub idle_time: f32,
+    /// Seconds of the demo, before it returns to the main menu.
+    pub demo_duration: f32,
+    /// Seconds each page of the overlay is shown, "PRESS START" and then the high scores.
+    pub page_duration: f32,
+}
+
+impl Default for AttractConfig {
+    fn default() -> Self {
+        Self {
+            idle_time: 30.0,
+            demo_duration: 60.0,
+            page_duration: 5.0,
+        }
+    }
+}
+
+/// Demo run played by the bot, while nobody is at the controls.
+#[derive(Debug, Default)]
+pub struct AttractMode {
+    pub active: bool,
+    /// Seconds without input in the main menu.
+    idle: f32,
+    /// Seconds since the demo started.
+    elapsed: f32,
+    /// Seed chosen for the player's next run, kept aside during the demo.
+    saved_seed: Option<u64>,
+    /// Mode chosen by the player, the demo is played in the endless mode.
+    saved_mode: Option<GameMode>,
+    /// Mutators chosen by the player, the demo is played without them.
+    saved_mutators: Vec<Mutator>,
+}
+
+#[derive(Debug, Component)]
+struct AttractOverlay;
+
+#[derive(Debug, Component)]
+struct AttractText;
+
+fn any_input(
+    keyboard_input: &Input<KeyCode>,
+    mouse_input: &Input<MouseButton>,
+    gamepad_input: &Input<GamepadButton>,
+) -> bool {
+    keyboard_input.get_just_pressed().next().is_some()
+        || mouse_input.get_just_pressed().next().is_some()
+        || gamepad_input.get_just_pressed().next().is_some()
+}
+
+fn reset_idle(mut attract: ResMut<AttractMode>) {
+    attract.idle = 0.0;
+}
+
+/// Restores the player's mode in the menu, after the run ended with it.
+fn restore_mode(mut attract: ResMut<AttractMode>, mut mode: ResMut<CurrentMode>) {
+    if let Some(saved) = attract.saved_mode.take() {
+        mode.0 = saved;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn start_demo(
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    mut attract: ResMut<AttractMode>,
+    mut autopilot: ResMut<Autopilot>,
+    mut mode: ResMut<CurrentMode>,
+    mut mutators: ResMut<Mutators>,
+    mut run: ResMut<CurrentRun>,
+    mut state: ResMut<State<GameState>>,
+) {
+    // Demo would replace the run, which the player can continue
+    if run.active || any_input(&keyboard_input, &mouse_input, &gamepad_input) {
+        attract.idle = 0.0;
+        return;
+    }
+    attract.idle += time.delta_seconds();
+    if attract.idle < config.attract.idle_time || state.set(GameState::NewRun).is_err() {
+        return;
+    }
+    attract.active = true;
+    attract.elapsed = 0.0;
+    attract.saved_seed = run.next_seed.take();
+    attract.saved_mode = Some(std::mem::replace(&mut mode.0, GameMode::Endless));
+    attract.saved_mutators = std::mem::take(&mut mutators.enabled);
+    autopilot.0 = true;
+}
+
+/// Returns to the main menu on any input, which is then hidden from the rest of the game.
+fn interrupt_demo(
+    attract: Res<AttractMode>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut mouse_input: ResMut<Input<MouseButton>>,
+    mut gamepad_input: ResMut<Input<GamepadButton>>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !attract.active
+        || *state.current() != GameState::InGame
+        || !any_input(&keyboard_input, &mouse_input, &gamepad_input)
+    {
+        return;
+    }
+    let _ = state.set(GameState::MainMenu);
+    keyboard_input.clear();
+    mouse_input.clear();
+    gamepad_input.clear();
+}
+
+/// Ends the demo after its duration or when the bot loses, before the game over screen.
+fn run_demo(
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    mut attract: ResMut<AttractMode>,
+    mut state: ResMut<State<GameState>>,
+    ships: Query<&Ship>,
+) {
+    if !attract.active {
+        return;
+    }
+    attract.elapsed += time.delta_seconds();
+    let destroyed = ships.iter().any(|ship| ship.lives == 0);
+    if attract.elapsed >= config.attract.demo_duration || destroyed {
+        let _ = state.set(GameState::MainMenu);
+    }
+}
+
+fn end_demo(
+    mut attract: ResMut<AttractMode>,
+    mut autopilot: ResMut<Autopilot>,
+    mut mutators: ResMut<Mutators>,
+    mut run: ResMut<CurrentRun>,
+) {
+    if !attract.active {
+        return;
+    }
+    attract.active = false;
+    autopilot.0 = false;
+    mutators.enabled = std::mem::take(&mut attract.saved_mutators);
+    // Demo cannot be continued from the menu
+    run.active = false;
+    run.next_seed = attract.saved_seed.take();
+}
+
+fn spawn_overlay(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    attract: Res<AttractMode>,
+) {
+    if !attract.active {
+        return;
+    }
+    let style = MenuStyle::new(&asset_server, &config, &settings, &theme);
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: UiColor(Color::NONE),
+            ..Default::default()
+        })
+        .insert(AttractOverlay)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(style.text("", OVERLAY_FONT_SIZE, style.highlight_color))
+                .insert(ScaledText {
+                    size: OVERLAY_FONT_SIZE,
+                })
+                .insert(AttractText);
+        });
+}
+
+fn despawn_overlay(mut commands: Commands, overlays: Query<Entity, With<AttractOverlay>>) {
+    for entity in overlays.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Alternates between blinking "PRESS START" and the high scores.
+fn update_overlay(
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    attract: Res<AttractMode>,
+    high_scores: Res<HighScoreTables>,
+    mut texts: Query<&mut Text, With<AttractText>>,
+) {
+    if !attract.active {
+        return;
+    }
+    let high_scores = high_scores.get(GameMode::Endless);
+    let page = (attract.elapsed / config.attract.page_duration) as usize;
+    let value = if page % 2 == 1 && !high_scores.entries.is_empty() {
+        let mut lines = vec!["HIGH SCORES".to_string()];
+        lines.extend(
+            high_scores
+                .entries
+                .iter()
+                .take(SHOWN_HIGH_SCORES)
+                .enumerate()
+                .map(|(rank, entry)| format!("{}. {:<3} {:>7}", rank + 1, entry.name, entry.score)),
+        );
+        lines.join("\n")
+    } else {
+        let hidden = (attract.elapsed / BLINK_INTERVAL) as usize % 2 == 1;
+        // Blinking is too much motion for some players
+        if hidden && !settings.accessibility.reduced_motion {
+            String::new()
+        } else {
+            "PRESS START".to_string()
+        }
+    };
+    for mut text in texts.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+/// Plays a demo with the bot on a random seed, when the main menu is left idle.
+#[derive(Default)]
+pub struct AttractPlugin;
+
+impl Plugin for AttractPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<Settings>()
+            .init_resource::<Theme>()
+            .init_resource::<CurrentMode>()
+            .init_resource::<Mutators>()
+            .init_resource::<HighScoreTables>()
+            .init_resource::<Autopilot>()
+            .init_resource::<AttractMode>()
+            .add_system_to_stage(CoreStage::PreUpdate, interrupt_demo.after(InputSystem))
+            .add_system_set(
+                SystemSet::on_enter(GameState::MainMenu)
+                    .with_system(reset_idle)
+                    .with_system(restore_mode),
+            )
+            .add_system_set(SystemSet::on_resume(GameState::MainMenu).with_system(reset_idle))
+            .add_system_set(SystemSet::on_update(GameState::MainMenu).with_system(start_demo))
+            .add_system_set(SystemSet::on_enter(GameState::InGame).with_system(spawn_overlay))
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .after(ShipSystem)
+                    .before(ShipDestroyedSystem)
+                    .before(ModeSystem)
+                    .with_system(run_demo)
+                    .with_system(update_overlay),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame)
+                    .with_system(end_demo)
+                    .with_system(despawn_overlay),
+            );
+    }
+}
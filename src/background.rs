@@ -0,0 +1,243 @@
+use bevy::{
+    pbr::AlphaMode,
+    prelude::*,
+    render::{
+        mesh::Indices,
+        render_resource::{Extent3d, PrimitiveTopology, TextureDimension, TextureFormat},
+    },
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::ops::Range;
+
+use super::config::GameConfig;
+
+/// Single depth of the starfield.
+#[derive(Debug, Clone)]
+pub struct StarLayerConfig {
+    pub count: usize,
+    /// Distance behind the gameplay plane.
+    pub depth: f32,
+    /// Edge length of the biggest stars.
+    pub size: f32,
+    pub brightness: f32,
+    /// How much the layer follows the camera, `0.0` stays in place, `1.0` moves with it.
+    pub parallax: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct BackgroundConfig {
+    /// Seed of the generator, so the sky is the same in every run.
+    pub seed: u64,
+    /// Layers of stars, from the nearest one.
+    pub layers: Vec<StarLayerConfig>,
+    /// Number of nebula quads behind the farthest layer.
+    pub nebulae: usize,
+    pub nebula_size: Range<f32>,
+    pub nebula_colors: Vec<Color>,
+}
+
+impl Default for BackgroundConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0x57a2_f1e1d,
+            layers: vec![
+                StarLayerConfig {
+                    count: 150,
+                    depth: 100.0,
+                    size: 0.8,
+                    brightness: 1.0,
+                    parallax: 0.2,
+                },
+                StarLayerConfig {
+                    count: 250,
+                    depth: 200.0,
+                    size: 1.0,
+                    brightness: 0.7,
+                    parallax: 0.5,
+                },
+                StarLayerConfig {
+                    count: 400,
+                    depth: 400.0,
+                    size: 1.4,
+                    brightness: 0.45,
+                    parallax: 0.8,
+                },
+            ],
+            nebulae: 4,
+            nebula_size: 150.0..350.0,
+            nebula_colors: vec![
+                Color::rgba(0.4, 0.1, 0.6, 0.25),
+                Color::rgba(0.1, 0.3, 0.6, 0.25),
+                Color::rgba(0.6, 0.15, 0.3, 0.2),
+            ],
+        }
+    }
+}
+
+/// Background entity moved with the camera by its `parallax`.
+#[derive(Debug, Component)]
+pub struct BackgroundLayer {
+    parallax: f32,
+    origin: Vec3,
+}
+
+/// Half of the area covered by the layer at `depth`, with a margin for camera movement.
+fn layer_extent(config: &GameConfig, depth: f32) -> Vec2 {
+    // Default vertical field of view is 45 degrees, wide screens are up to twice as wide
+    let distance = config.world.camera_position.z + depth;
+    let half_height = 1.5 * distance * (std::f32::consts::FRAC_PI_8).tan();
+    Vec2::new(2.0 * half_height, half_height)
+}
+
+/// Merges all stars of the layer into a single mesh, so it is drawn at once.
+fn star_mesh(rng: &mut StdRng, layer: &StarLayerConfig, extent: Vec2) -> Mesh {
+    let mut positions = Vec::with_capacity(4 * layer.count);
+    let mut indices = Vec::with_capacity(6 * layer.count);
+    for i in 0..layer.count as u32 {
+        let center = Vec2::new(
+            rng.gen_range(-extent.x..extent.x),
+            rng.gen_range(-extent.y..extent.y),
+        );
+        let half = 0.5 * layer.size * rng.gen_range(0.3..1.0);
+        for corner in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            positions.push([center.x + corner.0 * half, center.y + corner.1 * half, 0.0]);
+        }
+        let first = 4 * i;
+        indices.extend_from_slice(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+    let normals = vec![[0.0, 0.0, 1.0]; positions.len()];
+    let uvs = vec![[0.0, 0.0]; positions.len()];
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+/// White texture fading out to transparent edges.
+fn nebula_texture() -> Image {
+    const SIZE: usize = 64;
+    let mut data = Vec::with_capacity(SIZE * SIZE * 4);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let offset = Vec2::new(x as f32, y as f32) / (SIZE - 1) as f32 * 2.0 - Vec2::ONE;
+            let alpha = (1.0 - offset.length()).max(0.0).powi(2);
+            data.extend_from_slice(&[255, 255, 255, (alpha * 255.0) as u8]);
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: SIZE as u32,
+            height: SIZE as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+/// Spawns the starfield and nebulae behind the gameplay plane.
+pub fn spawn_background(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    images: &mut Assets<Image>,
+    config: &GameConfig,
+) {
+    let background = &config.world.background;
+    let mut rng = StdRng::seed_from_u64(background.seed);
+
+    for layer in background.layers.iter() {
+        let origin = Vec3::new(0.0, 0.0, -layer.depth);
+        let brightness = layer.brightness;
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: meshes.add(star_mesh(
+                    &mut rng,
+                    layer,
+                    layer_extent(config, layer.depth),
+                )),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgb(brightness, brightness, brightness),
+                    unlit: true,
+                    ..Default::default()
+                }),
+                transform: Transform::from_translation(origin),
+                ..Default::default()
+            })
+            .insert(BackgroundLayer {
+                parallax: layer.parallax,
+                origin,
+            });
+    }
+
+    let farthest = match background.layers.last() {
+        Some(layer) => layer,
+        None => return,
+    };
+    if background.nebulae == 0 || background.nebula_colors.is_empty() {
+        return;
+    }
+    let depth = farthest.depth + 10.0;
+    let extent = layer_extent(config, depth);
+    let texture = images.add(nebula_texture());
+    let origin = Vec3::new(0.0, 0.0, -depth);
+    commands
+        .spawn_bundle((
+            Transform::from_translation(origin),
+            GlobalTransform::default(),
+        ))
+        .insert(BackgroundLayer {
+            parallax: farthest.parallax,
+            origin,
+        })
+        .with_children(|parent| {
+            for i in 0..background.nebulae {
+                let size = rng.gen_range(background.nebula_size.clone());
+                let color = background.nebula_colors[i % background.nebula_colors.len()];
+                let position = Vec3::new(
+                    rng.gen_range(-extent.x..extent.x),
+                    rng.gen_range(-extent.y..extent.y),
+                    // Avoid z-fighting of overlapping nebulae
+                    i as f32,
+                );
+                parent.spawn_bundle(PbrBundle {
+                    mesh: meshes.add(Mesh::from(shape::Quad::new(Vec2::splat(size)))),
+                    material: materials.add(StandardMaterial {
+                        base_color: color,
+                        base_color_texture: Some(texture.clone()),
+                        alpha_mode: AlphaMode::Blend,
+                        unlit: true,
+                        ..Default::default()
+                    }),
+                    transform: Transform::from_translation(position).with_rotation(
+                        Quat::from_rotation_z(rng.gen_range(0.0..std::f32::consts::TAU)),
+                    ),
+                    ..Default::default()
+                });
+            }
+        });
+}
+
+/// Moves background layers by the camera's offset from its configured position.
+pub fn parallax(
+    config: Res<GameConfig>,
+    cameras: Query<&Transform, (With<PerspectiveProjection>, Without<BackgroundLayer>)>,
+    mut layers: Query<(&BackgroundLayer, &mut Transform)>,
+) {
+    let camera = match cameras.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+    let offset = (camera.translation - config.world.camera_position).truncate();
+    for (layer, mut transform) in layers.iter_mut() {
+        let translation = layer.origin + (offset * layer.parallax).extend(0.0);
+        // Avoid marking the transform changed, while the camera stands still
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use bevy::prelude::*;
+use std::cmp::Ordering;
+
+use super::controller::{CuboidState, Perception, ShipAction, ShipController};
+
+/// Positions along the predicted paths, at which the bot checks for hits.
+const SAMPLES: usize = 12;
+/// Directions the bot chooses from.
+const DIRECTIONS: [f32; 3] = [-1.0, 0.0, 1.0];
+/// Cost of not moving towards the target, so it only decides between equally safe directions.
+const DETOUR_COST: f32 = 0.01;
+
+#[derive(Debug, Clone)]
+pub struct BotConfig {
+    /// Cuboids further from the ship are ignored.
+    pub perception_radius: f32,
+    /// Seconds, for which the movement of cuboids is predicted.
+    pub horizon: f32,
+    /// Distance kept from the cuboids in addition to their sizes.
+    pub margin: f32,
+    /// Seconds between decisions, the last action is repeated in the meantime.
+    pub reaction_time: f32,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            perception_radius: 120.0,
+            horizon: 1.5,
+            margin: 3.0,
+            reaction_time: 0.1,
+        }
+    }
+}
+
+/// Built-in autopilot, which dodges the cuboids heading at the ship and shoots the others.
+///
+/// Cuboids are assumed to fly straight, bounces from the walls are not predicted.
+#[derive(Debug)]
+pub struct BotController {
+    config: BotConfig,
+    /// Seconds left until the next decision.
+    cooldown: f32,
+    action: ShipAction,
+}
+
+impl BotController {
+    pub fn new(config: BotConfig) -> Self {
+        Self {
+            config,
+            cooldown: 0.0,
+            action: ShipAction::default(),
+        }
+    }
+
+    /// Threat of moving in the `direction`, sooner hits are more dangerous.
+    fn danger(&self, perception: &Perception, cuboids: &[&CuboidState], direction: f32) -> f32 {
+        let config = perception.config;
+        let ship = &perception.ship;
+        let limit = perception.arena.half_size.x - config.ship.size;
+        let mut danger = 0.0;
+        for step in 1..=SAMPLES {
+            let time = self.config.horizon * step as f32 / SAMPLES as f32;
+            let x = (ship.position.x + direction * config.ship.speed * time).clamp(-limit, limit);
+            let ship_position = Vec2::new(x, ship.position.y);
+            for cuboid in cuboids {
+                let distance = (cuboid.position + cuboid.velocity * time - ship_position).abs();
+                let reach = 0.5 * config.ship.size
+                    + 0.5 * config.cuboids.mesh_size * cuboid.size as f32
+                    + self.config.margin;
+                if distance.x < reach && distance.y < reach {
+                    danger += 1.0 / time;
+                }
+            }
+        }
+        danger
+    }
+
+    /// Horizontal offset of the cuboid, which the bullet fired now hits first, and its extent.
+    fn aim(&self, perception: &Perception, cuboids: &[&CuboidState]) -> Option<(f32, f32)> {
+        let config = perception.config;
+        let ship = &perception.ship;
+        let muzzle = ship.position.y + config.ship.size;
+        cuboids
+            .iter()
+            .filter_map(|cuboid| {
+                let closing_speed = config.weapon.bullet_speed - cuboid.velocity.y;
+                let height = cuboid.position.y - muzzle;
+                if height <= 0.0 || closing_speed <= 0.0 {
+                    return None;
+                }
+                let time = height / closing_speed;
+                let x = cuboid.position.x + cuboid.velocity.x * time;
+                let extent = 0.5 * config.cuboids.mesh_size * cuboid.size as f32;
+                Some((time, x - ship.position.x, extent))
+            })
+            .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal))
+            .map(|(_, offset, extent)| (offset, extent))
+    }
+
+    fn decide(&self, perception: &Perception) -> ShipAction {
+        let ship = perception.ship.position;
+        let radius = self.config.perception_radius;
+        let nearby = perception
+            .cuboids
+            .iter()
+            .filter(|cuboid| cuboid.position.distance_squared(ship) <= radius * radius)
+            .collect::<Vec<_>>();
+
+        let aim = self.aim(perception, &nearby);
+        let preferred = match aim {
+            Some((offset, extent)) if offset.abs() > 0.5 * extent => offset.signum(),
+            _ => 0.0,
+        };
+        let cost = |direction: f32| {
+            let detour = if direction == preferred {
+                0.0
+            } else {
+                DETOUR_COST
+            };
+            self.danger(perception, &nearby, direction) + detour
+        };
+        let direction = DIRECTIONS
+            .into_iter()
+            .map(|direction| (direction, cost(direction)))
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+            .map(|(direction, _)| direction)
+            .unwrap_or_default();
+
+        ShipAction {
+            direction,
+            fire: matches!(aim, Some((offset, extent)) if offset.abs() <= extent),
+        }
+    }
+}
+
+impl ShipController for BotController {
+    fn act(&mut self, perception: &Perception) -> ShipAction {
+        self.cooldown -= perception.delta;
+        if self.cooldown <= 0.0 {
+            self.cooldown = self.config.reaction_time;
+            self.action = self.decide(perception);
+        }
+        self.action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::GameConfig, mutators::Effects, settings::Settings, world::Arena};
+
+    /// Everything a [`Perception`] borrows, with the ship near the bottom of the arena.
+    struct Scene {
+        config: GameConfig,
+        settings: Settings,
+        arena: Arena,
+        keyboard: Input<KeyCode>,
+        effects: Effects,
+    }
+
+    impl Scene {
+        fn new() -> Self {
+            let config = GameConfig::default();
+            let arena = Arena::fit(&config, config.camera.view);
+            Self {
+                config,
+                settings: Settings::default(),
+                arena,
+                keyboard: Input::default(),
+                effects: Effects::default(),
+            }
+        }
+
+        fn perception<'a>(&'a self, cuboids: &'a [CuboidState], delta: f32) -> Perception<'a> {
+            Perception {
+                ship: ShipState {
+                    position: Vec2::new(0.0, -80.0),
+                    ..Default::default()
+                },
+                cuboids,
+                arena: &self.arena,
+                config: &self.config,
+                settings: &self.settings,
+                keyboard: &self.keyboard,
+                effects: &self.effects,
+                delta,
+            }
+        }
+    }
+
+    fn cuboid(position: Vec2, velocity: Vec2) -> CuboidState {
+        CuboidState {
+            position,
+            velocity,
+            size: 4,
+        }
+    }
+
+    fn bot(scene: &Scene) -> BotController {
+        BotController::new(scene.config.bot.clone())
+    }
+
+    #[test]
+    fn cuboids_on_a_collision_course_are_dodged() {
+        let scene = Scene::new();
+        let cuboids = [cuboid(Vec2::new(0.0, -40.0), Vec2::new(0.0, -30.0))];
+        let perception = scene.perception(&cuboids, 0.0);
+        let bot = bot(&scene);
+        let nearby = cuboids.iter().collect::<Vec<_>>();
+
+        assert!(bot.danger(&perception, &nearby, 0.0) > 0.0);
+        assert_eq!(bot.danger(&perception, &nearby, -1.0), 0.0);
+        assert_eq!(bot.danger(&perception, &nearby, 1.0), 0.0);
+        assert_ne!(bot.decide(&perception).direction, 0.0);
+    }
+
+    #[test]
+    fn cuboids_flying_away_are_no_danger() {
+        let scene = Scene::new();
+        let cuboids = [cuboid(Vec2::new(0.0, -40.0), Vec2::new(0.0, 30.0))];
+        let perception = scene.perception(&cuboids, 0.0);
+        let nearby = cuboids.iter().collect::<Vec<_>>();
+        assert_eq!(bot(&scene).danger(&perception, &nearby, 0.0), 0.0);
+    }
+
+    #[test]
+    fn fires_when_aligned() {
+        let scene = Scene::new();
+        let bot = bot(&scene);
+
+        let above = [cuboid(Vec2::new(0.0, 20.0), Vec2::ZERO)];
+        let action = bot.decide(&scene.perception(&above, 0.0));
+        assert_eq!(
+            action,
+            ShipAction {
+                direction: 0.0,
+                fire: true
+            }
+        );
+
+        // Bullet meets the moving cuboid in front of the ship
+        let crossing = [cuboid(Vec2::new(-30.0, 40.0), Vec2::new(30.0, 0.0))];
+        let nearby = crossing.iter().collect::<Vec<_>>();
+        let (offset, extent) = bot.aim(&scene.perception(&crossing, 0.0), &nearby).unwrap();
+        assert!(offset.abs() < extent);
+        assert!(bot.decide(&scene.perception(&crossing, 0.0)).fire);
+    }
+
+    #[test]
+    fn moves_under_targets_before_firing() {
+        let scene = Scene::new();
+        let aside = [cuboid(Vec2::new(30.0, 20.0), Vec2::ZERO)];
+        let action = bot(&scene).decide(&scene.perception(&aside, 0.0));
+        assert_eq!(
+            action,
+            ShipAction {
+                direction: 1.0,
+                fire: false
+            }
+        );
+
+        let below = [cuboid(Vec2::new(0.0, -95.0), Vec2::ZERO)];
+        assert!(bot(&scene)
+            .aim(&scene.perception(&below, 0.0), &[&below[0]])
+            .is_none());
+    }
+
+    #[test]
+    fn actions_are_repeated_until_the_reaction_time() {
+        let scene = Scene::new();
+        let mut bot = bot(&scene);
+        let aside = [cuboid(Vec2::new(30.0, 20.0), Vec2::ZERO)];
+        let moving = ShipAction {
+            direction: 1.0,
+            fire: false,
+        };
+
+        assert_eq!(bot.act(&scene.perception(&aside, 0.05)), moving);
+        // Target is gone, but the bot has not reacted yet
+        assert_eq!(bot.act(&scene.perception(&[], 0.04)), moving);
+        assert_eq!(bot.act(&scene.perception(&[], 0.1)), ShipAction::default());
+    }
+}
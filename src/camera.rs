@@ -0,0 +1,221 @@
+use bevy::prelude::*;
+
+use super::{
+    config::GameConfig,
+    cuboids::CuboidDestroyed,
+    settings::Settings,
+    ship::{Ship, ShipHit, ShipSystem},
+    state::GameState,
+    weapon::WeaponSystem,
+    world::{Arena, ArenaSystem},
+};
+
+#[derive(Debug, Clone)]
+pub struct CameraConfig {
+    /// Size of the area around the focus, which is always visible.
+    ///
+    /// Its aspect ratio is the one of the arena, see [`crate::config::ArenaFit`].
+    pub view: Vec2,
+    /// Follows the ship instead of looking at the arena's center.
+    pub follow: bool,
+    /// Bounds of the followed area, the camera does not show anything outside of them.
+    pub bounds: Rect<f32>,
+    /// How fast zoom and follow catch up with their targets, in 1/seconds.
+    pub smoothing: f32,
+    /// Offset of the shaking camera with the full trauma.
+    pub max_shake_offset: f32,
+    /// Roll in radians of the shaking camera with the full trauma.
+    pub max_shake_roll: f32,
+    /// Trauma, which is removed every second.
+    pub trauma_decay: f32,
+    /// Speed of the shaking.
+    pub shake_frequency: f32,
+    pub hit_trauma: f32,
+    pub explosion_trauma: f32,
+    /// Trauma of destroying the cuboid of size 1.
+    pub break_trauma_per_size: f32,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            view: Vec2::new(220.0, 124.0),
+            follow: false,
+            bounds: Rect {
+                left: -110.0,
+                right: 110.0,
+                bottom: -100.0,
+                top: 100.0,
+            },
+            smoothing: 4.0,
+            max_shake_offset: 3.0,
+            max_shake_roll: 0.05,
+            trauma_decay: 1.2,
+            shake_frequency: 15.0,
+            hit_trauma: 0.5,
+            explosion_trauma: 1.0,
+            break_trauma_per_size: 0.03,
+        }
+    }
+}
+
+/// Moves the camera: zooms it to fit the view to the window, follows the ship and shakes it.
+#[derive(Debug, Default, Component)]
+pub struct CameraController {
+    /// Amount of the shaking in range `0.0..=1.0`.
+    pub trauma: f32,
+    /// Point of the gameplay plane in the center of the view.
+    focus: Vec2,
+    /// Distance of the camera from the gameplay plane, `None` until the first update.
+    distance: Option<f32>,
+}
+
+impl CameraController {
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).min(1.0);
+    }
+}
+
+/// Distance, at which the perspective camera sees the whole `view`.
+fn fit_distance(view: Vec2, projection: &PerspectiveProjection) -> f32 {
+    let tan = (0.5 * projection.fov).tan();
+    let vertical = 0.5 * view.y / tan;
+    let horizontal = 0.5 * view.x / (tan * projection.aspect_ratio.max(f32::EPSILON));
+    vertical.max(horizontal)
+}
+
+/// Smooth noise in range `-1.0..=1.0`, different for every `seed`.
+fn noise(time: f32, seed: f32) -> f32 {
+    let t = time + 17.0 * seed;
+    (t.sin() + 0.6 * (2.3 * t + seed).sin() + 0.3 * (5.1 * t).cos()) / 1.9
+}
+
+fn add_trauma(
+    config: Res<GameConfig>,
+    mut hits: EventReader<ShipHit>,
+    mut destroyed: EventReader<CuboidDestroyed>,
+    mut cameras: Query<&mut CameraController>,
+) {
+    let config = &config.camera;
+    let trauma = hits
+        .iter()
+        .map(|hit| {
+            if hit.lives == 0 {
+                config.explosion_trauma
+            } else {
+                config.hit_trauma
+            }
+        })
+        .chain(
+            destroyed
+                .iter()
+                .map(|event| config.break_trauma_per_size * event.size as f32),
+        )
+        .sum::<f32>();
+    if trauma <= 0.0 {
+        return;
+    }
+    for mut camera in cameras.iter_mut() {
+        camera.add_trauma(trauma);
+    }
+}
+
+fn update_camera(
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    arena: Res<Arena>,
+    ships: Query<&Transform, (With<Ship>, Without<CameraController>)>,
+    mut cameras: Query<(
+        &mut CameraController,
+        &PerspectiveProjection,
+        &mut Transform,
+    )>,
+) {
+    let camera_config = &config.camera;
+    let reduced_motion = settings.accessibility.reduced_motion;
+    let delta = time.delta_seconds();
+    // Exponential smoothing independent of the frame rate
+    let blend = 1.0 - (-camera_config.smoothing * delta).exp();
+
+    for (mut camera, projection, mut transform) in cameras.iter_mut() {
+        let target_distance = fit_distance(arena.view, projection);
+        let distance = match camera.distance {
+            Some(distance) if !reduced_motion => distance + (target_distance - distance) * blend,
+            _ => target_distance,
+        };
+        camera.distance = Some(distance);
+
+        let target_focus = match ships.iter().next() {
+            Some(ship) if camera_config.follow => {
+                // Keep the visible area inside the bounds
+                let bounds = &camera_config.bounds;
+                let half = 0.5 * arena.view;
+                let clamp = |value: f32, min: f32, max: f32| {
+                    if min > max {
+                        0.5 * (min + max)
+                    } else {
+                        value.clamp(min, max)
+                    }
+                };
+                Vec2::new(
+                    clamp(
+                        ship.translation.x,
+                        bounds.left + half.x,
+                        bounds.right - half.x,
+                    ),
+                    clamp(
+                        ship.translation.y,
+                        bounds.bottom + half.y,
+                        bounds.top - half.y,
+                    ),
+                )
+            }
+            _ => Vec2::ZERO,
+        };
+        let focus = camera.focus + (target_focus - camera.focus) * blend;
+        camera.focus = focus;
+
+        camera.trauma = (camera.trauma - camera_config.trauma_decay * delta).max(0.0);
+        let shake = if reduced_motion {
+            0.0
+        } else {
+            camera.trauma * camera.trauma
+        };
+        let t = time.seconds_since_startup() as f32 * camera_config.shake_frequency;
+        let offset =
+            camera_config.max_shake_offset * shake * Vec2::new(noise(t, 1.0), noise(t, 2.0));
+        let roll = camera_config.max_shake_roll * shake * noise(t, 3.0);
+
+        // Configured camera position is the offset of the camera from its focus
+        let position = config.world.camera_position;
+        let target = (focus + offset).extend(0.0);
+        *transform =
+            Transform::from_translation(target + Vec3::new(position.x, position.y, distance))
+                .looking_at(target, Vec3::Y);
+        transform.rotate(Quat::from_rotation_z(roll));
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct CameraSystem;
+
+/// Drives cameras with the [`CameraController`].
+#[derive(Default)]
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<Settings>()
+            .init_resource::<Arena>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .after(ShipSystem)
+                    .after(WeaponSystem)
+                    .before(CameraSystem)
+                    .with_system(add_trauma),
+            )
+            .add_system(update_camera.label(CameraSystem).after(ArenaSystem));
+    }
+}
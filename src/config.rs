@@ -0,0 +1,298 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{ops::Range, path::PathBuf};
+
+pub use super::achievements::AchievementsConfig;
+pub use super::attract::AttractConfig;
+pub use super::background::{BackgroundConfig, StarLayerConfig};
+pub use super::bot::BotConfig;
+pub use super::camera::CameraConfig;
+pub use super::mode::ModesConfig;
+pub use super::mutators::MutatorsConfig;
+pub use super::particles::{EmitterConfig, ParticlesConfig};
+pub use super::perf::PerfConfig;
+pub use super::shapes::CuboidShapeConfig;
+pub use super::sound::SoundsConfig;
+pub use super::world::ArenaFit;
+
+/// Settings of a single cuboids spawner placed in the world.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpawnerConfig {
+    /// Position of the spawner in world units.
+    pub position: Vec2,
+    /// Seconds between spawned cuboids.
+    pub cooldown: f32,
+    /// At which angles (in degrees) cuboids will be spawned.
+    pub angle_range: Option<Range<u16>>,
+    /// Possible cuboid's sizes to generate.
+    pub size_range: Option<Range<u8>>,
+    /// Possible velocity vector's lengths.
+    pub speed_range: Option<Range<u8>>,
+}
+
+impl SpawnerConfig {
+    pub fn new(position: Vec2, cooldown: f32) -> Self {
+        Self {
+            position,
+            cooldown,
+            angle_range: None,
+            size_range: None,
+            speed_range: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ShipConfig {
+    /// Edge length of the ship's cube.
+    pub size: f32,
+    /// Horizontal speed of the ship.
+    pub speed: f32,
+    /// Angular velocity of the ship, when moving at full speed.
+    pub spin: f32,
+    /// Lives at the beginning of the game.
+    pub lives: u8,
+    /// Starting position of the ship.
+    pub position: Vec2,
+    pub restitution: f32,
+}
+
+impl Default for ShipConfig {
+    fn default() -> Self {
+        Self {
+            size: 8.0,
+            speed: 25.0,
+            spin: 5.0,
+            lives: 3,
+            position: Vec2::new(0.0, -50.0),
+            restitution: 1.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WeaponConfig {
+    /// Seconds between shots.
+    pub cooldown: f32,
+    pub bullet_speed: f32,
+    /// Edge length of the bullet's cube.
+    pub bullet_size: f32,
+    /// Released bullets kept for reuse, the rest is despawned.
+    pub pool_size: usize,
+}
+
+impl Default for WeaponConfig {
+    fn default() -> Self {
+        Self {
+            cooldown: 0.25,
+            bullet_speed: 120.0,
+            bullet_size: 1.0,
+            pool_size: 64,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CuboidsConfig {
+    /// Edge length of the cuboid of size 1.
+    pub mesh_size: f32,
+    /// Sizes, for which meshes and materials are generated.
+    pub sizes: Range<u8>,
+    /// Irregularity of the generated meshes and colliders.
+    pub shape: CuboidShapeConfig,
+    pub restitution: f32,
+    /// Points for shooting down the cuboid of size 1.
+    pub points_per_size: u32,
+    /// Seconds, after which the next wave begins.
+    pub wave_duration: f32,
+    /// Released cuboids kept for reuse, the rest is despawned.
+    pub pool_size: usize,
+}
+
+impl Default for CuboidsConfig {
+    fn default() -> Self {
+        Self {
+            mesh_size: 3.75,
+            sizes: 1..10,
+            shape: CuboidShapeConfig::default(),
+            restitution: 1.5,
+            points_per_size: 10,
+            wave_duration: 30.0,
+            pool_size: 512,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WorldConfig {
+    pub camera_position: Vec3,
+    pub light_position: Vec3,
+    /// Distance of the left and right walls from the arena's center.
+    pub wall_position: f32,
+    /// Distance of the top and bottom traps from the arena's center.
+    pub trap_position: f32,
+    /// How the arena adapts to the window's aspect ratio.
+    pub arena_fit: ArenaFit,
+    /// Spawners of the default level, see [`crate::level::Level`].
+    pub spawners: Vec<SpawnerConfig>,
+    pub background: BackgroundConfig,
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        let spawner = |x, cooldown| SpawnerConfig {
+            angle_range: Some(240..300),
+            ..SpawnerConfig::new(Vec2::new(x, 70.0), cooldown)
+        };
+        Self {
+            camera_position: Vec3::new(0.0, 1.0, 150.0),
+            light_position: Vec3::new(4.0, -4.0, 50.0),
+            wall_position: 110.0,
+            trap_position: 100.0,
+            arena_fit: ArenaFit::default(),
+            spawners: vec![spawner(-75.0, 3.13), spawner(0.0, 1.5), spawner(75.0, 2.79)],
+            background: BackgroundConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UiConfig {
+    /// Path of the font, relative to the assets directory.
+    pub font: String,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            font: "galaxy-monkey/galax___.ttf".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StorageConfig {
+    /// Directory for the high scores and other saved data. Nothing is saved, when `None`.
+    pub directory: Option<PathBuf>,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        let home = std::env::var_os("HOME").or_else(|| std::env::var_os("APPDATA"));
+        Self {
+            directory: home.map(|home| PathBuf::from(home).join(".rscuboids")),
+        }
+    }
+}
+
+/// Tunables of the game, read by all plugins from [`crate::GamePlugins`].
+///
+/// Plugins fall back to the default configuration when they are used
+/// without [`ConfigPlugin`].
+#[derive(Debug, Clone, Default)]
+pub struct GameConfig {
+    pub ship: ShipConfig,
+    pub weapon: WeaponConfig,
+    pub cuboids: CuboidsConfig,
+    pub world: WorldConfig,
+    pub camera: CameraConfig,
+    pub ui: UiConfig,
+    pub sounds: SoundsConfig,
+    pub particles: ParticlesConfig,
+    pub perf: PerfConfig,
+    pub bot: BotConfig,
+    pub attract: AttractConfig,
+    pub modes: ModesConfig,
+    pub mutators: MutatorsConfig,
+    pub achievements: AchievementsConfig,
+    pub storage: StorageConfig,
+}
+
+impl GameConfig {
+    pub fn with_ship(mut self, ship: ShipConfig) -> Self {
+        self.ship = ship;
+        self
+    }
+
+    pub fn with_weapon(mut self, weapon: WeaponConfig) -> Self {
+        self.weapon = weapon;
+        self
+    }
+
+    pub fn with_cuboids(mut self, cuboids: CuboidsConfig) -> Self {
+        self.cuboids = cuboids;
+        self
+    }
+
+    pub fn with_world(mut self, world: WorldConfig) -> Self {
+        self.world = world;
+        self
+    }
+
+    pub fn with_camera(mut self, camera: CameraConfig) -> Self {
+        self.camera = camera;
+        self
+    }
+
+    pub fn with_ui(mut self, ui: UiConfig) -> Self {
+        self.ui = ui;
+        self
+    }
+
+    pub fn with_sounds(mut self, sounds: SoundsConfig) -> Self {
+        self.sounds = sounds;
+        self
+    }
+
+    pub fn with_particles(mut self, particles: ParticlesConfig) -> Self {
+        self.particles = particles;
+        self
+    }
+
+    pub fn with_perf(mut self, perf: PerfConfig) -> Self {
+        self.perf = perf;
+        self
+    }
+
+    pub fn with_bot(mut self, bot: BotConfig) -> Self {
+        self.bot = bot;
+        self
+    }
+
+    pub fn with_attract(mut self, attract: AttractConfig) -> Self {
+        self.attract = attract;
+        self
+    }
+
+    pub fn with_modes(mut self, modes: ModesConfig) -> Self {
+        self.modes = modes;
+        self
+    }
+
+    pub fn with_mutators(mut self, mutators: MutatorsConfig) -> Self {
+        self.mutators = mutators;
+        self
+    }
+
+    pub fn with_achievements(mut self, achievements: AchievementsConfig) -> Self {
+        self.achievements = achievements;
+        self
+    }
+
+    pub fn with_storage(mut self, storage: StorageConfig) -> Self {
+        self.storage = storage;
+        self
+    }
+}
+
+/// Inserts [`GameConfig`] resource read by the other plugins.
+#[derive(Default)]
+pub struct ConfigPlugin {
+    pub config: GameConfig,
+}
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.config.clone());
+    }
+}
@@ -0,0 +1,417 @@
+use bevy::{input::InputSystem, prelude::*, window::ReceivedCharacter};
+use std::{collections::BTreeMap, str::FromStr};
+
+use super::{
+    config::GameConfig,
+    menu::{MenuStyle, ScaledText},
+    settings::Settings,
+    theme::Theme,
+};
+
+/// Key opening and closing the console.
+const TOGGLE_KEY: KeyCode = KeyCode::Grave;
+const CONSOLE_FONT_SIZE: f32 = 16.0;
+/// Lines of the output kept in the log.
+const MAX_LOG_LINES: usize = 100;
+/// Lines of the log shown above the input.
+const VISIBLE_LOG_LINES: usize = 14;
+
+/// Registered command, shown by `help` and used for autocompletion.
+#[derive(Debug, Clone)]
+pub struct CommandInfo {
+    pub usage: &'static str,
+    pub help: &'static str,
+}
+
+/// Commands registered by the plugins with [`ConsoleAppExt::add_console_command`].
+#[derive(Debug, Default)]
+pub struct ConsoleCommands {
+    commands: BTreeMap<&'static str, CommandInfo>,
+}
+
+impl ConsoleCommands {
+    /// Names of the commands starting with `prefix`, in alphabetical order.
+    pub fn matching<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'static str> + 'a {
+        self.commands
+            .keys()
+            .copied()
+            .filter(move |name| name.starts_with(prefix))
+    }
+}
+
+/// Command entered in the console, handled by the plugin, which registered it.
+#[derive(Debug, Clone)]
+pub struct ConsoleCommand {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+impl ConsoleCommand {
+    /// Parses the argument at `index`, `None` if it is missing or invalid.
+    pub fn arg<T: FromStr>(&self, index: usize) -> Option<T> {
+        self.args.get(index)?.parse().ok()
+    }
+}
+
+/// Line printed in the console, e.g. a command's result or error.
+#[derive(Debug, Clone)]
+pub struct ConsoleOutput(pub String);
+
+impl ConsoleOutput {
+    pub fn usage(commands: &ConsoleCommands, name: &str) -> Self {
+        match commands.commands.get(name) {
+            Some(info) => Self(format!("Usage: {} {}", name, info.usage)),
+            None => Self(format!("Unknown command {:?}", name)),
+        }
+    }
+}
+
+pub trait ConsoleAppExt {
+    /// Registers `name`, which is then sent as [`ConsoleCommand`] event.
+    fn add_console_command(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        help: &'static str,
+    ) -> &mut Self;
+}
+
+impl ConsoleAppExt for App {
+    fn add_console_command(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        help: &'static str,
+    ) -> &mut Self {
+        // Events registered twice would be cleared twice a frame
+        if self
+            .world
+            .get_resource::<Events<ConsoleCommand>>()
+            .is_none()
+        {
+            self.add_event::<ConsoleCommand>()
+                .add_event::<ConsoleOutput>();
+        }
+        self.init_resource::<ConsoleCommands>();
+        self.world
+            .get_resource_mut::<ConsoleCommands>()
+            .expect("Commands are initialized")
+            .commands
+            .insert(name, CommandInfo { usage, help });
+        self
+    }
+}
+
+/// State of the drop-down console.
+#[derive(Debug, Default)]
+pub struct Console {
+    pub open: bool,
+    input: String,
+    log: Vec<String>,
+    history: Vec<String>,
+    /// Position in the history, while browsing it.
+    history_index: Option<usize>,
+}
+
+impl Console {
+    fn print(&mut self, line: String) {
+        self.log.extend(line.lines().map(str::to_string));
+        let overflow = self.log.len().saturating_sub(MAX_LOG_LINES);
+        self.log.drain(..overflow);
+    }
+
+    /// Completes the command's name to the longest prefix shared by all matching commands.
+    fn complete(&mut self, commands: &ConsoleCommands) {
+        if self.input.contains(' ') {
+            return;
+        }
+        let matching = commands.matching(&self.input).collect::<Vec<_>>();
+        let first = match matching.first() {
+            Some(first) => *first,
+            None => return,
+        };
+        let common = matching.iter().fold(first.len(), |length, name| {
+            first
+                .chars()
+                .zip(name.chars())
+                .take(length)
+                .take_while(|(a, b)| a == b)
+                .count()
+        });
+        if matching.len() == 1 {
+            self.input = format!("{} ", first);
+        } else if common > self.input.len() {
+            self.input = first[..common].to_string();
+        } else {
+            self.print(matching.join("  "));
+        }
+    }
+
+    fn browse_history(&mut self, older: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+        let last = self.history.len() - 1;
+        self.history_index = match (self.history_index, older) {
+            (None, true) => Some(last),
+            (None, false) => None,
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) if index < last => Some(index + 1),
+            (Some(_), false) => None,
+        };
+        self.input = self
+            .history_index
+            .map(|index| self.history[index].clone())
+            .unwrap_or_default();
+    }
+}
+
+#[derive(Debug, Component)]
+struct ConsoleRoot;
+
+#[derive(Debug, Component)]
+struct ConsoleText;
+
+fn setup_console(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+) {
+    let style = MenuStyle::new(&asset_server, &config, &settings, &theme);
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                display: Display::None,
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(0.0),
+                    top: Val::Px(0.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            color: UiColor(style.overlay_color),
+            ..Default::default()
+        })
+        .insert(ConsoleRoot)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(style.text("", CONSOLE_FONT_SIZE, style.text_color))
+                .insert(ScaledText {
+                    size: CONSOLE_FONT_SIZE,
+                })
+                .insert(ConsoleText);
+        });
+}
+
+/// Types into the open console and hides the keys from the rest of the game.
+fn console_input(
+    mut console: ResMut<Console>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    registered: Res<ConsoleCommands>,
+    mut entered: EventWriter<ConsoleCommand>,
+) {
+    if keyboard_input.just_pressed(TOGGLE_KEY) {
+        console.open = !console.open;
+    }
+    if !console.open {
+        // Drop characters typed into the game
+        characters.iter().last();
+        return;
+    }
+
+    for character in characters.iter() {
+        if !character.char.is_control() && !matches!(character.char, '`' | '~') {
+            console.input.push(character.char);
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        console.input.pop();
+    }
+    if keyboard_input.just_pressed(KeyCode::Tab) {
+        console.complete(&registered);
+    }
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        console.browse_history(true);
+    } else if keyboard_input.just_pressed(KeyCode::Down) {
+        console.browse_history(false);
+    }
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        let line = std::mem::take(&mut console.input);
+        console.history_index = None;
+        console.print(format!("> {}", line));
+        let mut words = line.split_whitespace().map(str::to_string);
+        if let Some(name) = words.next() {
+            if console.history.last() != Some(&line) {
+                console.history.push(line.clone());
+            }
+            if name == "help" {
+                for (name, info) in registered.commands.iter() {
+                    console.print(format!("{} {} - {}", name, info.usage, info.help));
+                }
+            } else if registered.commands.contains_key(name.as_str()) {
+                entered.send(ConsoleCommand {
+                    name,
+                    args: words.collect(),
+                });
+            } else {
+                console.print(format!("Unknown command {:?}, try help", name));
+            }
+        }
+    }
+
+    // Keys typed into the console do not control the game
+    let pressed = keyboard_input.get_pressed().copied().collect::<Vec<_>>();
+    for key in pressed {
+        keyboard_input.reset(key);
+    }
+    keyboard_input.clear();
+}
+
+fn print_output(mut console: ResMut<Console>, mut output: EventReader<ConsoleOutput>) {
+    for line in output.iter() {
+        console.print(line.0.clone());
+    }
+}
+
+fn update_console(
+    console: Res<Console>,
+    mut roots: Query<&mut Style, With<ConsoleRoot>>,
+    mut texts: Query<&mut Text, With<ConsoleText>>,
+) {
+    if !console.is_changed() {
+        return;
+    }
+    for mut style in roots.iter_mut() {
+        style.display = if console.open {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+    let skipped = console.log.len().saturating_sub(VISIBLE_LOG_LINES);
+    let mut lines = console.log[skipped..].to_vec();
+    lines.push(format!("> {}_", console.input));
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+struct ConsoleOutputSystem;
+
+/// Drop-down developer console toggled with the backquote key.
+///
+/// Plugins register their commands with [`ConsoleAppExt::add_console_command`],
+/// handle [`ConsoleCommand`] events and report back with [`ConsoleOutput`].
+#[derive(Default)]
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<Settings>()
+            .init_resource::<Theme>()
+            .init_resource::<Console>()
+            .add_console_command("help", "", "Lists all commands")
+            .add_startup_system(setup_console)
+            .add_system_to_stage(CoreStage::PreUpdate, console_input.after(InputSystem))
+            .add_system(print_output.label(ConsoleOutputSystem))
+            .add_system(update_console.after(ConsoleOutputSystem));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(names: &[&'static str]) -> ConsoleCommands {
+        ConsoleCommands {
+            commands: names
+                .iter()
+                .map(|name| {
+                    (
+                        *name,
+                        CommandInfo {
+                            usage: "",
+                            help: "",
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn console(input: &str) -> Console {
+        Console {
+            input: input.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn single_match_is_completed_with_a_space() {
+        let mut console = console("de");
+        console.complete(&commands(&["daily", "debug", "seed"]));
+        assert_eq!(console.input, "debug ");
+    }
+
+    #[test]
+    fn shared_prefix_is_completed_and_then_listed() {
+        let commands = commands(&["seed", "spawn_cuboid", "spawner"]);
+        let mut console = console("sp");
+        console.complete(&commands);
+        assert_eq!(console.input, "spawn");
+        assert!(console.log.is_empty());
+
+        console.complete(&commands);
+        assert_eq!(console.input, "spawn");
+        assert_eq!(console.log, vec!["spawn_cuboid  spawner".to_string()]);
+    }
+
+    #[test]
+    fn arguments_and_unknown_commands_are_not_completed() {
+        let commands = commands(&["debug", "seed"]);
+        let mut console = console("seed 4");
+        console.complete(&commands);
+        assert_eq!(console.input, "seed 4");
+
+        let mut console = console("x");
+        console.complete(&commands);
+        assert_eq!(console.input, "x");
+    }
+
+    #[test]
+    fn history_is_browsed_back_to_the_empty_input() {
+        let mut console = Console {
+            history: vec!["a".to_string(), "b".to_string()],
+            ..Default::default()
+        };
+        console.browse_history(true);
+        assert_eq!(console.input, "b");
+        console.browse_history(true);
+        assert_eq!(console.input, "a");
+        console.browse_history(true);
+        assert_eq!(console.input, "a");
+        console.browse_history(false);
+        assert_eq!(console.input, "b");
+        console.browse_history(false);
+        assert_eq!(console.input, "");
+        assert_eq!(console.history_index, None);
+    }
+
+    #[test]
+    fn log_keeps_the_last_lines() {
+        let mut console = Console::default();
+        for line in 0..MAX_LOG_LINES + 5 {
+            console.print(line.to_string());
+        }
+        assert_eq!(console.log.len(), MAX_LOG_LINES);
+        assert_eq!(console.log.first(), Some(&"5".to_string()));
+    }
+}
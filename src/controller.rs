@@ -0,0 +1,373 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::{
+    bot::BotController,
+    config::GameConfig,
+    console::{ConsoleAppExt, ConsoleCommand, ConsoleOutput},
+    cuboids::Cuboid,
+    mutators::{Effects, RunEffects},
+    settings::{Assist, Settings, SimulationSpeed},
+    ship::{Ship, ShipSystem},
+    state::GameState,
+    weapon::WeaponSystem,
+    world::Arena,
+};
+
+const FIRE_KEYS: [KeyCode; 1] = [KeyCode::Space];
+/// Key reversing the ship in the one button assist.
+const ONE_BUTTON_KEY: KeyCode = KeyCode::Return;
+
+/// What the ship does in the current frame, chosen by its [`Pilot`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Component)]
+pub struct ShipAction {
+    /// Horizontal movement between -1 (left) and 1 (right).
+    pub direction: f32,
+    /// Fires a bullet, when the weapon is ready.
+    pub fire: bool,
+}
+
+fn to_vec(vector: &Vector<Real>) -> Vec2 {
+    Vec2::new(vector.x, vector.y)
+}
+
+/// Ship, as seen by its controller.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShipState {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub lives: u8,
+}
+
+impl ShipState {
+    pub fn new(
+        ship: &Ship,
+        position: &RigidBodyPositionComponent,
+        velocity: &RigidBodyVelocityComponent,
+    ) -> Self {
+        Self {
+            position: to_vec(&position.0.position.translation.vector),
+            velocity: to_vec(&velocity.0.linvel),
+            lives: ship.lives,
+        }
+    }
+}
+
+/// Cuboid, as seen by the controllers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CuboidState {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub size: u8,
+}
+
+impl CuboidState {
+    pub fn new(
+        cuboid: &Cuboid,
+        position: &RigidBodyPositionComponent,
+        velocity: &RigidBodyVelocityComponent,
+    ) -> Self {
+        Self {
+            position: to_vec(&position.0.position.translation.vector),
+            velocity: to_vec(&velocity.0.linvel),
+            size: cuboid.size,
+        }
+    }
+}
+
+/// Everything a controller can base its decision on.
+pub struct Perception<'a> {
+    pub ship: ShipState,
+    pub cuboids: &'a [CuboidState],
+    pub arena: &'a Arena,
+    pub config: &'a GameConfig,
+    pub settings: &'a Settings,
+    pub keyboard: &'a Input<KeyCode>,
+    pub effects: &'a Effects,
+    /// Seconds of game time since the last decision.
+    pub delta: f32,
+}
+
+/// Decides, how a ship moves and shoots.
+///
+/// The player's [`HumanController`] reads the keyboard, [`BotController`] plays on its own.
+pub trait ShipController: Send + Sync + 'static {
+    fn act(&mut self, perception: &Perception) -> ShipAction;
+}
+
+/// Controls the ship with the keys from the settings, including the assists.
+#[derive(Debug, Default)]
+pub struct HumanController {
+    one_button_direction: f32,
+}
+
+impl ShipController for HumanController {
+    fn act(&mut self, perception: &Perception) -> ShipAction {
+        let keyboard = perception.keyboard;
+        let settings = perception.settings;
+        let pressed = |keys: &[KeyCode]| keys.iter().any(|k| keyboard.pressed(*k));
+        let (left, right) = settings.controls.keys();
+
+        let direction = if settings.accessibility.assist == Assist::OneButton {
+            // Ship keeps moving and turns around at the walls or when the button is pressed
+            if self.one_button_direction == 0.0 {
+                self.one_button_direction = 1.0;
+            }
+            let limit = perception.arena.half_size.x - perception.config.ship.size;
+            let at_wall = perception.ship.position.x * self.one_button_direction > limit;
+            if keyboard.just_pressed(ONE_BUTTON_KEY) || at_wall {
+                self.one_button_direction = -self.one_button_direction;
+            }
+            self.one_button_direction
+        } else if pressed(left) {
+            -1.0
+        } else if pressed(right) {
+            1.0
+        } else {
+            0.0
+        };
+
+        let direction = if perception.effects.mirror_controls {
+            -direction
+        } else {
+            direction
+        };
+        ShipAction {
+            direction,
+            fire: settings.accessibility.assist.auto_fire() || pressed(&FIRE_KEYS),
+        }
+    }
+}
+
+/// Controller of the ship.
+#[derive(Component)]
+pub struct Pilot(pub Box<dyn ShipController>);
+
+impl Pilot {
+    pub fn human() -> Self {
+        Self(Box::new(HumanController::default()))
+    }
+
+    pub fn bot(config: &GameConfig) -> Self {
+        Self(Box::new(BotController::new(config.bot.clone())))
+    }
+}
+
+/// New ships are flown by the bot instead of the player, e.g. in demos and soak tests.
+#[derive(Debug, Default)]
+pub struct Autopilot(pub bool);
+
+fn assign_pilots(
+    mut commands: Commands,
+    config: Res<GameConfig>,
+    autopilot: Res<Autopilot>,
+    ships: Query<Entity, (With<Ship>, Without<Pilot>)>,
+) {
+    for entity in ships.iter() {
+        let pilot = if autopilot.0 {
+            Pilot::bot(&config)
+        } else {
+            Pilot::human()
+        };
+        commands.entity(entity).insert(pilot);
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn control_ships(
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    speed: Res<SimulationSpeed>,
+    arena: Res<Arena>,
+    run_effects: Res<RunEffects>,
+    cuboids: Query<(
+        &Cuboid,
+        &RigidBodyPositionComponent,
+        &RigidBodyVelocityComponent,
+    )>,
+    mut ships: Query<(
+        &Ship,
+        &RigidBodyPositionComponent,
+        &RigidBodyVelocityComponent,
+        &mut Pilot,
+        &mut ShipAction,
+    )>,
+) {
+    let cuboids = cuboids
+        .iter()
+        .map(|(cuboid, position, velocity)| CuboidState::new(cuboid, position, velocity))
+        .collect::<Vec<_>>();
+    let delta = speed.game_delta(&time, &settings).as_secs_f32();
+
+    for (ship, position, velocity, mut pilot, mut action) in ships.iter_mut() {
+        let perception = Perception {
+            ship: ShipState::new(ship, position, velocity),
+            cuboids: &cuboids,
+            arena: &arena,
+            config: &config,
+            settings: &settings,
+            keyboard: &keyboard_input,
+            effects: &run_effects.effects,
+            delta,
+        };
+        *action = pilot.0.act(&perception);
+    }
+}
+
+fn console_commands(
+    mut commands: Commands,
+    mut entered: EventReader<ConsoleCommand>,
+    mut output: EventWriter<ConsoleOutput>,
+    mut autopilot: ResMut<Autopilot>,
+    ships: Query<Entity, With<Ship>>,
+) {
+    for _ in entered.iter().filter(|command| command.name == "autopilot") {
+        autopilot.0 = !autopilot.0;
+        for entity in ships.iter() {
+            commands.entity(entity).remove::<Pilot>();
+        }
+        let state = if autopilot.0 { "on" } else { "off" };
+        output.send(ConsoleOutput(format!("Autopilot {}", state)));
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct ControllerSystem;
+
+/// Lets a [`Pilot`] choose the [`ShipAction`] of every ship before it moves and shoots.
+#[derive(Default)]
+pub struct ControllerPlugin;
+
+impl Plugin for ControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<Settings>()
+            .init_resource::<SimulationSpeed>()
+            .init_resource::<Arena>()
+            .init_resource::<Autopilot>()
+            .init_resource::<RunEffects>()
+            .add_console_command("autopilot", "", "Toggles the bot flying the ship")
+            .add_system(console_commands)
+            .add_system(assign_pilots)
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .label(ControllerSystem)
+                    .before(ShipSystem)
+                    .before(WeaponSystem)
+                    .with_system(control_ships),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::ControlScheme;
+
+    /// Direction chosen by the `controller` for the ship at `x`.
+    fn steer(
+        controller: &mut HumanController,
+        settings: &Settings,
+        keyboard: &Input<KeyCode>,
+        effects: &Effects,
+        x: f32,
+    ) -> f32 {
+        let config = GameConfig::default();
+        let arena = Arena::fit(&config, config.camera.view);
+        let perception = Perception {
+            ship: ShipState {
+                position: Vec2::new(x, -80.0),
+                ..Default::default()
+            },
+            cuboids: &[],
+            arena: &arena,
+            config: &config,
+            settings,
+            keyboard,
+            effects,
+            delta: 0.0,
+        };
+        controller.act(&perception).direction
+    }
+
+    #[test]
+    fn mirrored_controls_swap_left_and_right() {
+        let settings = Settings {
+            controls: ControlScheme::Wasd,
+            ..Default::default()
+        };
+        let mut keyboard = Input::default();
+        keyboard.press(KeyCode::D);
+        let mirrored = Effects {
+            mirror_controls: true,
+            ..Default::default()
+        };
+        let mut controller = HumanController::default();
+
+        let normal = Effects::default();
+        assert_eq!(
+            steer(&mut controller, &settings, &keyboard, &normal, 0.0),
+            1.0
+        );
+        assert_eq!(
+            steer(&mut controller, &settings, &keyboard, &mirrored, 0.0),
+            -1.0
+        );
+        // Keys of other schemes are ignored
+        keyboard.release(KeyCode::D);
+        keyboard.press(KeyCode::Left);
+        assert_eq!(
+            steer(&mut controller, &settings, &keyboard, &normal, 0.0),
+            0.0
+        );
+    }
+
+    #[test]
+    fn one_button_reverses_on_presses_and_at_walls() {
+        let mut settings = Settings::default();
+        settings.accessibility.assist = Assist::OneButton;
+        let effects = Effects::default();
+        let mut keyboard = Input::default();
+        let mut controller = HumanController::default();
+        let mut turn = |keyboard: &Input<KeyCode>, x: f32| {
+            steer(&mut controller, &settings, keyboard, &effects, x)
+        };
+
+        assert_eq!(turn(&keyboard, 0.0), 1.0);
+        keyboard.press(ONE_BUTTON_KEY);
+        assert_eq!(turn(&keyboard, 0.0), -1.0);
+        // Holding the button does not reverse the ship again
+        keyboard.clear();
+        assert_eq!(turn(&keyboard, 0.0), -1.0);
+        assert_eq!(turn(&keyboard, -200.0), 1.0);
+        assert_eq!(turn(&keyboard, 0.0), 1.0);
+    }
+
+    #[test]
+    fn one_button_fires_automatically() {
+        let mut settings = Settings::default();
+        let config = GameConfig::default();
+        let arena = Arena::fit(&config, config.camera.view);
+        let keyboard = Input::default();
+        let effects = Effects::default();
+        let fire = |settings: &Settings| {
+            let perception = Perception {
+                ship: ShipState::default(),
+                cuboids: &[],
+                arena: &arena,
+                config: &config,
+                settings,
+                keyboard: &keyboard,
+                effects: &effects,
+                delta: 0.0,
+            };
+            HumanController::default().act(&perception).fire
+        };
+
+        assert!(!fire(&settings));
+        settings.accessibility.assist = Assist::OneButton;
+        assert!(fire(&settings));
+    }
+}
@@ -1,10 +1,15 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
-use rand::{rngs::StdRng, Rng, SeedableRng};
+use rand::Rng;
 use std::ops::Range;
 
-use super::assets::{Materials, Meshes, CUBOID_MESH_SIZE};
+use super::assets::{CuboidMesh, CuboidShapes, Materials};
+use super::config::{CuboidsConfig, GameConfig, SpawnerConfig};
+use super::console::{ConsoleAppExt, ConsoleCommand, ConsoleCommands, ConsoleOutput};
 use super::physics_layers;
+use super::pool::{EntityPool, PoolSystem, Release};
+use super::settings::{Settings, SimulationSpeed};
+use super::state::{GameState, RunRng};
 
 #[derive(Debug, Component)]
 pub struct Cuboid {
@@ -17,51 +22,137 @@ impl Default for Cuboid {
     }
 }
 
+/// Why the cuboid was destroyed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DestructionCause {
+    Shot,
+    ShipCollision,
+}
+
+/// Sent, when cuboid is destroyed by the player.
+#[derive(Debug, Clone)]
+pub struct CuboidDestroyed {
+    pub size: u8,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub cause: DestructionCause,
+}
+
+impl CuboidDestroyed {
+    pub fn new(
+        cuboid: &Cuboid,
+        position: &RigidBodyPositionComponent,
+        velocity: &RigidBodyVelocityComponent,
+        cause: DestructionCause,
+    ) -> Self {
+        let translation = position.0.position.translation.vector;
+        let linvel = velocity.0.linvel;
+        Self {
+            size: cuboid.size,
+            position: Vec2::new(translation.x, translation.y),
+            velocity: Vec2::new(linvel.x, linvel.y),
+            cause,
+        }
+    }
+}
+
+/// Number of the current wave of cuboids, starting from 1.
+#[derive(Debug)]
+pub struct Wave {
+    pub number: u32,
+    timer: Timer,
+}
+
+impl Default for Wave {
+    fn default() -> Self {
+        Self {
+            number: 1,
+            timer: Timer::default(),
+        }
+    }
+}
+
+fn reset_wave(mut wave: ResMut<Wave>, config: Res<GameConfig>) {
+    *wave = Wave {
+        number: 1,
+        timer: Timer::from_seconds(config.cuboids.wave_duration, true),
+    };
+}
+
+fn wave_system(
+    mut wave: ResMut<Wave>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    speed: Res<SimulationSpeed>,
+) {
+    let delta = speed.game_delta(&time, &settings);
+    if wave.timer.tick(delta).just_finished() {
+        wave.number += 1;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn spawn_cuboid(
     commands: &mut Commands,
-    meshes: &Res<Meshes>,
+    pool: &mut EntityPool<Cuboid>,
     materials: &Res<Materials>,
+    config: &CuboidsConfig,
     size: u8,
+    shape: &CuboidMesh,
     position: Vec2,
     velocity: Vec2,
 ) {
-    let extent = 0.5 * CUBOID_MESH_SIZE * size as f32;
-    let body = RigidBodyBundle {
-        position: position.into(),
-        velocity: RigidBodyVelocity {
-            linvel: velocity.into(),
-            ..Default::default()
-        }
-        .into(),
+    let velocity = RigidBodyVelocity {
+        linvel: velocity.into(),
         ..Default::default()
     };
-    let collider = ColliderBundle {
-        shape: ColliderShape::cuboid(extent, extent).into(),
-        material: ColliderMaterial {
-            restitution: 1.5,
-            ..Default::default()
-        }
-        .into(),
-        flags: ColliderFlags {
-            collision_groups: physics_layers::ALL,
-            ..Default::default()
-        }
-        .into(),
+    let material = ColliderMaterial {
+        restitution: config.restitution,
+        ..Default::default()
+    };
+    let flags = ColliderFlags {
+        collision_groups: physics_layers::ALL,
         ..Default::default()
     };
 
-    commands
-        .spawn()
+    let mut entity = match pool.acquire() {
+        // Bundles would reset the handles of the body and collider in the physics sets
+        Some(entity) => {
+            let mut entity = commands.entity(entity);
+            entity
+                .insert(RigidBodyPositionComponent::from(position))
+                .insert(RigidBodyVelocityComponent(velocity))
+                .insert(RigidBodyActivationComponent(RigidBodyActivation::active()))
+                .insert(ColliderShapeComponent(shape.collider.clone()))
+                .insert(ColliderMaterialComponent(material))
+                .insert(ColliderFlagsComponent(flags))
+                .insert(Visibility { is_visible: true });
+            entity
+        }
+        None => {
+            let mut entity = commands.spawn();
+            entity
+                .insert_bundle(RigidBodyBundle {
+                    position: position.into(),
+                    velocity: velocity.into(),
+                    ..Default::default()
+                })
+                .insert_bundle(ColliderBundle {
+                    shape: shape.collider.clone().into(),
+                    material: material.into(),
+                    flags: flags.into(),
+                    ..Default::default()
+                })
+                .insert_bundle(PbrBundle::default())
+                .insert(RigidBodyPositionSync::Discrete);
+            entity
+        }
+    };
+    entity
         .insert(Cuboid { size })
-        .insert_bundle(body)
-        .insert_bundle(collider)
-        .insert_bundle(PbrBundle {
-            mesh: meshes.cuboid[&size].clone(),
-            material: materials.cuboid[&size].clone(),
-            transform: Transform::from_xyz(position.x, position.y, 0.0),
-            ..Default::default()
-        })
-        .insert(RigidBodyPositionSync::Discrete);
+        .insert(shape.mesh.clone())
+        .insert(materials.cuboid[&size].clone())
+        .insert(Transform::from_xyz(position.x, position.y, 0.0));
 }
 
 #[derive(Debug, Component)]
@@ -90,6 +181,39 @@ impl Spawner {
             speed_range: speed_range.unwrap_or(10..50),
         }
     }
+
+    pub fn cooldown(&self) -> &Timer {
+        &self.cooldown
+    }
+
+    /// Changes seconds between spawned cuboids, keeping the elapsed time.
+    pub fn set_cooldown(&mut self, seconds: f32) {
+        self.cooldown
+            .set_duration(std::time::Duration::from_secs_f32(seconds));
+    }
+
+    pub fn angle_range(&self) -> Range<u16> {
+        self.angle_range.clone()
+    }
+
+    pub fn size_range(&self) -> Range<u8> {
+        self.size_range.clone()
+    }
+
+    pub fn speed_range(&self) -> Range<u8> {
+        self.speed_range.clone()
+    }
+}
+
+impl From<&SpawnerConfig> for Spawner {
+    fn from(config: &SpawnerConfig) -> Self {
+        Self::new(
+            Timer::from_seconds(config.cooldown, true),
+            config.angle_range.clone(),
+            config.size_range.clone(),
+            config.speed_range.clone(),
+        )
+    }
 }
 
 impl Default for Spawner {
@@ -103,22 +227,32 @@ impl Default for Spawner {
     }
 }
 
-fn spawner_system(
+/// Spawns cuboids from the [`Spawner`]s, whose cooldown has elapsed.
+#[allow(clippy::too_many_arguments)]
+pub fn spawner_system(
     mut commands: Commands,
     mut spawners: Query<(&Transform, &mut Spawner)>,
     time: Res<Time>,
-    meshes: Res<Meshes>,
+    mut shapes: ResMut<CuboidShapes>,
+    mut meshes: ResMut<Assets<Mesh>>,
     materials: Res<Materials>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    speed: Res<SimulationSpeed>,
+    mut rng: ResMut<RunRng>,
+    mut pool: ResMut<EntityPool<Cuboid>>,
 ) {
+    let rng = &mut rng.0;
+    let delta = speed.game_delta(&time, &settings);
     for (transform, mut spawner) in spawners.iter_mut() {
         // Advance time in spawner and skip spawning, if time has not elapsed
-        if !spawner.cooldown.tick(time.delta()).just_finished() {
+        if !spawner.cooldown.tick(delta).just_finished() {
             continue;
         }
 
-        // Pick random size and velocity
-        let mut rng = StdRng::from_entropy();
+        // Pick random size, shape and velocity
         let size = rng.gen_range(spawner.size_range.clone());
+        let seed = rng.gen();
         let angle = (rng.gen_range(spawner.angle_range.clone()) as f32).to_radians();
         let speed = rng.gen_range(spawner.speed_range.clone()) as f32;
         let movement_direction = Vec2::new(angle.cos(), angle.sin());
@@ -126,7 +260,82 @@ fn spawner_system(
         let position = Vec2::new(transform.translation.x, transform.translation.y)
             + (size as f32) * movement_direction;
 
-        spawn_cuboid(&mut commands, &meshes, &materials, size, position, velocity);
+        let shape = shapes.get(&mut meshes, &config.cuboids, size, seed);
+        spawn_cuboid(
+            &mut commands,
+            &mut pool,
+            &materials,
+            &config.cuboids,
+            size,
+            shape,
+            position,
+            velocity,
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn console_commands(
+    mut commands: Commands,
+    mut entered: EventReader<ConsoleCommand>,
+    mut output: EventWriter<ConsoleOutput>,
+    registered: Res<ConsoleCommands>,
+    mut shapes: ResMut<CuboidShapes>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    materials: Res<Materials>,
+    config: Res<GameConfig>,
+    mut wave: ResMut<Wave>,
+    mut pool: ResMut<EntityPool<Cuboid>>,
+    mut released: EventWriter<Release>,
+    cuboids: Query<Entity, With<Cuboid>>,
+) {
+    for command in entered.iter() {
+        match command.name.as_str() {
+            "spawn" => {
+                let args = (
+                    command.args.first().map(String::as_str),
+                    command.arg::<u8>(1),
+                    (2..6)
+                        .map(|index| command.arg::<f32>(index))
+                        .collect::<Option<Vec<_>>>(),
+                );
+                match args {
+                    (Some("cuboid"), Some(size), Some(values))
+                        if config.cuboids.sizes.contains(&size) =>
+                    {
+                        // Cuboids spawned from the console share a shape
+                        let shape = shapes.get(&mut meshes, &config.cuboids, size, 0);
+                        spawn_cuboid(
+                            &mut commands,
+                            &mut pool,
+                            &materials,
+                            &config.cuboids,
+                            size,
+                            shape,
+                            Vec2::new(values[0], values[1]),
+                            Vec2::new(values[2], values[3]),
+                        );
+                    }
+                    _ => output.send(ConsoleOutput::usage(&registered, "spawn")),
+                }
+            }
+            "wave" => match command.arg::<u32>(0) {
+                Some(number) if number > 0 => {
+                    wave.number = number;
+                    wave.timer.reset();
+                }
+                _ => output.send(ConsoleOutput::usage(&registered, "wave")),
+            },
+            "clear" => {
+                let mut count = 0;
+                for entity in cuboids.iter() {
+                    released.send(Release(entity));
+                    count += 1;
+                }
+                output.send(ConsoleOutput(format!("Removed {} cuboids", count)));
+            }
+            _ => {}
+        }
     }
 }
 
@@ -135,6 +344,24 @@ pub struct CuboidsPlugin;
 
 impl Plugin for CuboidsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(spawner_system);
+        app.init_resource::<GameConfig>()
+            .init_resource::<SimulationSpeed>()
+            .init_resource::<CuboidShapes>()
+            .init_resource::<Wave>()
+            .add_event::<CuboidDestroyed>()
+            .add_console_command(
+                "spawn",
+                "cuboid <size> <x> <y> <vx> <vy>",
+                "Spawns a cuboid",
+            )
+            .add_console_command("wave", "<n>", "Jumps to the wave")
+            .add_console_command("clear", "", "Removes all cuboids")
+            .add_system(console_commands.before(PoolSystem))
+            .add_system_set(SystemSet::on_enter(GameState::NewRun).with_system(reset_wave))
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(spawner_system.before(PoolSystem))
+                    .with_system(wave_system),
+            );
     }
 }
@@ -0,0 +1,388 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{
+    config::GameConfig,
+    console::{ConsoleAppExt, ConsoleCommand, ConsoleOutput},
+    level::{CurrentLevel, Level},
+    mode::{CurrentMode, GameMode},
+    mutators::Effects,
+    scoring::{RunStats, Score},
+    settings::{Assist, Difficulty, Settings},
+    state::{CurrentRun, GameState, SeedSystem},
+    storage,
+};
+
+/// Modifiers applied in a single daily challenge.
+const MODIFIERS_PER_DAY: usize = 2;
+
+/// Calendar day in UTC, so players in all time zones share the challenge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    pub fn today() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        Self::from_days((seconds / 86400) as i64)
+    }
+
+    /// Date `days` after 1970-01-01, see <http://howardhinnant.github.io/date_algorithms.html>.
+    pub fn from_days(days: i64) -> Self {
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + (month <= 2) as i64;
+        Self {
+            year: year as i32,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Deterministic generator, whose output does not change with the `rand` version.
+fn splitmix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Rule changed for the whole daily challenge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DailyModifier {
+    /// Cuboids fly half again as fast.
+    FastCuboids,
+    /// Spawners spawn more often.
+    FastSpawners,
+    /// Cuboids are one size bigger.
+    BigCuboids,
+    /// Ship has a single life.
+    SingleLife,
+    /// Cuboids leaving the arena on one side come back on the other.
+    WrapArena,
+}
+
+impl DailyModifier {
+    pub const ALL: [DailyModifier; 5] = [
+        DailyModifier::FastCuboids,
+        DailyModifier::FastSpawners,
+        DailyModifier::BigCuboids,
+        DailyModifier::SingleLife,
+        DailyModifier::WrapArena,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DailyModifier::FastCuboids => "Fast cuboids",
+            DailyModifier::FastSpawners => "Fast spawners",
+            DailyModifier::BigCuboids => "Big cuboids",
+            DailyModifier::SingleLife => "Single life",
+            DailyModifier::WrapArena => "Wrap arena",
+        }
+    }
+
+    pub fn effects(&self) -> Effects {
+        let identity = Effects::default();
+        match self {
+            DailyModifier::FastCuboids => Effects {
+                cuboid_speed: 1.5,
+                ..identity
+            },
+            DailyModifier::FastSpawners => Effects {
+                spawn_cooldown: 0.7,
+                ..identity
+            },
+            DailyModifier::BigCuboids => Effects {
+                cuboid_sizes: 1,
+                ..identity
+            },
+            DailyModifier::SingleLife => Effects {
+                max_lives: Some(1),
+                ..identity
+            },
+            DailyModifier::WrapArena => Effects {
+                wrap_arena: true,
+                ..identity
+            },
+        }
+    }
+}
+
+/// Seed and modifiers of the day's challenge, the same for everybody.
+#[derive(Debug, Clone)]
+pub struct DailyChallenge {
+    pub date: Date,
+    pub seed: u64,
+    pub modifiers: Vec<DailyModifier>,
+}
+
+impl Default for DailyChallenge {
+    fn default() -> Self {
+        Self::new(Date::today())
+    }
+}
+
+impl DailyChallenge {
+    pub fn new(date: Date) -> Self {
+        let mut state = date.year as u64 * 10000 + date.month as u64 * 100 + date.day as u64;
+        let seed = splitmix(&mut state);
+        let mut modifiers = DailyModifier::ALL.to_vec();
+        // Partial Fisher-Yates shuffle picks the first modifiers
+        for index in 0..MODIFIERS_PER_DAY {
+            let remaining = (modifiers.len() - index) as u64;
+            let other = index + (splitmix(&mut state) % remaining) as usize;
+            modifiers.swap(index, other);
+        }
+        modifiers.truncate(MODIFIERS_PER_DAY);
+        Self {
+            date,
+            seed,
+            modifiers,
+        }
+    }
+
+    /// Summary of the result, which can be shared with other players.
+    pub fn share(&self, result: &DailyResult) -> String {
+        let modifiers = self
+            .modifiers
+            .iter()
+            .map(DailyModifier::name)
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "rsCuboids Daily {}: {} points, wave {} ({})",
+            self.date, result.score, result.wave, modifiers
+        )
+    }
+}
+
+/// Whether the daily run is played like everybody else's, so its result can be compared.
+///
+/// Other difficulties, slower game speeds and assists make the run practice.
+pub fn is_comparable(settings: &Settings) -> bool {
+    settings.difficulty == Difficulty::Normal
+        && settings.accessibility.game_speed == 1.0
+        && settings.accessibility.assist == Assist::Off
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DailyResult {
+    pub score: u32,
+    pub wave: u32,
+}
+
+/// Scored attempts by their dates.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DailyResults {
+    pub results: BTreeMap<String, DailyResult>,
+}
+
+impl DailyResults {
+    const STORAGE_NAME: &'static str = "daily";
+
+    pub fn get(&self, date: Date) -> Option<&DailyResult> {
+        self.results.get(&date.to_string())
+    }
+
+    pub fn load(config: &GameConfig) -> Self {
+        storage::load(&config.storage, Self::STORAGE_NAME).unwrap_or_default()
+    }
+
+    pub fn save(&self, config: &GameConfig) {
+        storage::save(&config.storage, Self::STORAGE_NAME, self);
+    }
+}
+
+fn load_results(mut commands: Commands, config: Res<GameConfig>) {
+    commands.insert_resource(DailyResults::load(&config));
+}
+
+/// Seeds the daily run and records the attempt, later runs of the day are practice.
+fn start_daily(
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    mode: Res<CurrentMode>,
+    level: Res<CurrentLevel>,
+    mut daily: ResMut<DailyChallenge>,
+    mut results: ResMut<DailyResults>,
+    mut run: ResMut<CurrentRun>,
+) {
+    run.practice = false;
+    if mode.0 != GameMode::Daily {
+        return;
+    }
+    // Game may run past midnight
+    let today = Date::today();
+    if daily.date != today {
+        *daily = DailyChallenge::new(today);
+    }
+    run.next_seed = Some(daily.seed);
+    // Play-tests of the editor's levels and changed rules do not use up the attempt
+    if results.get(today).is_some()
+        || level.0 != Level::from(&config.world)
+        || !is_comparable(&settings)
+    {
+        run.practice = true;
+        return;
+    }
+    // Attempt counts even if the game is closed before it ends
+    results
+        .results
+        .insert(today.to_string(), DailyResult::default());
+    results.save(&config);
+}
+
+/// Stores the score, when the scored daily run ends or is left.
+fn record_result(
+    config: Res<GameConfig>,
+    mode: Res<CurrentMode>,
+    run: Res<CurrentRun>,
+    daily: Res<DailyChallenge>,
+    score: Res<Score>,
+    stats: Res<RunStats>,
+    mut results: ResMut<DailyResults>,
+) {
+    if mode.0 != GameMode::Daily || run.practice {
+        return;
+    }
+    let result = DailyResult {
+        score: score.score,
+        wave: stats.wave,
+    };
+    info!("{}", daily.share(&result));
+    results.results.insert(daily.date.to_string(), result);
+    results.save(&config);
+}
+
+fn console_commands(
+    mut entered: EventReader<ConsoleCommand>,
+    mut output: EventWriter<ConsoleOutput>,
+    daily: Res<DailyChallenge>,
+    results: Res<DailyResults>,
+) {
+    for _ in entered.iter().filter(|command| command.name == "daily") {
+        let message = match results.get(daily.date) {
+            Some(result) => daily.share(result),
+            None => {
+                let modifiers = daily
+                    .modifiers
+                    .iter()
+                    .map(DailyModifier::name)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("Daily {}: seed {}, {}", daily.date, daily.seed, modifiers)
+            }
+        };
+        output.send(ConsoleOutput(message));
+    }
+}
+
+/// Challenge with the seed and modifiers derived from the date, scored once a day.
+#[derive(Default)]
+pub struct DailyPlugin;
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<CurrentMode>()
+            .init_resource::<DailyChallenge>()
+            .init_resource::<DailyResults>()
+            .init_resource::<CurrentLevel>()
+            .init_resource::<Settings>()
+            .add_console_command("daily", "", "Shows today's challenge or its result")
+            .add_startup_system(load_results)
+            .add_system(console_commands)
+            .add_system_set(
+                SystemSet::on_enter(GameState::NewRun).with_system(start_daily.before(SeedSystem)),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::InGame).with_system(record_result));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> Date {
+        Date { year, month, day }
+    }
+
+    #[test]
+    fn days_are_converted_to_dates() {
+        assert_eq!(Date::from_days(0), date(1970, 1, 1));
+        assert_eq!(Date::from_days(-1), date(1969, 12, 31));
+        assert_eq!(Date::from_days(11016), date(2000, 2, 29));
+        assert_eq!(Date::from_days(20745), date(2026, 10, 19));
+        assert_eq!(date(2026, 10, 19).to_string(), "2026-10-19");
+    }
+
+    #[test]
+    fn challenge_is_derived_from_the_date_only() {
+        let challenge = DailyChallenge::new(date(2026, 10, 19));
+        assert_eq!(challenge.seed, 5956274182541815163);
+        assert_eq!(
+            challenge.modifiers,
+            vec![DailyModifier::FastSpawners, DailyModifier::BigCuboids]
+        );
+        let again = DailyChallenge::new(date(2026, 10, 19));
+        assert_eq!(again.seed, challenge.seed);
+        assert_eq!(again.modifiers, challenge.modifiers);
+    }
+
+    #[test]
+    fn challenges_differ_between_days() {
+        let first = DailyChallenge::new(date(2026, 10, 19));
+        let second = DailyChallenge::new(date(2026, 10, 20));
+        assert_ne!(first.seed, second.seed);
+    }
+
+    #[test]
+    fn changed_rules_are_not_comparable() {
+        let mut settings = Settings::default();
+        assert!(is_comparable(&settings));
+        settings.difficulty = Difficulty::Easy;
+        assert!(!is_comparable(&settings));
+
+        let mut settings = Settings::default();
+        settings.accessibility.game_speed = 0.5;
+        assert!(!is_comparable(&settings));
+
+        let mut settings = Settings::default();
+        settings.accessibility.assist = Assist::AutoFire;
+        assert!(!is_comparable(&settings));
+    }
+
+    #[test]
+    fn modifiers_are_distinct() {
+        for days in 20000..20400 {
+            let challenge = DailyChallenge::new(Date::from_days(days));
+            assert_eq!(challenge.modifiers.len(), MODIFIERS_PER_DAY);
+            assert_ne!(challenge.modifiers[0], challenge.modifiers[1]);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use bevy::{prelude::*, render::render_resource::PrimitiveTopology};
+use bevy_rapier2d::prelude::*;
+
+use super::{
+    console::{ConsoleAppExt, ConsoleCommand, ConsoleOutput},
+    cuboids::Spawner,
+};
+
+/// Key toggling the overlay.
+const TOGGLE_KEY: KeyCode = KeyCode::F3;
+/// Depth of the lines, in front of the cuboids.
+const LINES_Z: f32 = 5.0;
+/// Length of the velocity vectors, in seconds of movement.
+const VELOCITY_SCALE: f32 = 0.25;
+/// Length of the spawner's direction cone.
+const CONE_LENGTH: f32 = 15.0;
+/// Segments of the circles and arcs.
+const SEGMENTS: usize = 16;
+
+/// Whether collider outlines, spawners, velocities and entity counts are shown.
+#[derive(Debug, Default)]
+pub struct DebugOverlay {
+    pub enabled: bool,
+}
+
+/// Kind of lines, each drawn with its own colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+enum DebugLayer {
+    /// Solid colliders of all bodies.
+    Colliders,
+    /// Trap sensors.
+    Sensors,
+    Spawners,
+    Velocities,
+}
+
+impl DebugLayer {
+    const ALL: [DebugLayer; 4] = [
+        DebugLayer::Colliders,
+        DebugLayer::Sensors,
+        DebugLayer::Spawners,
+        DebugLayer::Velocities,
+    ];
+
+    fn color(self) -> Color {
+        match self {
+            DebugLayer::Colliders => Color::rgb(0.2, 1.0, 0.2),
+            DebugLayer::Sensors => Color::rgb(1.0, 0.2, 0.2),
+            DebugLayer::Spawners => Color::rgb(1.0, 0.9, 0.2),
+            DebugLayer::Velocities => Color::rgb(0.2, 0.8, 1.0),
+        }
+    }
+}
+
+/// Segments of a single layer, rebuilt every frame.
+#[derive(Debug, Default)]
+struct Lines {
+    positions: Vec<[f32; 3]>,
+}
+
+impl Lines {
+    fn line(&mut self, from: Vec2, to: Vec2) {
+        self.positions.push([from.x, from.y, LINES_Z]);
+        self.positions.push([to.x, to.y, LINES_Z]);
+    }
+
+    /// Closed polygon through the `points`.
+    fn polygon(&mut self, points: &[Vec2]) {
+        for (index, point) in points.iter().enumerate() {
+            self.line(*point, points[(index + 1) % points.len()]);
+        }
+    }
+
+    /// Arc around the `center` between the angles in radians.
+    fn arc(&mut self, center: Vec2, radius: f32, angles: std::ops::Range<f32>) {
+        let point = |step: usize| {
+            let angle = angles.start + (angles.end - angles.start) * step as f32 / SEGMENTS as f32;
+            center + radius * Vec2::new(angle.cos(), angle.sin())
+        };
+        for step in 0..SEGMENTS {
+            self.line(point(step), point(step + 1));
+        }
+    }
+
+    fn write(self, mesh: &mut Mesh) {
+        let count = self.positions.len();
+        mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; count]);
+        mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; count]);
+    }
+}
+
+fn to_vec(point: &Point<Real>) -> Vec2 {
+    Vec2::new(point.x, point.y)
+}
+
+/// Outline of the collider in world coordinates.
+fn outline(lines: &mut Lines, shape: &ColliderShape, position: &Isometry<Real>) {
+    if let Some(cuboid) = shape.as_cuboid() {
+        let extent = cuboid.half_extents;
+        let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+            .map(|(x, y)| to_vec(&(position * point![x * extent.x, y * extent.y])));
+        lines.polygon(&corners);
+    } else if let Some(polygon) = shape.as_convex_polygon() {
+        let points = polygon
+            .points()
+            .iter()
+            .map(|point| to_vec(&(position * point)))
+            .collect::<Vec<_>>();
+        lines.polygon(&points);
+    } else if let Some(ball) = shape.as_ball() {
+        let center = to_vec(&(position * point![0.0, 0.0]));
+        lines.arc(center, ball.radius, 0.0..std::f32::consts::TAU);
+    }
+}
+
+fn spawn_overlay(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for layer in DebugLayer::ALL {
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: meshes.add(Mesh::new(PrimitiveTopology::LineList)),
+                material: materials.add(StandardMaterial {
+                    base_color: layer.color(),
+                    unlit: true,
+                    ..Default::default()
+                }),
+                visibility: Visibility { is_visible: false },
+                ..Default::default()
+            })
+            .insert(layer);
+    }
+}
+
+fn toggle_overlay(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut entered: EventReader<ConsoleCommand>,
+    mut output: EventWriter<ConsoleOutput>,
+    mut overlay: ResMut<DebugOverlay>,
+) {
+    let command = entered.iter().any(|command| command.name == "debug");
+    if keyboard_input.just_pressed(TOGGLE_KEY) || command {
+        overlay.enabled = !overlay.enabled;
+    }
+    if command {
+        let state = if overlay.enabled { "on" } else { "off" };
+        output.send(ConsoleOutput(format!("Debug overlay {}", state)));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn draw_overlay(
+    overlay: Res<DebugOverlay>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    colliders: Query<(
+        &ColliderShapeComponent,
+        &ColliderPositionComponent,
+        &ColliderTypeComponent,
+    )>,
+    spawners: Query<(&Spawner, &Transform)>,
+    bodies: Query<(&RigidBodyPositionComponent, &RigidBodyVelocityComponent)>,
+    mut layers: Query<(&DebugLayer, &Handle<Mesh>, &mut Visibility)>,
+) {
+    if !overlay.enabled {
+        if overlay.is_changed() {
+            for (_, _, mut visibility) in layers.iter_mut() {
+                visibility.is_visible = false;
+            }
+        }
+        return;
+    }
+
+    let mut lines: [Lines; 4] = Default::default();
+    for (shape, position, collider_type) in colliders.iter() {
+        let layer = match collider_type.0 {
+            ColliderType::Sensor => DebugLayer::Sensors,
+            ColliderType::Solid => DebugLayer::Colliders,
+        };
+        outline(&mut lines[layer as usize], &shape.0, &position.0 .0);
+    }
+
+    let spawners_lines = &mut lines[DebugLayer::Spawners as usize];
+    for (spawner, transform) in spawners.iter() {
+        let center = transform.translation.truncate();
+        let angles = spawner.angle_range();
+        let start = (angles.start as f32).to_radians();
+        let end = (angles.end as f32).to_radians();
+        for angle in [start, end] {
+            let direction = Vec2::new(angle.cos(), angle.sin());
+            spawners_lines.line(center, center + CONE_LENGTH * direction);
+        }
+        spawners_lines.arc(center, CONE_LENGTH, start..end);
+        // Cooldown progress as a bar growing under the spawner
+        let progress = spawner.cooldown().percent();
+        let bar = center - Vec2::new(0.5 * CONE_LENGTH, 2.0);
+        spawners_lines.line(bar, bar + Vec2::new(CONE_LENGTH * progress, 0.0));
+    }
+
+    let velocities_lines = &mut lines[DebugLayer::Velocities as usize];
+    for (position, velocity) in bodies.iter() {
+        let linvel = velocity.0.linvel;
+        if linvel.norm_squared() <= f32::EPSILON {
+            continue;
+        }
+        let translation = position.0.position.translation.vector;
+        let from = Vec2::new(translation.x, translation.y);
+        velocities_lines.line(from, from + VELOCITY_SCALE * Vec2::new(linvel.x, linvel.y));
+    }
+
+    for (layer, handle, mut visibility) in layers.iter_mut() {
+        let layer_lines = std::mem::take(&mut lines[*layer as usize]);
+        // Empty meshes cannot be drawn
+        visibility.is_visible = !layer_lines.positions.is_empty();
+        if let Some(mesh) = meshes.get_mut(handle) {
+            layer_lines.write(mesh);
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct DebugSystem;
+
+/// Draws collider outlines, trap sensors, spawners with their direction cones and cooldowns
+/// and velocity vectors. Toggled with F3 or the `debug` console command.
+#[derive(Default)]
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugOverlay>()
+            .add_console_command("debug", "", "Toggles the debug overlay")
+            .add_startup_system(spawn_overlay)
+            .add_system(toggle_overlay.label(DebugSystem))
+            .add_system(draw_overlay.after(DebugSystem));
+    }
+}
@@ -0,0 +1,644 @@
+use bevy::{pbr::AlphaMode, prelude::*, render::camera::CameraProjection, window::Windows};
+use std::ops::Range;
+
+use super::{
+    camera::CameraController,
+    config::{GameConfig, SpawnerConfig},
+    cuboids::Spawner,
+    level::{Block, CurrentLevel, Level},
+    menu::{despawn_screen, MenuInput, MenuStyle, MenuSystem, ScaledText},
+    settings::Settings,
+    state::GameState,
+    theme::Theme,
+    world::Arena,
+};
+
+/// Name, under which the edited level is saved.
+const EDITED_LEVEL: &str = "custom";
+const PANEL_FONT_SIZE: f32 = 18.0;
+/// Edge length of the spawner's handle.
+const SPAWNER_HANDLE_SIZE: f32 = 4.0;
+/// Distance from the block, at which it can still be clicked.
+const PICK_MARGIN: f32 = 2.0;
+/// Smallest half size of walls and traps.
+const MIN_HALF_SIZE: f32 = 0.5;
+
+const HELP: &str = "Click: select and drag   1/2/3: add wall/trap/spawner   Delete: remove\n\
+                    Up/Down: field   Left/Right: adjust   Enter: play-test   Ctrl+S: save   Esc: menu";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ItemKind {
+    Wall,
+    Trap,
+    Spawner,
+}
+
+/// Item of the edited level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Selection {
+    kind: ItemKind,
+    index: usize,
+}
+
+/// Property of the selected item, which can be adjusted in the panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Width,
+    Height,
+    Cooldown,
+    MinAngle,
+    MaxAngle,
+    MinSize,
+    MaxSize,
+    MinSpeed,
+    MaxSpeed,
+}
+
+impl Field {
+    fn of(kind: ItemKind) -> &'static [Field] {
+        match kind {
+            ItemKind::Wall | ItemKind::Trap => &[Field::Width, Field::Height],
+            ItemKind::Spawner => &[
+                Field::Cooldown,
+                Field::MinAngle,
+                Field::MaxAngle,
+                Field::MinSize,
+                Field::MaxSize,
+                Field::MinSpeed,
+                Field::MaxSpeed,
+            ],
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Field::Width => "Half width",
+            Field::Height => "Half height",
+            Field::Cooldown => "Cooldown",
+            Field::MinAngle => "Min angle",
+            Field::MaxAngle => "Max angle",
+            Field::MinSize => "Min size",
+            Field::MaxSize => "Max size",
+            Field::MinSpeed => "Min speed",
+            Field::MaxSpeed => "Max speed",
+        }
+    }
+}
+
+/// State of the level editor.
+#[derive(Debug, Default)]
+struct Editor {
+    selected: Option<Selection>,
+    /// Offset of the dragged item from the cursor.
+    drag: Option<Vec2>,
+    /// Index of the selected [`Field`].
+    field: usize,
+    /// Edited level, kept while it is play-tested.
+    level: Option<Level>,
+}
+
+fn blocks(level: &Level, kind: ItemKind) -> Option<&Vec<Block>> {
+    match kind {
+        ItemKind::Wall => Some(&level.walls),
+        ItemKind::Trap => Some(&level.traps),
+        ItemKind::Spawner => None,
+    }
+}
+
+fn blocks_mut(level: &mut Level, kind: ItemKind) -> Option<&mut Vec<Block>> {
+    match kind {
+        ItemKind::Wall => Some(&mut level.walls),
+        ItemKind::Trap => Some(&mut level.traps),
+        ItemKind::Spawner => None,
+    }
+}
+
+fn position(level: &Level, selection: Selection) -> Option<Vec2> {
+    match blocks(level, selection.kind) {
+        Some(blocks) => blocks.get(selection.index).map(|block| block.position),
+        None => level
+            .spawners
+            .get(selection.index)
+            .map(|spawner| spawner.position),
+    }
+}
+
+fn set_position(level: &mut Level, selection: Selection, position: Vec2) {
+    match blocks_mut(level, selection.kind) {
+        Some(blocks) => blocks[selection.index].position = position,
+        None => level.spawners[selection.index].position = position,
+    }
+}
+
+fn remove(level: &mut Level, selection: Selection) {
+    match blocks_mut(level, selection.kind) {
+        Some(blocks) => {
+            blocks.remove(selection.index);
+        }
+        None => {
+            level.spawners.remove(selection.index);
+        }
+    }
+}
+
+/// Item under the `cursor`, spawners are picked first as they are the smallest.
+fn pick(level: &Level, cursor: Vec2) -> Option<Selection> {
+    let spawner = level
+        .spawners
+        .iter()
+        .position(|spawner| {
+            (spawner.position - cursor).abs().max_element()
+                <= 0.5 * SPAWNER_HANDLE_SIZE + PICK_MARGIN
+        })
+        .map(|index| Selection {
+            kind: ItemKind::Spawner,
+            index,
+        });
+    let pick_block = |kind| {
+        blocks(level, kind)?
+            .iter()
+            .position(|block| {
+                let distance = (block.position - cursor).abs() - block.half_size;
+                distance.max_element() <= PICK_MARGIN
+            })
+            .map(|index| Selection { kind, index })
+    };
+    spawner
+        .or_else(|| pick_block(ItemKind::Trap))
+        .or_else(|| pick_block(ItemKind::Wall))
+}
+
+/// Moves the start or end of the `range` by `delta`, keeping it non-empty and within `limits`.
+fn adjust_range(range: Range<i32>, start: bool, delta: i32, limits: Range<i32>) -> Range<i32> {
+    if start {
+        (range.start + delta).min(range.end - 1).max(limits.start)..range.end
+    } else {
+        range.start..(range.end + delta).max(range.start + 1).min(limits.end)
+    }
+}
+
+fn to_i32<T: Copy + Into<i32>>(range: Range<T>) -> Range<i32> {
+    range.start.into()..range.end.into()
+}
+
+fn from_i32<T: TryFrom<i32>>(range: Range<i32>) -> Option<Range<T>> {
+    Some(T::try_from(range.start).ok()?..T::try_from(range.end).ok()?)
+}
+
+fn adjust_spawner(config: &GameConfig, spawner: &mut SpawnerConfig, field: Field, delta: i32) {
+    // Ranges without values use defaults of the spawner
+    let defaults = Spawner::from(&*spawner);
+    let sizes = config.cuboids.sizes.clone();
+    let sizes = sizes.start as i32..sizes.end as i32;
+    match field {
+        Field::Cooldown => {
+            spawner.cooldown = (spawner.cooldown + 0.1 * delta as f32).max(0.1);
+        }
+        Field::MinAngle | Field::MaxAngle => {
+            let range = to_i32(defaults.angle_range());
+            let range = adjust_range(range, field == Field::MinAngle, 5 * delta, 0..360);
+            spawner.angle_range = from_i32(range);
+        }
+        Field::MinSize | Field::MaxSize => {
+            let range = to_i32(defaults.size_range());
+            let range = adjust_range(range, field == Field::MinSize, delta, sizes);
+            spawner.size_range = from_i32(range);
+        }
+        Field::MinSpeed | Field::MaxSpeed => {
+            let range = to_i32(defaults.speed_range());
+            let range = adjust_range(range, field == Field::MinSpeed, delta, 1..255);
+            spawner.speed_range = from_i32(range);
+        }
+        Field::Width | Field::Height => {}
+    }
+}
+
+fn adjust_field(
+    config: &GameConfig,
+    level: &mut Level,
+    selection: Selection,
+    field: Field,
+    delta: i32,
+) {
+    match blocks_mut(level, selection.kind) {
+        Some(blocks) => {
+            let half_size = &mut blocks[selection.index].half_size;
+            let value = match field {
+                Field::Width => &mut half_size.x,
+                Field::Height => &mut half_size.y,
+                _ => return,
+            };
+            *value = (*value + delta as f32).max(MIN_HALF_SIZE);
+        }
+        None => adjust_spawner(config, &mut level.spawners[selection.index], field, delta),
+    }
+}
+
+fn field_value(level: &Level, selection: Selection, field: Field) -> String {
+    if let Some(blocks) = blocks(level, selection.kind) {
+        let half_size = blocks[selection.index].half_size;
+        return match field {
+            Field::Width => format!("{:.1}", half_size.x),
+            _ => format!("{:.1}", half_size.y),
+        };
+    }
+    let config = &level.spawners[selection.index];
+    let spawner = Spawner::from(config);
+    match field {
+        Field::Cooldown => format!("{:.1}s", config.cooldown),
+        Field::MinAngle => spawner.angle_range().start.to_string(),
+        Field::MaxAngle => spawner.angle_range().end.to_string(),
+        Field::MinSize => spawner.size_range().start.to_string(),
+        Field::MaxSize => spawner.size_range().end.to_string(),
+        Field::MinSpeed => spawner.speed_range().start.to_string(),
+        _ => spawner.speed_range().end.to_string(),
+    }
+}
+
+/// Point of the gameplay plane under the cursor.
+fn cursor_position(
+    windows: &Windows,
+    camera: &GlobalTransform,
+    projection: &PerspectiveProjection,
+) -> Option<Vec2> {
+    let window = windows.get_primary()?;
+    let cursor = window.cursor_position()?;
+    let ndc = cursor / Vec2::new(window.width(), window.height()) * 2.0 - Vec2::ONE;
+    let ndc_to_world = camera.compute_matrix() * projection.get_projection_matrix().inverse();
+    // Reversed depth, the near plane is at 1
+    let near = ndc_to_world.project_point3(ndc.extend(1.0));
+    let far = ndc_to_world.project_point3(ndc.extend(0.5));
+    let direction = far - near;
+    if direction.z.abs() <= f32::EPSILON {
+        return None;
+    }
+    Some((near - direction * (near.z / direction.z)).truncate())
+}
+
+/// Meshes and materials of the handles.
+struct EditorAssets {
+    cube: Handle<Mesh>,
+    wall: Handle<StandardMaterial>,
+    trap: Handle<StandardMaterial>,
+    spawner: Handle<StandardMaterial>,
+    selected: Handle<StandardMaterial>,
+}
+
+/// Visible marker of an item of the edited level.
+#[derive(Debug, Component)]
+struct EditorHandle;
+
+#[derive(Debug, Component)]
+struct EditorScreen;
+
+#[derive(Debug, Component)]
+struct EditorPanel;
+
+fn spawn_handles(
+    commands: &mut Commands,
+    assets: &EditorAssets,
+    arena: &Arena,
+    level: &Level,
+    selected: Option<Selection>,
+) {
+    let mut spawn = |selection: Selection, position: Vec2, size: Vec3| {
+        let material = if selected == Some(selection) {
+            assets.selected.clone()
+        } else {
+            match selection.kind {
+                ItemKind::Wall => assets.wall.clone(),
+                ItemKind::Trap => assets.trap.clone(),
+                ItemKind::Spawner => assets.spawner.clone(),
+            }
+        };
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: assets.cube.clone(),
+                material,
+                transform: Transform::from_translation(arena.place(position).extend(0.0))
+                    .with_scale(size),
+                ..Default::default()
+            })
+            .insert(EditorHandle);
+    };
+    for kind in [ItemKind::Wall, ItemKind::Trap] {
+        for (index, block) in blocks(level, kind).into_iter().flatten().enumerate() {
+            let size = 2.0 * arena.place(block.half_size);
+            spawn(Selection { kind, index }, block.position, size.extend(1.0));
+        }
+    }
+    for (index, spawner) in level.spawners.iter().enumerate() {
+        let selection = Selection {
+            kind: ItemKind::Spawner,
+            index,
+        };
+        spawn(
+            selection,
+            spawner.position,
+            Vec3::splat(SPAWNER_HANDLE_SIZE),
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn enter_editor(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    arena: Res<Arena>,
+    mut editor: ResMut<Editor>,
+    mut current: ResMut<CurrentLevel>,
+) {
+    let level = editor
+        .level
+        .take()
+        .or_else(|| Level::load(&config, EDITED_LEVEL))
+        .unwrap_or_else(|| Level {
+            name: EDITED_LEVEL.to_string(),
+            ..Level::from(&config.world)
+        });
+    if current.0 != level {
+        current.0 = level;
+    }
+    editor.selected = None;
+    editor.drag = None;
+
+    let mut material = |color| {
+        materials.add(StandardMaterial {
+            base_color: color,
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..Default::default()
+        })
+    };
+    let assets = EditorAssets {
+        cube: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
+        wall: material(Color::rgba(0.6, 0.6, 0.75, 0.5)),
+        trap: material(Color::rgba(0.9, 0.2, 0.2, 0.4)),
+        spawner: material(Color::rgba(0.2, 0.9, 0.3, 0.7)),
+        selected: material(Color::rgba(1.0, 0.9, 0.2, 0.7)),
+    };
+    spawn_handles(&mut commands, &assets, &arena, &current.0, None);
+    commands.insert_resource(assets);
+
+    let style = MenuStyle::new(&asset_server, &config, &settings, &theme);
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.0),
+                    top: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            color: UiColor(style.overlay_color),
+            ..Default::default()
+        })
+        .insert(EditorScreen)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(style.text("", PANEL_FONT_SIZE, style.text_color))
+                .insert(ScaledText {
+                    size: PANEL_FONT_SIZE,
+                })
+                .insert(EditorPanel);
+        });
+}
+
+fn exit_editor(mut editor: ResMut<Editor>, current: Res<CurrentLevel>) {
+    editor.level = Some(current.0.clone());
+    editor.drag = None;
+}
+
+/// Leaves the edited level, which is only played from the editor.
+fn restore_level(config: Res<GameConfig>, mut current: ResMut<CurrentLevel>) {
+    let level = Level::from(&config.world);
+    if current.0 != level {
+        current.0 = level;
+    }
+}
+
+/// Selects and drags the items with the mouse.
+fn drag_items(
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    arena: Res<Arena>,
+    cameras: Query<(&GlobalTransform, &PerspectiveProjection), With<CameraController>>,
+    mut editor: ResMut<Editor>,
+    mut current: ResMut<CurrentLevel>,
+) {
+    let cursor = cameras
+        .iter()
+        .next()
+        .and_then(|(camera, projection)| cursor_position(&windows, camera, projection))
+        .map(|cursor| cursor / arena.scale);
+    let cursor = match cursor {
+        Some(cursor) => cursor,
+        None => return,
+    };
+
+    if mouse.just_pressed(MouseButton::Left) {
+        let selected = pick(&current.0, cursor);
+        if selected != editor.selected {
+            editor.selected = selected;
+            editor.field = 0;
+        }
+        editor.drag = selected
+            .and_then(|selection| position(&current.0, selection))
+            .map(|position| position - cursor);
+    } else if mouse.just_released(MouseButton::Left) {
+        editor.drag = None;
+    }
+
+    if let (Some(selection), Some(offset)) = (editor.selected, editor.drag) {
+        let target = (cursor + offset).round();
+        if position(&current.0, selection) != Some(target) {
+            set_position(&mut current.0, selection, target);
+        }
+    }
+}
+
+/// Adds, removes and adjusts the items with the keyboard, saves and play-tests the level.
+fn edit_items(
+    keyboard_input: Res<Input<KeyCode>>,
+    config: Res<GameConfig>,
+    mut input: ResMut<MenuInput>,
+    mut editor: ResMut<Editor>,
+    mut current: ResMut<CurrentLevel>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let ctrl =
+        keyboard_input.pressed(KeyCode::LControl) || keyboard_input.pressed(KeyCode::RControl);
+    if ctrl && keyboard_input.just_pressed(KeyCode::S) {
+        current.0.save(&config);
+        input.down = false;
+    }
+
+    let added = if keyboard_input.just_pressed(KeyCode::Key1) {
+        current
+            .0
+            .walls
+            .push(Block::new(Vec2::ZERO, Vec2::new(1.0, 20.0)));
+        Some((ItemKind::Wall, current.0.walls.len()))
+    } else if keyboard_input.just_pressed(KeyCode::Key2) {
+        current
+            .0
+            .traps
+            .push(Block::new(Vec2::ZERO, Vec2::new(20.0, 1.0)));
+        Some((ItemKind::Trap, current.0.traps.len()))
+    } else if keyboard_input.just_pressed(KeyCode::Key3) {
+        current.0.spawners.push(SpawnerConfig::new(Vec2::ZERO, 2.0));
+        Some((ItemKind::Spawner, current.0.spawners.len()))
+    } else {
+        None
+    };
+    if let Some((kind, count)) = added {
+        editor.selected = Some(Selection {
+            kind,
+            index: count - 1,
+        });
+        editor.field = 0;
+    }
+
+    if let Some(selection) = editor.selected {
+        if keyboard_input.just_pressed(KeyCode::Delete) {
+            remove(&mut current.0, selection);
+            editor.selected = None;
+            editor.drag = None;
+        } else {
+            let fields = Field::of(selection.kind);
+            if std::mem::take(&mut input.up) {
+                editor.field = (editor.field + fields.len() - 1) % fields.len();
+            } else if std::mem::take(&mut input.down) {
+                editor.field = (editor.field + 1) % fields.len();
+            }
+            let delta = input.take_horizontal();
+            if delta != 0 {
+                let field = fields[editor.field.min(fields.len() - 1)];
+                adjust_field(&config, &mut current.0, selection, field, delta as i32);
+            }
+        }
+    }
+
+    if input.take_confirm() {
+        let _ = state.set(GameState::NewRun);
+        input.clear();
+    } else if input.take_back() {
+        let _ = state.set(GameState::MainMenu);
+        input.clear();
+    }
+}
+
+fn sync_handles(
+    mut commands: Commands,
+    assets: Option<Res<EditorAssets>>,
+    arena: Res<Arena>,
+    editor: Res<Editor>,
+    current: Res<CurrentLevel>,
+    handles: Query<Entity, With<EditorHandle>>,
+) {
+    let assets = match assets {
+        Some(assets) => assets,
+        None => return,
+    };
+    if !current.is_changed() && !editor.is_changed() && !arena.is_changed() {
+        return;
+    }
+    for entity in handles.iter() {
+        commands.entity(entity).despawn();
+    }
+    spawn_handles(&mut commands, &assets, &arena, &current.0, editor.selected);
+}
+
+fn update_panel(
+    editor: Res<Editor>,
+    current: Res<CurrentLevel>,
+    mut panels: Query<&mut Text, With<EditorPanel>>,
+) {
+    if !current.is_changed() && !editor.is_changed() {
+        return;
+    }
+    let level = &current.0;
+    let mut lines = vec![
+        format!(
+            "Level {:?}: {} walls, {} traps, {} spawners",
+            level.name,
+            level.walls.len(),
+            level.traps.len(),
+            level.spawners.len()
+        ),
+        HELP.to_string(),
+    ];
+    if let Some(selection) = editor.selected {
+        let position = position(level, selection).unwrap_or_default();
+        lines.push(format!(
+            "{:?} {} at ({:.0}, {:.0})",
+            selection.kind,
+            selection.index + 1,
+            position.x,
+            position.y
+        ));
+        for (index, field) in Field::of(selection.kind).iter().enumerate() {
+            let marker = if index == editor.field { ">" } else { " " };
+            lines.push(format!(
+                "{} {}: {}",
+                marker,
+                field.label(),
+                field_value(level, selection, *field)
+            ));
+        }
+    }
+    for mut text in panels.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+enum EditorSystem {
+    Mouse,
+    Keyboard,
+    Display,
+}
+
+/// In-game editor of the [`CurrentLevel`], which is saved in the level format.
+#[derive(Default)]
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<Editor>()
+            .init_resource::<CurrentLevel>()
+            .add_system_set(SystemSet::on_enter(GameState::Editor).with_system(enter_editor))
+            .add_system_set(
+                SystemSet::on_update(GameState::Editor)
+                    .after(MenuSystem::Input)
+                    .with_system(drag_items.label(EditorSystem::Mouse))
+                    .with_system(
+                        edit_items
+                            .label(EditorSystem::Keyboard)
+                            .after(EditorSystem::Mouse),
+                    ),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Editor)
+                    .label(EditorSystem::Display)
+                    .after(EditorSystem::Keyboard)
+                    .with_system(sync_handles)
+                    .with_system(update_panel),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Editor)
+                    .with_system(exit_editor)
+                    .with_system(despawn_screen::<EditorScreen>)
+                    .with_system(despawn_screen::<EditorHandle>),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::MainMenu).with_system(restore_level));
+    }
+}
@@ -0,0 +1,482 @@
+use bevy::{core::DefaultTaskPoolOptions, prelude::*};
+use bevy_rapier2d::prelude::*;
+use std::{
+    cmp::Ordering,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use super::{
+    config::{GameConfig, StorageConfig},
+    controller::{CuboidState, Perception, Pilot, ShipAction, ShipController, ShipState},
+    cuboids::Cuboid,
+    scoring::Score,
+    settings::SimulationSpeed,
+    ship::Ship,
+    state::{CurrentRun, GameState},
+    world::Arena,
+    GamePlugins,
+};
+
+/// App with the gameplay plugins, without windows, rendering, UI, sound and saved data.
+///
+/// The app runs its systems on a single thread, so many of them can run side by side.
+pub fn headless_app(config: GameConfig) -> App {
+    let config = config.with_storage(StorageConfig { directory: None });
+    let mut app = App::new();
+    app.insert_resource(DefaultTaskPoolOptions::with_num_threads(1))
+        .add_plugins(MinimalPlugins)
+        .add_plugin(bevy::transform::TransformPlugin)
+        .add_plugin(bevy::input::InputPlugin)
+        .add_plugin(bevy::asset::AssetPlugin)
+        .add_asset::<Mesh>()
+        .add_asset::<StandardMaterial>()
+        .add_asset::<Image>()
+        .init_resource::<Windows>()
+        .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+        .add_plugins_with(GamePlugins::with_config(config), |group| {
+            group
+                .disable::<super::achievements::AchievementsPlugin>()
+                .disable::<super::attract::AttractPlugin>()
+                .disable::<super::console::ConsolePlugin>()
+                .disable::<super::debug::DebugPlugin>()
+                .disable::<super::perf::PerfPlugin>()
+                .disable::<super::ui::UiPlugin>()
+                .disable::<super::menu::MenuPlugin>()
+                .disable::<super::pause::PausePlugin>()
+                .disable::<super::game_over::GameOverPlugin>()
+                .disable::<super::editor::EditorPlugin>()
+                .disable::<super::sound::SoundPlugin>()
+        });
+    app
+}
+
+/// Discrete actions of the agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Idle,
+    Left,
+    Right,
+    Fire,
+    LeftFire,
+    RightFire,
+}
+
+impl Action {
+    pub const ALL: [Action; 6] = [
+        Action::Idle,
+        Action::Left,
+        Action::Right,
+        Action::Fire,
+        Action::LeftFire,
+        Action::RightFire,
+    ];
+}
+
+impl From<Action> for ShipAction {
+    fn from(action: Action) -> Self {
+        let (direction, fire) = match action {
+            Action::Idle => (0.0, false),
+            Action::Left => (-1.0, false),
+            Action::Right => (1.0, false),
+            Action::Fire => (0.0, true),
+            Action::LeftFire => (-1.0, true),
+            Action::RightFire => (1.0, true),
+        };
+        Self { direction, fire }
+    }
+}
+
+/// Resolution of the rasterized arena in the observations.
+#[derive(Debug, Clone, Copy)]
+pub struct GridConfig {
+    pub columns: usize,
+    pub rows: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnvConfig {
+    pub game: GameConfig,
+    /// Seconds of game time simulated by a single frame.
+    pub frame_time: f32,
+    /// Frames simulated by each step, repeating the action.
+    pub frame_skip: u32,
+    /// Steps, after which the episode ends even with lives left.
+    pub max_steps: u32,
+    /// Reward subtracted for each lost life, the score gives the positive reward.
+    pub life_penalty: f32,
+    /// Adds the rasterized arena to the observations.
+    pub grid: Option<GridConfig>,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            game: GameConfig::default(),
+            frame_time: 1.0 / 60.0,
+            frame_skip: 4,
+            max_steps: 10_000,
+            life_penalty: 100.0,
+            grid: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Observation {
+    pub ship: ShipState,
+    pub cuboids: Vec<CuboidState>,
+    pub score: u32,
+    /// Largest size of the cuboids in each cell, rows from the bottom of the arena.
+    pub grid: Option<Vec<f32>>,
+}
+
+impl Observation {
+    /// Fixed length vector of the ship's position, velocity and lives, followed by the relative
+    /// positions, velocities and sizes of the `count` nearest cuboids, padded with zeros.
+    pub fn features(&self, count: usize) -> Vec<f32> {
+        let ship = &self.ship;
+        let mut features = vec![
+            ship.position.x,
+            ship.position.y,
+            ship.velocity.x,
+            ship.velocity.y,
+            ship.lives as f32,
+        ];
+        let mut cuboids = self.cuboids.iter().collect::<Vec<_>>();
+        cuboids.sort_by(|a, b| {
+            let distance = |cuboid: &CuboidState| cuboid.position.distance_squared(ship.position);
+            distance(a)
+                .partial_cmp(&distance(b))
+                .unwrap_or(Ordering::Equal)
+        });
+        for index in 0..count {
+            match cuboids.get(index) {
+                Some(cuboid) => {
+                    let offset = cuboid.position - ship.position;
+                    features.extend([
+                        offset.x,
+                        offset.y,
+                        cuboid.velocity.x,
+                        cuboid.velocity.y,
+                        cuboid.size as f32,
+                    ]);
+                }
+                None => features.extend([0.0; 5]),
+            }
+        }
+        features
+    }
+}
+
+fn rasterize(cuboids: &[CuboidState], arena: &Arena, grid: GridConfig) -> Vec<f32> {
+    let mut cells = vec![0.0; grid.columns * grid.rows];
+    for cuboid in cuboids {
+        let normalized = (cuboid.position / arena.half_size + Vec2::ONE) * 0.5;
+        if !(0.0..1.0).contains(&normalized.x) || !(0.0..1.0).contains(&normalized.y) {
+            continue;
+        }
+        let column = (normalized.x * grid.columns as f32) as usize;
+        let row = (normalized.y * grid.rows as f32) as usize;
+        let cell = &mut cells[row * grid.columns + column];
+        *cell = cell.max(cuboid.size as f32);
+    }
+    cells
+}
+
+/// Repeats the last action chosen in [`Environment::step`].
+struct AgentController(Arc<Mutex<ShipAction>>);
+
+impl ShipController for AgentController {
+    fn act(&mut self, _perception: &Perception) -> ShipAction {
+        *self.0.lock().expect("Action is not poisoned")
+    }
+}
+
+/// Gym-style environment over the headless simulation.
+///
+/// Each episode is a run started with [`Environment::reset`], the agent flies the ship with
+/// [`Environment::step`] until the run is over.
+pub struct Environment {
+    app: App,
+    config: EnvConfig,
+    action: Arc<Mutex<ShipAction>>,
+    steps: u32,
+    score: u32,
+    lives: u8,
+}
+
+impl Environment {
+    pub fn new(config: EnvConfig) -> Self {
+        let mut app = headless_app(config.game.clone());
+        // Startup systems run before the first episode
+        app.update();
+        app.world
+            .get_resource_mut::<SimulationSpeed>()
+            .expect("SimulationSpeed is added by the settings plugin")
+            .fixed_step = Some(Duration::from_secs_f32(config.frame_time));
+        Self {
+            app,
+            config,
+            action: Arc::default(),
+            steps: 0,
+            score: 0,
+            lives: 0,
+        }
+    }
+
+    /// Starts a new run with the random generator seeded with `seed`.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        let world = &mut self.app.world;
+        world
+            .get_resource_mut::<CurrentRun>()
+            .expect("Run is added by the state plugin")
+            .next_seed = Some(seed);
+        let _ = world
+            .get_resource_mut::<State<GameState>>()
+            .expect("States are added by the state plugin")
+            .set(GameState::NewRun);
+        // Enter the new run and start it
+        self.app.update();
+        self.app.update();
+
+        let world = &mut self.app.world;
+        let ships = world
+            .query_filtered::<Entity, With<Ship>>()
+            .iter(world)
+            .collect::<Vec<_>>();
+        for entity in ships {
+            world
+                .entity_mut(entity)
+                .insert(Pilot(Box::new(AgentController(self.action.clone()))));
+        }
+        *self.action.lock().expect("Action is not poisoned") = ShipAction::default();
+
+        let observation = self.observe();
+        self.steps = 0;
+        self.score = observation.score;
+        self.lives = observation.ship.lives;
+        observation
+    }
+
+    /// Simulates the configured number of frames with the `action`.
+    ///
+    /// Returns the observation, the reward from the gained score and lost lives and whether
+    /// the episode is over.
+    pub fn step(&mut self, action: Action) -> (Observation, f32, bool) {
+        *self.action.lock().expect("Action is not poisoned") = action.into();
+        for _ in 0..self.config.frame_skip {
+            self.app.update();
+            if !self.running() {
+                break;
+            }
+        }
+        self.steps += 1;
+
+        let observation = self.observe();
+        let lost_lives = self.lives.saturating_sub(observation.ship.lives);
+        let reward = observation.score.saturating_sub(self.score) as f32
+            - lost_lives as f32 * self.config.life_penalty;
+        self.score = observation.score;
+        self.lives = observation.ship.lives;
+        let done = !self.running() || self.steps >= self.config.max_steps;
+        (observation, reward, done)
+    }
+
+    fn running(&self) -> bool {
+        self.app
+            .world
+            .get_resource::<State<GameState>>()
+            .map_or(false, |state| *state.current() == GameState::InGame)
+    }
+
+    fn observe(&mut self) -> Observation {
+        let world = &mut self.app.world;
+        let ship = world
+            .query::<(
+                &Ship,
+                &RigidBodyPositionComponent,
+                &RigidBodyVelocityComponent,
+            )>()
+            .iter(world)
+            .map(|(ship, position, velocity)| ShipState::new(ship, position, velocity))
+            .next()
+            .unwrap_or_default();
+        let cuboids = world
+            .query::<(
+                &Cuboid,
+                &RigidBodyPositionComponent,
+                &RigidBodyVelocityComponent,
+            )>()
+            .iter(world)
+            .map(|(cuboid, position, velocity)| CuboidState::new(cuboid, position, velocity))
+            .collect::<Vec<_>>();
+        let grid = self.config.grid.map(|grid| {
+            let arena = world.get_resource::<Arena>().expect("Arena is fitted");
+            rasterize(&cuboids, arena, grid)
+        });
+        let score = world.get_resource::<Score>().map_or(0, |score| score.score);
+        Observation {
+            ship,
+            cuboids,
+            score,
+            grid,
+        }
+    }
+}
+
+enum Request {
+    Reset(u64),
+    Step(Action),
+}
+
+/// Environment running on its own thread, as apps cannot be sent between threads.
+struct Worker {
+    requests: mpsc::Sender<Request>,
+    responses: mpsc::Receiver<(Observation, f32, bool)>,
+    thread: thread::JoinHandle<()>,
+}
+
+impl Worker {
+    fn spawn(config: EnvConfig) -> Self {
+        let (requests, received) = mpsc::channel();
+        let (sender, responses) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            let mut environment = Environment::new(config);
+            for request in received {
+                let response = match request {
+                    Request::Reset(seed) => (environment.reset(seed), 0.0, false),
+                    Request::Step(action) => environment.step(action),
+                };
+                if sender.send(response).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            requests,
+            responses,
+            thread,
+        }
+    }
+
+    fn send(&self, request: Request) {
+        self.requests
+            .send(request)
+            .expect("Environment thread is running");
+    }
+
+    fn receive(&self) -> (Observation, f32, bool) {
+        self.responses
+            .recv()
+            .expect("Environment thread is running")
+    }
+}
+
+/// Environments stepping in parallel, each on its own thread.
+pub struct ParallelEnvironments {
+    workers: Vec<Worker>,
+}
+
+impl ParallelEnvironments {
+    pub fn new(count: usize, config: EnvConfig) -> Self {
+        Self {
+            workers: (0..count).map(|_| Worker::spawn(config.clone())).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.workers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.workers.is_empty()
+    }
+
+    /// Resets every environment with its seed from `seeds`.
+    pub fn reset(&mut self, seeds: &[u64]) -> Vec<Observation> {
+        assert_eq!(
+            seeds.len(),
+            self.workers.len(),
+            "Seed for every environment"
+        );
+        for (worker, seed) in self.workers.iter().zip(seeds) {
+            worker.send(Request::Reset(*seed));
+        }
+        self.workers
+            .iter()
+            .map(|worker| worker.receive().0)
+            .collect()
+    }
+
+    /// Steps every environment with its action from `actions`.
+    pub fn step(&mut self, actions: &[Action]) -> Vec<(Observation, f32, bool)> {
+        assert_eq!(
+            actions.len(),
+            self.workers.len(),
+            "Action for every environment"
+        );
+        for (worker, action) in self.workers.iter().zip(actions) {
+            worker.send(Request::Step(*action));
+        }
+        self.workers.iter().map(Worker::receive).collect()
+    }
+}
+
+impl Drop for ParallelEnvironments {
+    fn drop(&mut self) {
+        for worker in self.workers.drain(..) {
+            let Worker {
+                requests, thread, ..
+            } = worker;
+            // Closed channel ends the thread
+            drop(requests);
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Nearest cuboids in the compared observations.
+    const FEATURE_CUBOIDS: usize = 8;
+    const STEPS: usize = 150;
+
+    /// Observations and rewards of an episode, in which the actions cycle in a fixed order.
+    fn episode(env: &mut Environment, seed: u64) -> Vec<(Vec<f32>, u32, usize, f32, bool)> {
+        let observation = env.reset(seed);
+        let mut steps = vec![(
+            observation.features(FEATURE_CUBOIDS),
+            observation.score,
+            observation.cuboids.len(),
+            0.0,
+            false,
+        )];
+        for index in 0..STEPS {
+            let (observation, reward, done) = env.step(Action::ALL[index % Action::ALL.len()]);
+            steps.push((
+                observation.features(FEATURE_CUBOIDS),
+                observation.score,
+                observation.cuboids.len(),
+                reward,
+                done,
+            ));
+            if done {
+                break;
+            }
+        }
+        steps
+    }
+
+    #[test]
+    fn same_seed_and_actions_give_same_episode() {
+        let mut first = Environment::new(EnvConfig::default());
+        let mut second = Environment::new(EnvConfig::default());
+        let expected = episode(&mut first, 42);
+        assert!(expected.iter().any(|(_, _, cuboids, _, _)| *cuboids > 0));
+        assert_eq!(episode(&mut second, 42), expected);
+    }
+}
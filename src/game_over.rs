@@ -0,0 +1,396 @@
+use bevy::prelude::*;
+
+use super::{
+    config::GameConfig,
+    daily::{DailyChallenge, DailyResult},
+    menu::{
+        activated_item, despawn_screen, spawn_item, spawn_menu, MenuCursor, MenuInput, MenuItem,
+        MenuStyle, MenuSystem, ScaledText,
+    },
+    mode::{CurrentMode, GameMode},
+    scoring::{HighScore, HighScoreTables, RunStats, Score},
+    settings::Settings,
+    state::{CurrentRun, GameState},
+    theme::Theme,
+};
+
+const SUMMARY_FONT_SIZE: f32 = 22.0;
+const NAME_LENGTH: usize = 3;
+/// Seconds, in which input is ignored, so the player does not skip the summary by accident.
+const INPUT_DELAY: f32 = 1.0;
+
+#[derive(Debug, Component)]
+struct GameOverScreen;
+
+#[derive(Debug, Component)]
+struct NameEntryText;
+
+#[derive(Debug, Clone, Copy, Component)]
+enum GameOverAction {
+    Retry,
+    MainMenu,
+}
+
+/// Initials of the player, entered after reaching a high score.
+#[derive(Debug)]
+struct NameEntry {
+    letters: [char; NAME_LENGTH],
+    position: usize,
+}
+
+impl Default for NameEntry {
+    fn default() -> Self {
+        Self {
+            letters: ['A'; NAME_LENGTH],
+            position: 0,
+        }
+    }
+}
+
+impl NameEntry {
+    fn change_letter(&mut self, delta: i8) {
+        let letter = self.letters[self.position] as u8 - b'A';
+        let letter = (letter as i8 + delta).rem_euclid(26) as u8;
+        self.letters[self.position] = (b'A' + letter) as char;
+    }
+
+    fn type_letter(&mut self, letter: char) {
+        if letter.is_ascii_alphabetic() {
+            self.letters[self.position] = letter.to_ascii_uppercase();
+            self.position = (self.position + 1).min(NAME_LENGTH - 1);
+        }
+    }
+
+    fn label(&self) -> String {
+        self.letters
+            .iter()
+            .enumerate()
+            .map(|(i, letter)| {
+                if i == self.position {
+                    format!("[{}]", letter)
+                } else {
+                    format!(" {} ", letter)
+                }
+            })
+            .collect()
+    }
+
+    fn name(&self) -> String {
+        self.letters.iter().collect()
+    }
+}
+
+struct InputDelay(Timer);
+
+fn format_time(seconds: f32) -> String {
+    let seconds = seconds as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn summary(mode: GameMode, practice: bool, score: &Score, stats: &RunStats) -> Vec<String> {
+    let accuracy = match stats.accuracy() {
+        Some(accuracy) => format!("{:.0}%", accuracy * 100.0),
+        None => "-".to_string(),
+    };
+    let destroyed = if stats.destroyed.is_empty() {
+        "-".to_string()
+    } else {
+        stats
+            .destroyed
+            .iter()
+            .map(|(size, count)| format!("{}x size {}", count, size))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mode = if practice {
+        format!("{} (practice)", mode.name())
+    } else {
+        mode.name().to_string()
+    };
+    vec![
+        format!("Mode: {}", mode),
+        format!("Score: {}", score.score),
+        format!("Wave: {}", stats.wave),
+        format!("Accuracy: {}", accuracy),
+        format!("Destroyed: {}", destroyed),
+        format!("Time: {}", format_time(stats.time_survived)),
+        format!("Best combo: {}", stats.best_combo),
+    ]
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_game_over_screen(
+    commands: &mut Commands,
+    style: &MenuStyle,
+    mode: GameMode,
+    run: &CurrentRun,
+    daily: &DailyChallenge,
+    score: &Score,
+    stats: &RunStats,
+    name_entry: Option<&NameEntry>,
+) {
+    spawn_menu(commands, style, GameOverScreen, "Game Over", |parent| {
+        for line in summary(mode, run.practice, score, stats) {
+            parent
+                .spawn_bundle(style.text(&line, SUMMARY_FONT_SIZE, style.text_color))
+                .insert(ScaledText {
+                    size: SUMMARY_FONT_SIZE,
+                });
+        }
+        if mode == GameMode::Daily && !run.practice {
+            let result = DailyResult {
+                score: score.score,
+                wave: stats.wave,
+            };
+            parent
+                .spawn_bundle(style.text(
+                    &daily.share(&result),
+                    SUMMARY_FONT_SIZE,
+                    style.highlight_color,
+                ))
+                .insert(ScaledText {
+                    size: SUMMARY_FONT_SIZE,
+                });
+        }
+        match name_entry {
+            Some(entry) => {
+                parent
+                    .spawn_bundle(style.text(
+                        "New high score! Enter your name:",
+                        SUMMARY_FONT_SIZE,
+                        style.highlight_color,
+                    ))
+                    .insert(ScaledText {
+                        size: SUMMARY_FONT_SIZE,
+                    });
+                parent
+                    .spawn_bundle(style.text(
+                        &entry.label(),
+                        2.0 * SUMMARY_FONT_SIZE,
+                        style.highlight_color,
+                    ))
+                    .insert(ScaledText {
+                        size: 2.0 * SUMMARY_FONT_SIZE,
+                    })
+                    .insert(NameEntryText);
+            }
+            None => {
+                spawn_item(parent, style, "Retry", 0, true, GameOverAction::Retry);
+                spawn_item(
+                    parent,
+                    style,
+                    "Main menu",
+                    1,
+                    true,
+                    GameOverAction::MainMenu,
+                );
+            }
+        }
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn setup_game_over(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    mode: Res<CurrentMode>,
+    run: Res<CurrentRun>,
+    daily: Res<DailyChallenge>,
+    high_scores: Res<HighScoreTables>,
+    score: Res<Score>,
+    stats: Res<RunStats>,
+) {
+    let style = MenuStyle::new(&asset_server, &config, &settings, &theme);
+    let name_entry = if !run.practice && high_scores.get(mode.0).qualifies(score.score) {
+        Some(NameEntry::default())
+    } else {
+        None
+    };
+    spawn_game_over_screen(
+        &mut commands,
+        &style,
+        mode.0,
+        &run,
+        &daily,
+        &score,
+        &stats,
+        name_entry.as_ref(),
+    );
+    if let Some(entry) = name_entry {
+        commands.insert_resource(entry);
+    }
+    commands.insert_resource(InputDelay(Timer::from_seconds(INPUT_DELAY, false)));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn name_entry_input(
+    mut commands: Commands,
+    mut input: ResMut<MenuInput>,
+    mut characters: EventReader<ReceivedCharacter>,
+    keyboard_input: Res<Input<KeyCode>>,
+    delay: Res<InputDelay>,
+    entry: Option<ResMut<NameEntry>>,
+    (mode, run, daily): (Res<CurrentMode>, Res<CurrentRun>, Res<DailyChallenge>),
+    mut high_scores: ResMut<HighScoreTables>,
+    mut texts: Query<&mut Text, With<NameEntryText>>,
+    screens: Query<Entity, With<GameOverScreen>>,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    score: Res<Score>,
+    stats: Res<RunStats>,
+) {
+    let mut entry = match entry {
+        Some(entry) => entry,
+        None => return,
+    };
+    // Skip letters typed, while the screen was not ready yet
+    let typed = characters
+        .iter()
+        .map(|event| event.char)
+        .collect::<Vec<_>>();
+    if !delay.0.finished() {
+        return;
+    }
+    // W, A, S and D are letters of the name here, not directions
+    let letter_keys = [KeyCode::W, KeyCode::A, KeyCode::S, KeyCode::D];
+    if !typed.is_empty()
+        || letter_keys
+            .iter()
+            .any(|key| keyboard_input.just_pressed(*key))
+    {
+        input.up = false;
+        input.down = false;
+        input.left = false;
+        input.right = false;
+    }
+    for letter in typed {
+        entry.type_letter(letter);
+    }
+    if std::mem::take(&mut input.up) {
+        entry.change_letter(1);
+    } else if std::mem::take(&mut input.down) {
+        entry.change_letter(-1);
+    }
+    let delta = input.take_horizontal() as i32;
+    entry.position = (entry.position as i32 + delta).clamp(0, NAME_LENGTH as i32 - 1) as usize;
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = entry.label();
+    }
+
+    if !input.take_confirm() {
+        return;
+    }
+    let table = high_scores.get_mut(mode.0);
+    table.insert(HighScore {
+        name: entry.name(),
+        score: score.score,
+        wave: stats.wave,
+    });
+    table.save(&config, mode.0);
+    commands.remove_resource::<NameEntry>();
+    for screen in screens.iter() {
+        commands.entity(screen).despawn_recursive();
+    }
+    let style = MenuStyle::new(&asset_server, &config, &settings, &theme);
+    spawn_game_over_screen(
+        &mut commands,
+        &style,
+        mode.0,
+        &run,
+        &daily,
+        &score,
+        &stats,
+        None,
+    );
+}
+
+fn game_over_actions(
+    mut input: ResMut<MenuInput>,
+    cursor: Res<MenuCursor>,
+    items: Query<(&MenuItem, &GameOverAction)>,
+    mut run: ResMut<CurrentRun>,
+    mut state: ResMut<State<GameState>>,
+) {
+    match activated_item(&mut input, &cursor, &items) {
+        Some(GameOverAction::Retry) => {
+            run.next_seed = Some(run.seed);
+            let _ = state.set(GameState::NewRun);
+        }
+        Some(GameOverAction::MainMenu) => {
+            let _ = state.set(GameState::MainMenu);
+        }
+        None => return,
+    }
+    input.clear();
+}
+
+/// Drops the player's input until the [`InputDelay`] elapses.
+fn delay_input(time: Res<Time>, mut delay: ResMut<InputDelay>, mut input: ResMut<MenuInput>) {
+    if !delay.0.tick(time.delta()).finished() {
+        input.clear();
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+struct DelayInputSystem;
+
+#[derive(Default)]
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<DailyChallenge>()
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(setup_game_over))
+            .add_system_set(
+                SystemSet::on_update(GameState::GameOver)
+                    .label(MenuSystem::Actions)
+                    .after(MenuSystem::Navigation)
+                    .with_system(delay_input.label(DelayInputSystem))
+                    .with_system(name_entry_input.after(DelayInputSystem))
+                    .with_system(game_over_actions.after(DelayInputSystem)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::GameOver)
+                    .with_system(despawn_screen::<GameOverScreen>),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_letters_advance_to_the_last_slot() {
+        let mut entry = NameEntry::default();
+        for letter in "wasd".chars() {
+            entry.type_letter(letter);
+        }
+        assert_eq!(entry.name(), "WAD");
+        assert_eq!(entry.position, NAME_LENGTH - 1);
+    }
+
+    #[test]
+    fn other_characters_are_ignored() {
+        let mut entry = NameEntry::default();
+        entry.type_letter('1');
+        entry.type_letter(' ');
+        assert_eq!(entry.name(), "AAA");
+        assert_eq!(entry.position, 0);
+    }
+
+    #[test]
+    fn changed_letter_wraps_around_the_alphabet() {
+        let mut entry = NameEntry::default();
+        entry.change_letter(-1);
+        assert_eq!(entry.letters[0], 'Z');
+        entry.change_letter(1);
+        assert_eq!(entry.letters[0], 'A');
+    }
+}
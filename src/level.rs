@@ -0,0 +1,199 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, Range, Sub};
+
+use super::{
+    config::{GameConfig, SpawnerConfig, WorldConfig},
+    storage,
+};
+
+/// Directory in the storage with the levels.
+const LEVELS_DIRECTORY: &str = "levels";
+
+/// Seconds between spawned cuboids allowed in a level, the editor's minimum is the same.
+const SPAWNER_COOLDOWNS: Range<f32> = 0.1..3600.0;
+/// Angles (in degrees) of the spawned cuboids allowed in a level, as in the editor.
+const SPAWNER_ANGLES: Range<u16> = 0..360;
+/// Speeds of the spawned cuboids allowed in a level, as in the editor.
+const SPAWNER_SPEEDS: Range<u8> = 1..255;
+
+/// Axis aligned wall or trap.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Block {
+    pub position: Vec2,
+    /// Half of the block's width and height.
+    pub half_size: Vec2,
+}
+
+impl Block {
+    pub fn new(position: Vec2, half_size: Vec2) -> Self {
+        Self {
+            position,
+            half_size,
+        }
+    }
+}
+
+/// Layout of the world, which can be saved as RON and edited in the level editor.
+///
+/// Positions are in the configured arena's units, see [`crate::world::Arena`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Level {
+    pub name: String,
+    /// Bodies bouncing cuboids and stopping the ship.
+    pub walls: Vec<Block>,
+    /// Sensors removing cuboids and bullets.
+    pub traps: Vec<Block>,
+    pub spawners: Vec<SpawnerConfig>,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self::from(&WorldConfig::default())
+    }
+}
+
+impl From<&WorldConfig> for Level {
+    /// Level with the walls, traps and spawners of the `config`.
+    fn from(config: &WorldConfig) -> Self {
+        let wall = |x| Block::new(Vec2::new(x, 0.0), Vec2::new(1.0, 150.0));
+        let trap = |y| Block::new(Vec2::new(0.0, y), Vec2::new(config.wall_position, 1.0));
+        Self {
+            name: "Default".to_string(),
+            walls: vec![wall(-config.wall_position), wall(config.wall_position)],
+            traps: vec![trap(-config.trap_position), trap(config.trap_position)],
+            spawners: config.spawners.clone(),
+        }
+    }
+}
+
+impl Level {
+    fn storage_name(name: &str) -> String {
+        format!("{}/{}", LEVELS_DIRECTORY, name)
+    }
+
+    /// Loads the level saved as `name`, fixing spawners which could not spawn cuboids.
+    pub fn load(config: &GameConfig, name: &str) -> Option<Self> {
+        let mut level: Self = storage::load(&config.storage, &Self::storage_name(name))?;
+        if level.validate(&config.cuboids.sizes) {
+            warn!("Level {} had invalid spawners, which were clamped", name);
+        }
+        Some(level)
+    }
+
+    /// Keeps the spawners' cooldowns and ranges within the editor's limits and the generated
+    /// cuboid `sizes`. Returns whether any spawner changed.
+    pub fn validate(&mut self, sizes: &Range<u8>) -> bool {
+        let mut changed = false;
+        for spawner in &mut self.spawners {
+            let cooldown = if spawner.cooldown.is_nan() {
+                SPAWNER_COOLDOWNS.start
+            } else {
+                spawner
+                    .cooldown
+                    .clamp(SPAWNER_COOLDOWNS.start, SPAWNER_COOLDOWNS.end)
+            };
+            let valid = SpawnerConfig {
+                position: spawner.position,
+                cooldown,
+                angle_range: spawner
+                    .angle_range
+                    .as_ref()
+                    .map(|range| clamp_range(range, &SPAWNER_ANGLES)),
+                size_range: spawner
+                    .size_range
+                    .as_ref()
+                    .map(|range| clamp_range(range, sizes)),
+                speed_range: spawner
+                    .speed_range
+                    .as_ref()
+                    .map(|range| clamp_range(range, &SPAWNER_SPEEDS)),
+            };
+            if *spawner != valid {
+                *spawner = valid;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Saves the level under its name.
+    pub fn save(&self, config: &GameConfig) {
+        storage::save(&config.storage, &Self::storage_name(&self.name), self);
+    }
+}
+
+/// Non-empty part of the `range` within the non-empty `limits`.
+fn clamp_range<T>(range: &Range<T>, limits: &Range<T>) -> Range<T>
+where
+    T: Copy + Ord + Add<Output = T> + Sub<Output = T> + From<u8>,
+{
+    let one = T::from(1);
+    let start = range.start.clamp(limits.start, limits.end - one);
+    start..range.end.clamp(start + one, limits.end)
+}
+
+/// Level played in the next run, the world is rebuilt when it changes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurrentLevel(pub Level);
+
+impl FromWorld for CurrentLevel {
+    fn from_world(world: &mut World) -> Self {
+        let config = world.get_resource_or_insert_with(GameConfig::default);
+        Self(Level::from(&config.world))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(spawner: SpawnerConfig) -> Level {
+        Level {
+            spawners: vec![spawner],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn valid_spawners_are_kept() {
+        let mut valid = level(SpawnerConfig {
+            angle_range: Some(240..300),
+            size_range: Some(1..4),
+            speed_range: Some(10..50),
+            ..SpawnerConfig::new(Vec2::ZERO, 1.0)
+        });
+        let original = valid.clone();
+        assert!(!valid.validate(&(1..8)));
+        assert_eq!(valid, original);
+    }
+
+    #[test]
+    fn invalid_spawners_are_clamped() {
+        let mut invalid = level(SpawnerConfig {
+            angle_range: Some(300..240),
+            size_range: Some(6..20),
+            speed_range: Some(0..0),
+            ..SpawnerConfig::new(Vec2::ZERO, -1.0)
+        });
+        assert!(invalid.validate(&(1..8)));
+        let spawner = &invalid.spawners[0];
+        assert_eq!(spawner.cooldown, SPAWNER_COOLDOWNS.start);
+        assert_eq!(spawner.angle_range, Some(300..301));
+        assert_eq!(spawner.size_range, Some(6..8));
+        assert_eq!(spawner.speed_range, Some(1..2));
+    }
+
+    #[test]
+    fn sizes_above_the_generated_ones_are_clamped_to_the_largest() {
+        let mut invalid = level(SpawnerConfig {
+            size_range: Some(9..12),
+            cooldown: f32::NAN,
+            ..SpawnerConfig::new(Vec2::ZERO, 1.0)
+        });
+        assert!(invalid.validate(&(1..8)));
+        assert_eq!(invalid.spawners[0].size_range, Some(7..8));
+        assert_eq!(invalid.spawners[0].cooldown, SPAWNER_COOLDOWNS.start);
+    }
+}
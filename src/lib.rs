@@ -7,28 +7,104 @@ pub mod physics_layers {
         InteractionGroups::new(0b0000_0000_0000_0001, 0b0000_0000_0000_0001);
     pub const TRAPS: InteractionGroups =
         InteractionGroups::new(0b0000_0000_0000_0010, 0b0000_0000_0000_0010);
+    /// Ship hits walls and cuboids, but not its own bullets.
+    pub const SHIP: InteractionGroups =
+        InteractionGroups::new(0b0000_0000_0000_1001, !0b0000_0000_0000_0100);
+    /// Bullets hit cuboids (which belong to all groups) and traps only.
+    pub const BULLETS: InteractionGroups =
+        InteractionGroups::new(0b0000_0000_0000_0110, 0b0000_0000_0000_0010);
+    /// Cuboids of the wrapped arena pass through the walls.
+    pub const WRAPPING: InteractionGroups =
+        InteractionGroups::new(u32::MAX, !0b0000_0000_0000_0001);
     pub const ALL: InteractionGroups = InteractionGroups::all();
 }
 
-mod assets;
-mod cuboids;
-mod scoring;
-mod ship;
-mod trap;
-mod ui;
-mod world;
+pub mod achievements;
+pub mod assets;
+pub mod attract;
+pub mod background;
+pub mod bot;
+pub mod camera;
+pub mod config;
+pub mod console;
+pub mod controller;
+pub mod cuboids;
+pub mod daily;
+pub mod debug;
+pub mod editor;
+pub mod env;
+pub mod game_over;
+pub mod level;
+pub mod menu;
+pub mod mode;
+pub mod mutators;
+pub mod particles;
+pub mod pause;
+pub mod perf;
+pub mod pool;
+pub mod scoring;
+pub mod settings;
+pub mod shapes;
+pub mod ship;
+pub mod sound;
+pub mod state;
+pub mod storage;
+pub mod theme;
+pub mod trap;
+pub mod ui;
+pub mod weapon;
+pub mod world;
 
-pub struct GamePlugins;
+pub use config::GameConfig;
+
+/// All game plugins, configured with single [`GameConfig`].
+///
+/// Plugins can be swapped with the [`PluginGroupBuilder`] API, e.g.
+/// `app.add_plugins_with(GamePlugins::default(), |group| group.disable::<ship::ShipPlugin>())`.
+#[derive(Default)]
+pub struct GamePlugins {
+    config: GameConfig,
+}
+
+impl GamePlugins {
+    pub fn with_config(config: GameConfig) -> Self {
+        Self { config }
+    }
+}
 
 impl PluginGroup for GamePlugins {
     fn build(&mut self, group: &mut PluginGroupBuilder) {
         group
+            .add(crate::config::ConfigPlugin {
+                config: self.config.clone(),
+            })
+            .add(crate::state::StatePlugin::default())
+            .add(crate::settings::SettingsPlugin::default())
+            .add(crate::console::ConsolePlugin::default())
+            .add(crate::debug::DebugPlugin::default())
+            .add(crate::perf::PerfPlugin::default())
+            .add(crate::theme::ThemePlugin::default())
             .add(crate::assets::AssetsPlugin::default())
+            .add(crate::mode::ModePlugin::default())
+            .add(crate::daily::DailyPlugin::default())
+            .add(crate::mutators::MutatorsPlugin::default())
             .add(crate::scoring::ScoringPlugin::default())
             .add(crate::ui::UiPlugin::default())
+            .add(crate::menu::MenuPlugin::default())
+            .add(crate::pause::PausePlugin::default())
+            .add(crate::game_over::GameOverPlugin::default())
+            .add(crate::pool::PoolPlugin::default())
             .add(crate::trap::TrapsPlugin::default())
             .add(crate::cuboids::CuboidsPlugin::default())
             .add(crate::world::WorldPlugin::default())
-            .add(crate::ship::ShipPlugin::default());
+            .add(crate::camera::CameraPlugin::default())
+            .add(crate::editor::EditorPlugin::default())
+            .add(crate::controller::ControllerPlugin::default())
+            .add(crate::ship::ShipPlugin::default())
+            .add(crate::weapon::WeaponPlugin::default())
+            .add(crate::particles::ParticlesPlugin::default())
+            .add(crate::achievements::AchievementsPlugin::default())
+            .add(crate::attract::AttractPlugin::default())
+            .add(crate::sound::SoundPlugin::default());
     }
 }
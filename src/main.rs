@@ -11,9 +11,8 @@ fn main() {
             title: "rsCuboids".to_string(),
             ..Default::default()
         })
-        .insert_resource(ClearColor(Color::rgb(0.1, 0.1, 0.1)))
         .add_plugins(DefaultPlugins)
         .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
-        .add_plugins(GamePlugins)
+        .add_plugins(GamePlugins::default())
         .run();
 }
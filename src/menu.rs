@@ -0,0 +1,826 @@
+use bevy::{app::AppExit, prelude::*};
+
+use super::{
+    config::GameConfig,
+    mode::{CurrentMode, GameMode},
+    mutators::{Mutator, Mutators},
+    scoring::{HighScoreTables, HighScores},
+    settings::{Setting, Settings},
+    state::{CurrentRun, GameState},
+    theme::{Theme, ThemeSystem, Themes},
+};
+
+const TITLE_FONT_SIZE: f32 = 48.0;
+const ITEM_FONT_SIZE: f32 = 28.0;
+
+/// Menu commands read from keyboard and gamepads in the current frame.
+///
+/// Screens consume the commands they react to, so a single key press is not
+/// handled again by the screen shown after the state transition.
+#[derive(Debug, Default)]
+pub struct MenuInput {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    pub confirm: bool,
+    pub back: bool,
+    /// Pauses the game.
+    pub pause: bool,
+}
+
+impl MenuInput {
+    /// Drops all commands, e.g. after they caused transition to another screen.
+    pub fn clear(&mut self) {
+        *self = MenuInput::default();
+    }
+
+    pub fn take_confirm(&mut self) -> bool {
+        std::mem::take(&mut self.confirm)
+    }
+
+    pub fn take_back(&mut self) -> bool {
+        std::mem::take(&mut self.back)
+    }
+
+    /// Consumes horizontal movement, returning -1 for left and 1 for right.
+    pub fn take_horizontal(&mut self) -> i8 {
+        let left = std::mem::take(&mut self.left);
+        let right = std::mem::take(&mut self.right);
+        right as i8 - left as i8
+    }
+}
+
+fn read_menu_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    mut input: ResMut<MenuInput>,
+) {
+    let key = |keys: &[KeyCode]| keys.iter().any(|k| keyboard_input.just_pressed(*k));
+    let button = |buttons: &[GamepadButtonType]| {
+        gamepad_input
+            .get_just_pressed()
+            .any(|pressed| buttons.contains(&pressed.1))
+    };
+
+    *input = MenuInput {
+        up: key(&[KeyCode::Up, KeyCode::W]) || button(&[GamepadButtonType::DPadUp]),
+        down: key(&[KeyCode::Down, KeyCode::S]) || button(&[GamepadButtonType::DPadDown]),
+        left: key(&[KeyCode::Left, KeyCode::A]) || button(&[GamepadButtonType::DPadLeft]),
+        right: key(&[KeyCode::Right, KeyCode::D]) || button(&[GamepadButtonType::DPadRight]),
+        confirm: key(&[KeyCode::Return, KeyCode::Space])
+            || button(&[GamepadButtonType::South, GamepadButtonType::Start]),
+        back: key(&[KeyCode::Escape, KeyCode::Back])
+            || button(&[GamepadButtonType::East, GamepadButtonType::Select]),
+        pause: key(&[KeyCode::Escape, KeyCode::P]) || button(&[GamepadButtonType::Start]),
+    };
+}
+
+/// Selectable entry of the currently shown menu.
+#[derive(Debug, Component)]
+pub struct MenuItem {
+    /// Position of the item in the menu.
+    pub index: usize,
+    pub enabled: bool,
+}
+
+/// Index of the selected [`MenuItem`].
+#[derive(Debug, Default)]
+pub struct MenuCursor {
+    pub selected: usize,
+}
+
+/// Text of the menu or HUD, which follows the text scale setting and theme.
+#[derive(Debug, Component)]
+pub struct ScaledText {
+    pub size: f32,
+}
+
+fn navigate_menu(
+    mut input: ResMut<MenuInput>,
+    mut cursor: ResMut<MenuCursor>,
+    theme: Res<Theme>,
+    interactions: Query<(&MenuItem, &Interaction), Changed<Interaction>>,
+    mut items: Query<(&MenuItem, &mut Text)>,
+) {
+    let mut enabled = items
+        .iter()
+        .filter(|(item, _)| item.enabled)
+        .map(|(item, _)| item.index)
+        .collect::<Vec<_>>();
+    if enabled.is_empty() {
+        return;
+    }
+    enabled.sort_unstable();
+
+    for (item, interaction) in interactions.iter() {
+        if !item.enabled {
+            continue;
+        }
+        match interaction {
+            Interaction::Clicked => {
+                cursor.selected = item.index;
+                input.confirm = true;
+            }
+            Interaction::Hovered => cursor.selected = item.index,
+            Interaction::None => {}
+        }
+    }
+
+    let position = enabled
+        .iter()
+        .position(|&index| index == cursor.selected)
+        .unwrap_or(0);
+    let count = enabled.len();
+    let position = if std::mem::take(&mut input.up) {
+        (position + count - 1) % count
+    } else if std::mem::take(&mut input.down) {
+        (position + 1) % count
+    } else {
+        position
+    };
+    if cursor.selected != enabled[position] {
+        cursor.selected = enabled[position];
+    }
+
+    for (item, mut text) in items.iter_mut() {
+        let color = if !item.enabled {
+            theme.disabled
+        } else if item.index == cursor.selected {
+            theme.highlight
+        } else {
+            theme.text
+        };
+        for section in text.sections.iter_mut() {
+            section.style.color = color;
+        }
+    }
+}
+
+fn scale_menu_text(settings: Res<Settings>, mut texts: Query<(&ScaledText, &mut Text)>) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (scaled, mut text) in texts.iter_mut() {
+        for section in text.sections.iter_mut() {
+            section.style.font_size = scaled.size * settings.accessibility.text_scale;
+        }
+    }
+}
+
+/// Switches font and colours of the menus and HUD to the new [`Theme`].
+fn restyle_text(
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    theme: Res<Theme>,
+    mut texts: Query<(&mut Text, Option<&MenuTitle>, Option<&MenuItem>), With<ScaledText>>,
+    mut roots: Query<&mut UiColor, With<MenuRoot>>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+    let font: Handle<Font> = asset_server.load(theme.font(&config));
+    for (mut text, title, item) in texts.iter_mut() {
+        for section in text.sections.iter_mut() {
+            section.style.font = font.clone();
+            // Colours of the items are updated by the navigation
+            if title.is_some() {
+                section.style.color = theme.highlight;
+            } else if item.is_none() {
+                section.style.color = theme.text;
+            }
+        }
+    }
+    for mut color in roots.iter_mut() {
+        color.0 = theme.overlay;
+    }
+}
+
+/// Returns action of the selected, enabled item, if the player confirmed it.
+pub fn activated_item<A: Component + Copy>(
+    input: &mut MenuInput,
+    cursor: &MenuCursor,
+    items: &Query<(&MenuItem, &A)>,
+) -> Option<A> {
+    let action = items
+        .iter()
+        .find(|(item, _)| item.enabled && item.index == cursor.selected)
+        .map(|(_, action)| *action)?;
+    if input.take_confirm() {
+        Some(action)
+    } else {
+        None
+    }
+}
+
+/// Font, colours and text scale of the menus.
+pub struct MenuStyle {
+    pub font: Handle<Font>,
+    pub text_scale: f32,
+    pub text_color: Color,
+    pub highlight_color: Color,
+    pub disabled_color: Color,
+    pub overlay_color: Color,
+}
+
+impl MenuStyle {
+    pub fn new(
+        asset_server: &AssetServer,
+        config: &GameConfig,
+        settings: &Settings,
+        theme: &Theme,
+    ) -> Self {
+        Self {
+            font: asset_server.load(theme.font(config)),
+            text_scale: settings.accessibility.text_scale,
+            text_color: theme.text,
+            highlight_color: theme.highlight,
+            disabled_color: theme.disabled,
+            overlay_color: theme.overlay,
+        }
+    }
+
+    pub fn text(&self, value: &str, size: f32, color: Color) -> TextBundle {
+        TextBundle {
+            style: Style {
+                margin: Rect::all(Val::Px(6.0)),
+                ..Default::default()
+            },
+            text: Text::with_section(
+                value.to_string(),
+                TextStyle {
+                    font: self.font.clone(),
+                    font_size: size * self.text_scale,
+                    color,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Component)]
+struct MenuRoot;
+
+#[derive(Debug, Component)]
+struct MenuTitle;
+
+/// Spawns full screen menu with a title and items added by `spawn_items`.
+pub fn spawn_menu<M: Component>(
+    commands: &mut Commands,
+    style: &MenuStyle,
+    marker: M,
+    title: &str,
+    spawn_items: impl FnOnce(&mut ChildBuilder),
+) {
+    commands.insert_resource(MenuCursor::default());
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: UiColor(style.overlay_color),
+            ..Default::default()
+        })
+        .insert(MenuRoot)
+        .insert(marker)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(style.text(title, TITLE_FONT_SIZE, style.highlight_color))
+                .insert(ScaledText {
+                    size: TITLE_FONT_SIZE,
+                })
+                .insert(MenuTitle);
+            spawn_items(parent);
+        });
+}
+
+/// Spawns selectable menu item with the screen specific `action`.
+pub fn spawn_item<A: Component>(
+    parent: &mut ChildBuilder,
+    style: &MenuStyle,
+    label: &str,
+    index: usize,
+    enabled: bool,
+    action: A,
+) {
+    let color = if enabled {
+        style.text_color
+    } else {
+        style.disabled_color
+    };
+    parent
+        .spawn_bundle(style.text(label, ITEM_FONT_SIZE, color))
+        .insert(Interaction::default())
+        .insert(MenuItem { index, enabled })
+        .insert(ScaledText {
+            size: ITEM_FONT_SIZE,
+        })
+        .insert(action);
+}
+
+/// Despawns screen marked with `M`.
+pub fn despawn_screen<M: Component>(mut commands: Commands, screens: Query<Entity, With<M>>) {
+    for entity in screens.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[derive(Debug, Component)]
+struct MainMenuScreen;
+
+#[derive(Debug, Clone, Copy, Component)]
+enum MainMenuAction {
+    Play,
+    Continue,
+    Modes,
+    HighScores,
+    Editor,
+    Settings,
+    Quit,
+}
+
+fn spawn_main_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    run: Res<CurrentRun>,
+) {
+    let style = MenuStyle::new(&asset_server, &config, &settings, &theme);
+    let items = [
+        ("Play", MainMenuAction::Play, true),
+        ("Continue", MainMenuAction::Continue, run.active),
+        ("Modes", MainMenuAction::Modes, true),
+        ("High Scores", MainMenuAction::HighScores, true),
+        ("Level Editor", MainMenuAction::Editor, true),
+        ("Settings", MainMenuAction::Settings, true),
+        ("Quit", MainMenuAction::Quit, true),
+    ];
+    spawn_menu(
+        &mut commands,
+        &style,
+        MainMenuScreen,
+        "rsCuboids",
+        |parent| {
+            for (index, (label, action, enabled)) in items.into_iter().enumerate() {
+                spawn_item(parent, &style, label, index, enabled, action);
+            }
+        },
+    );
+}
+
+fn main_menu_actions(
+    mut input: ResMut<MenuInput>,
+    cursor: Res<MenuCursor>,
+    items: Query<(&MenuItem, &MainMenuAction)>,
+    mut state: ResMut<State<GameState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let action = activated_item(&mut input, &cursor, &items);
+    match action {
+        Some(MainMenuAction::Play) => {
+            let _ = state.set(GameState::NewRun);
+        }
+        Some(MainMenuAction::Continue) => {
+            let _ = state.set(GameState::InGame);
+        }
+        Some(MainMenuAction::Modes) => {
+            let _ = state.set(GameState::Modes);
+        }
+        Some(MainMenuAction::HighScores) => {
+            let _ = state.push(GameState::HighScores);
+        }
+        Some(MainMenuAction::Editor) => {
+            let _ = state.set(GameState::Editor);
+        }
+        Some(MainMenuAction::Settings) => {
+            let _ = state.push(GameState::Settings);
+        }
+        Some(MainMenuAction::Quit) => exit.send(AppExit),
+        None => {}
+    }
+    if action.is_some() {
+        input.clear();
+    }
+}
+
+#[derive(Debug, Component)]
+struct SettingsScreen;
+
+#[derive(Debug, Clone, Copy, Component)]
+enum SettingsAction {
+    Adjust(Setting),
+    Back,
+}
+
+fn spawn_settings_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+) {
+    let style = MenuStyle::new(&asset_server, &config, &settings, &theme);
+    spawn_menu(
+        &mut commands,
+        &style,
+        SettingsScreen,
+        "Settings",
+        |parent| {
+            for (index, setting) in Setting::ALL.iter().enumerate() {
+                let label = settings.label(*setting);
+                spawn_item(
+                    parent,
+                    &style,
+                    &label,
+                    index,
+                    true,
+                    SettingsAction::Adjust(*setting),
+                );
+            }
+            spawn_item(
+                parent,
+                &style,
+                "Back",
+                Setting::ALL.len(),
+                true,
+                SettingsAction::Back,
+            );
+        },
+    );
+}
+
+fn adjust_setting(settings: &mut Settings, themes: &Themes, setting: Setting, delta: i8) {
+    if setting == Setting::Theme {
+        settings.display.theme = themes.cycle(&settings.display.theme, delta);
+    } else {
+        settings.adjust(setting, delta);
+    }
+}
+
+fn settings_menu_actions(
+    mut input: ResMut<MenuInput>,
+    cursor: Res<MenuCursor>,
+    items: Query<(&MenuItem, &SettingsAction)>,
+    themes: Res<Themes>,
+    mut settings: ResMut<Settings>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let selected = items
+        .iter()
+        .find(|(item, _)| item.index == cursor.selected)
+        .map(|(_, action)| *action);
+    let delta = input.take_horizontal();
+    if let (Some(SettingsAction::Adjust(setting)), true) = (selected, delta != 0) {
+        adjust_setting(&mut settings, &themes, setting, delta);
+    }
+
+    let back = match activated_item(&mut input, &cursor, &items) {
+        Some(SettingsAction::Adjust(setting)) => {
+            adjust_setting(&mut settings, &themes, setting, 1);
+            false
+        }
+        Some(SettingsAction::Back) => true,
+        None => false,
+    };
+    if input.take_back() || back {
+        let _ = state.pop();
+        input.clear();
+    }
+}
+
+fn update_settings_labels(settings: Res<Settings>, mut items: Query<(&SettingsAction, &mut Text)>) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (action, mut text) in items.iter_mut() {
+        if let SettingsAction::Adjust(setting) = action {
+            text.sections[0].value = settings.label(*setting);
+        }
+    }
+}
+
+#[derive(Debug, Component)]
+struct ModesScreen;
+
+#[derive(Debug, Component)]
+struct ModeDescriptionText;
+
+#[derive(Debug, Clone, Copy, Component)]
+enum ModesAction {
+    Play(GameMode),
+    Toggle(Mutator),
+    Back,
+}
+
+fn mutator_label(mutators: &Mutators, mutator: Mutator) -> String {
+    let state = if mutators.is_enabled(mutator) {
+        "On"
+    } else {
+        "Off"
+    };
+    format!("{}: {}", mutator.name(), state)
+}
+
+fn spawn_modes_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    mode: Res<CurrentMode>,
+    mutators: Res<Mutators>,
+) {
+    let style = MenuStyle::new(&asset_server, &config, &settings, &theme);
+    spawn_menu(&mut commands, &style, ModesScreen, "Modes", |parent| {
+        for (index, mode) in GameMode::ALL.iter().enumerate() {
+            spawn_item(
+                parent,
+                &style,
+                mode.name(),
+                index,
+                true,
+                ModesAction::Play(*mode),
+            );
+        }
+        for (index, mutator) in Mutator::ALL.iter().enumerate() {
+            spawn_item(
+                parent,
+                &style,
+                &mutator_label(&mutators, *mutator),
+                GameMode::ALL.len() + index,
+                true,
+                ModesAction::Toggle(*mutator),
+            );
+        }
+        spawn_item(
+            parent,
+            &style,
+            "Back",
+            GameMode::ALL.len() + Mutator::ALL.len(),
+            true,
+            ModesAction::Back,
+        );
+        parent
+            .spawn_bundle(style.text(mode.0.description(), ITEM_FONT_SIZE, style.disabled_color))
+            .insert(ScaledText {
+                size: ITEM_FONT_SIZE,
+            })
+            .insert(ModeDescriptionText);
+    });
+    // Selection starts at the last played mode
+    let selected = GameMode::ALL.iter().position(|other| *other == mode.0);
+    commands.insert_resource(MenuCursor {
+        selected: selected.unwrap_or_default(),
+    });
+}
+
+fn modes_menu_actions(
+    mut input: ResMut<MenuInput>,
+    cursor: Res<MenuCursor>,
+    items: Query<(&MenuItem, &ModesAction)>,
+    mut mode: ResMut<CurrentMode>,
+    mut mutators: ResMut<Mutators>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let back = match activated_item(&mut input, &cursor, &items) {
+        Some(ModesAction::Play(selected)) => {
+            mode.0 = selected;
+            let _ = state.set(GameState::NewRun);
+            input.clear();
+            return;
+        }
+        Some(ModesAction::Toggle(mutator)) => {
+            mutators.toggle(mutator);
+            false
+        }
+        Some(ModesAction::Back) => true,
+        None => false,
+    };
+    if input.take_back() || back {
+        let _ = state.set(GameState::MainMenu);
+        input.clear();
+    }
+}
+
+/// Describes the selected mode or mutator.
+fn update_mode_description(
+    cursor: Res<MenuCursor>,
+    items: Query<(&MenuItem, &ModesAction)>,
+    mut texts: Query<&mut Text, With<ModeDescriptionText>>,
+) {
+    if !cursor.is_changed() {
+        return;
+    }
+    let description = items
+        .iter()
+        .filter(|(item, _)| item.index == cursor.selected)
+        .find_map(|(_, action)| match action {
+            ModesAction::Play(mode) => Some(mode.description()),
+            ModesAction::Toggle(mutator) => Some(mutator.description()),
+            ModesAction::Back => None,
+        })
+        .unwrap_or_default();
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = description.to_string();
+    }
+}
+
+fn update_mutator_labels(mutators: Res<Mutators>, mut items: Query<(&ModesAction, &mut Text)>) {
+    if !mutators.is_changed() {
+        return;
+    }
+    for (action, mut text) in items.iter_mut() {
+        if let ModesAction::Toggle(mutator) = action {
+            text.sections[0].value = mutator_label(&mutators, *mutator);
+        }
+    }
+}
+
+/// High scores screen showing the table of the mode.
+#[derive(Debug, Component)]
+struct HighScoresScreen(GameMode);
+
+#[derive(Debug, Clone, Copy, Component)]
+struct HighScoresBack;
+
+fn spawn_high_scores_table(
+    commands: &mut Commands,
+    style: &MenuStyle,
+    mode: GameMode,
+    high_scores: &HighScores,
+) {
+    let title = format!("< {} >", mode.name());
+    spawn_menu(commands, style, HighScoresScreen(mode), &title, |parent| {
+        if high_scores.entries.is_empty() {
+            parent
+                .spawn_bundle(style.text("No scores yet", ITEM_FONT_SIZE, style.disabled_color))
+                .insert(ScaledText {
+                    size: ITEM_FONT_SIZE,
+                });
+        }
+        for (rank, entry) in high_scores.entries.iter().enumerate() {
+            let line = format!(
+                "{:>2}. {:<3} {:>7}  wave {}",
+                rank + 1,
+                entry.name,
+                entry.score,
+                entry.wave
+            );
+            parent
+                .spawn_bundle(style.text(&line, ITEM_FONT_SIZE, style.text_color))
+                .insert(ScaledText {
+                    size: ITEM_FONT_SIZE,
+                });
+        }
+        spawn_item(parent, style, "Back", 0, true, HighScoresBack);
+    });
+}
+
+fn spawn_high_scores(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    mode: Res<CurrentMode>,
+    high_scores: Res<HighScoreTables>,
+) {
+    let style = MenuStyle::new(&asset_server, &config, &settings, &theme);
+    spawn_high_scores_table(&mut commands, &style, mode.0, high_scores.get(mode.0));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn high_scores_actions(
+    mut commands: Commands,
+    mut input: ResMut<MenuInput>,
+    cursor: Res<MenuCursor>,
+    items: Query<(&MenuItem, &HighScoresBack)>,
+    screens: Query<(Entity, &HighScoresScreen)>,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    high_scores: Res<HighScoreTables>,
+    mut state: ResMut<State<GameState>>,
+) {
+    // Left and right browse the tables of the other modes
+    let delta = input.take_horizontal();
+    if delta != 0 {
+        if let Ok((entity, screen)) = screens.get_single() {
+            let mode = screen.0.cycle(delta);
+            commands.entity(entity).despawn_recursive();
+            let style = MenuStyle::new(&asset_server, &config, &settings, &theme);
+            spawn_high_scores_table(&mut commands, &style, mode, high_scores.get(mode));
+        }
+    }
+
+    let back = activated_item(&mut input, &cursor, &items).is_some();
+    if input.take_back() || back {
+        let _ = state.pop();
+        input.clear();
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum MenuSystem {
+    /// Reads [`MenuInput`].
+    Input,
+    /// Moves [`MenuCursor`] and highlights the selected item.
+    Navigation,
+    /// Screen specific handling of the menu commands.
+    Actions,
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+struct SettingsMenuSystem;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+struct ModesMenuSystem;
+
+#[derive(Default)]
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<MenuInput>()
+            .init_resource::<MenuCursor>()
+            .init_resource::<Theme>()
+            .init_resource::<Themes>()
+            .init_resource::<CurrentMode>()
+            .init_resource::<HighScoreTables>()
+            .init_resource::<Mutators>()
+            .add_system(read_menu_input.label(MenuSystem::Input))
+            .add_system(
+                navigate_menu
+                    .label(MenuSystem::Navigation)
+                    .after(MenuSystem::Input),
+            )
+            .add_system(scale_menu_text.after(MenuSystem::Actions))
+            .add_system(restyle_text.after(ThemeSystem).after(MenuSystem::Actions))
+            .add_system_set(SystemSet::on_enter(GameState::MainMenu).with_system(spawn_main_menu))
+            .add_system_set(SystemSet::on_resume(GameState::MainMenu).with_system(spawn_main_menu))
+            .add_system_set(
+                SystemSet::on_update(GameState::MainMenu)
+                    .label(MenuSystem::Actions)
+                    .after(MenuSystem::Navigation)
+                    .with_system(main_menu_actions),
+            )
+            .add_system_set(
+                SystemSet::on_pause(GameState::MainMenu)
+                    .with_system(despawn_screen::<MainMenuScreen>),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::MainMenu)
+                    .with_system(despawn_screen::<MainMenuScreen>),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Settings).with_system(spawn_settings_menu),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Settings)
+                    .label(MenuSystem::Actions)
+                    .after(MenuSystem::Navigation)
+                    .with_system(settings_menu_actions.label(SettingsMenuSystem))
+                    .with_system(update_settings_labels.after(SettingsMenuSystem)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Settings)
+                    .with_system(despawn_screen::<SettingsScreen>),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::Modes).with_system(spawn_modes_menu))
+            .add_system_set(
+                SystemSet::on_update(GameState::Modes)
+                    .label(MenuSystem::Actions)
+                    .after(MenuSystem::Navigation)
+                    .with_system(modes_menu_actions.label(ModesMenuSystem))
+                    .with_system(update_mode_description.after(ModesMenuSystem))
+                    .with_system(update_mutator_labels.after(ModesMenuSystem)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Modes).with_system(despawn_screen::<ModesScreen>),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::HighScores).with_system(spawn_high_scores),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::HighScores)
+                    .label(MenuSystem::Actions)
+                    .after(MenuSystem::Navigation)
+                    .with_system(high_scores_actions),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::HighScores)
+                    .with_system(despawn_screen::<HighScoresScreen>),
+            );
+    }
+}
@@ -0,0 +1,269 @@
+use bevy::prelude::*;
+
+use super::{
+    config::GameConfig,
+    cuboids::{Spawner, Wave},
+    settings::{Settings, SimulationSpeed},
+    ship::ShipSystem,
+    state::GameState,
+};
+
+#[derive(Debug, Clone)]
+pub struct ModesConfig {
+    /// Seconds of a time attack run.
+    pub time_limit: f32,
+    /// Multiplier of the spawners' cooldowns with every wave of survival.
+    pub survival_escalation: f32,
+    /// Seconds, below which survival does not shorten the cooldowns.
+    pub survival_min_cooldown: f32,
+    /// Multiplier of the spawners' cooldowns in zen.
+    pub zen_cooldown_scale: f32,
+}
+
+impl Default for ModesConfig {
+    fn default() -> Self {
+        Self {
+            time_limit: 180.0,
+            survival_escalation: 0.85,
+            survival_min_cooldown: 0.3,
+            zen_cooldown_scale: 2.0,
+        }
+    }
+}
+
+/// Rules of a run: how the cuboids spawn, when the run ends and how points are counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameMode {
+    /// Lives from the difficulty, the run ends when they are lost.
+    Endless,
+    /// Most points before the time runs out.
+    TimeAttack,
+    /// Single life and spawners speeding up with every wave, points multiplied by the wave.
+    Survival,
+    /// No damage and slower spawners, the run is ended from the pause menu.
+    Zen,
+    /// Seed and modifiers of the day, see [`crate::daily::DailyChallenge`].
+    Daily,
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        GameMode::Endless
+    }
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 5] = [
+        GameMode::Endless,
+        GameMode::TimeAttack,
+        GameMode::Survival,
+        GameMode::Zen,
+        GameMode::Daily,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Endless => "Endless",
+            GameMode::TimeAttack => "Time Attack",
+            GameMode::Survival => "Survival",
+            GameMode::Zen => "Zen",
+            GameMode::Daily => "Daily",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            GameMode::Endless => "Play until the lives are lost",
+            GameMode::TimeAttack => "Score the most before the time runs out",
+            GameMode::Survival => "One life, faster cuboids every wave",
+            GameMode::Zen => "No damage, fewer cuboids",
+            GameMode::Daily => "Today's challenge, one scored attempt",
+        }
+    }
+
+    /// Identifier of the mode in the saved data.
+    pub fn id(&self) -> &'static str {
+        match self {
+            GameMode::Endless => "endless",
+            GameMode::TimeAttack => "time_attack",
+            GameMode::Survival => "survival",
+            GameMode::Zen => "zen",
+            GameMode::Daily => "daily",
+        }
+    }
+
+    pub fn cycle(&self, delta: i8) -> Self {
+        let index = Self::ALL.iter().position(|mode| mode == self).unwrap_or(0) as i32;
+        let count = Self::ALL.len() as i32;
+        Self::ALL[(index + delta as i32).rem_euclid(count) as usize]
+    }
+
+    /// Lives at the beginning of the run for the lives given by the difficulty.
+    pub fn lives(&self, lives: u8) -> u8 {
+        match self {
+            GameMode::Survival => 1,
+            _ => lives,
+        }
+    }
+
+    /// Whether cuboids take lives of the ship.
+    pub fn damages_ship(&self) -> bool {
+        *self != GameMode::Zen
+    }
+
+    /// Multiplier of the spawners' cooldowns at the beginning of the run.
+    pub fn cooldown_scale(&self, config: &ModesConfig) -> f32 {
+        match self {
+            GameMode::Zen => config.zen_cooldown_scale,
+            _ => 1.0,
+        }
+    }
+
+    /// Seconds, after which the run ends.
+    pub fn time_limit(&self, config: &ModesConfig) -> Option<f32> {
+        match self {
+            GameMode::TimeAttack => Some(config.time_limit),
+            _ => None,
+        }
+    }
+
+    /// Points for a destroyed cuboid worth `points` in the `wave`.
+    pub fn points(&self, points: u32, wave: u32) -> u32 {
+        match self {
+            GameMode::Survival => points * wave.max(1),
+            _ => points,
+        }
+    }
+}
+
+/// Mode of the current run, or of the next one, when it is chosen in the menu.
+#[derive(Debug, Default)]
+pub struct CurrentMode(pub GameMode);
+
+/// Seconds of game time in the current run, counted against the time limit.
+#[derive(Debug, Default)]
+pub struct ModeClock {
+    pub elapsed: f32,
+}
+
+impl ModeClock {
+    /// Seconds left in the run, if the mode has a time limit.
+    pub fn remaining(&self, mode: GameMode, config: &ModesConfig) -> Option<f32> {
+        mode.time_limit(config)
+            .map(|limit| (limit - self.elapsed).max(0.0))
+    }
+}
+
+fn reset_clock(mut clock: ResMut<ModeClock>) {
+    clock.elapsed = 0.0;
+}
+
+fn time_limit(
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    speed: Res<SimulationSpeed>,
+    mode: Res<CurrentMode>,
+    mut clock: ResMut<ModeClock>,
+    mut state: ResMut<State<GameState>>,
+) {
+    clock.elapsed += speed.game_delta(&time, &settings).as_secs_f32();
+    if clock.remaining(mode.0, &config.modes) == Some(0.0) {
+        let _ = state.set(GameState::GameOver);
+    }
+}
+
+/// Shortens the spawners' cooldowns with every new wave of survival.
+fn escalate_spawners(
+    config: Res<GameConfig>,
+    mode: Res<CurrentMode>,
+    wave: Res<Wave>,
+    mut escalated: Local<u32>,
+    mut spawners: Query<&mut Spawner>,
+) {
+    // Waves start from 1 and a new run starts from it again
+    let waves = wave.number.saturating_sub((*escalated).max(1));
+    *escalated = wave.number;
+    if mode.0 != GameMode::Survival || waves == 0 {
+        return;
+    }
+    let config = &config.modes;
+    for mut spawner in spawners.iter_mut() {
+        let current = spawner.cooldown().duration().as_secs_f32();
+        let cooldown = current * config.survival_escalation.powi(waves as i32);
+        spawner.set_cooldown(cooldown.max(config.survival_min_cooldown.min(current)));
+    }
+}
+
+/// Ends the run, when the mode's time runs out.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct ModeSystem;
+
+/// Rules of the [`GameMode`] chosen in the menu.
+#[derive(Default)]
+pub struct ModePlugin;
+
+impl Plugin for ModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<Settings>()
+            .init_resource::<SimulationSpeed>()
+            .init_resource::<CurrentMode>()
+            .init_resource::<ModeClock>()
+            .add_system_set(SystemSet::on_enter(GameState::NewRun).with_system(reset_clock))
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .after(ShipSystem)
+                    .with_system(time_limit.label(ModeSystem))
+                    .with_system(escalate_spawners),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn survival_multiplies_points_by_the_wave() {
+        assert_eq!(GameMode::Survival.points(10, 0), 10);
+        assert_eq!(GameMode::Survival.points(10, 1), 10);
+        assert_eq!(GameMode::Survival.points(10, 4), 40);
+        for mode in [
+            GameMode::Endless,
+            GameMode::TimeAttack,
+            GameMode::Zen,
+            GameMode::Daily,
+        ] {
+            assert_eq!(mode.points(10, 4), 10);
+        }
+    }
+
+    #[test]
+    fn cycle_wraps_around() {
+        assert_eq!(GameMode::Endless.cycle(1), GameMode::TimeAttack);
+        assert_eq!(GameMode::Endless.cycle(-1), GameMode::Daily);
+        assert_eq!(GameMode::Daily.cycle(1), GameMode::Endless);
+        assert_eq!(
+            GameMode::Survival.cycle(GameMode::ALL.len() as i8),
+            GameMode::Survival
+        );
+    }
+
+    #[test]
+    fn rules_follow_the_mode() {
+        let config = ModesConfig::default();
+        assert_eq!(GameMode::Survival.lives(3), 1);
+        assert_eq!(GameMode::Endless.lives(3), 3);
+        assert!(!GameMode::Zen.damages_ship());
+        assert_eq!(
+            GameMode::TimeAttack.time_limit(&config),
+            Some(config.time_limit)
+        );
+        assert_eq!(GameMode::Endless.time_limit(&config), None);
+        assert_eq!(
+            GameMode::Zen.cooldown_scale(&config),
+            config.zen_cooldown_scale
+        );
+    }
+}
@@ -0,0 +1,434 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use std::ops::Range;
+
+use super::{
+    config::{GameConfig, SpawnerConfig},
+    cuboids::{Cuboid, Spawner},
+    daily::DailyChallenge,
+    mode::{CurrentMode, GameMode},
+    physics_layers,
+    settings::SimulationSpeed,
+    state::{GameState, SeedSystem},
+    world::{Arena, Wall},
+};
+
+#[derive(Debug, Clone)]
+pub struct MutatorsConfig {
+    /// Downward acceleration of the cuboids with low gravity.
+    pub low_gravity: f32,
+    /// Restitution of the bouncy walls.
+    pub wall_restitution: f32,
+    /// Sizes added to the giant cuboids.
+    pub giant_sizes: u8,
+    /// Multiplier of the weapon's cooldown of the glass cannon.
+    pub glass_cannon_cooldown: f32,
+    /// Multiplier of the game's speed with double speed.
+    pub speed: f32,
+}
+
+impl Default for MutatorsConfig {
+    fn default() -> Self {
+        Self {
+            low_gravity: 15.0,
+            wall_restitution: 2.5,
+            giant_sizes: 2,
+            glass_cannon_cooldown: 0.5,
+            speed: 2.0,
+        }
+    }
+}
+
+/// Changes of the gameplay, combined from all mutators and modifiers of the run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Effects {
+    /// Multiplier of the spawners' cooldowns.
+    pub spawn_cooldown: f32,
+    /// Multiplier of the cuboids' speed.
+    pub cuboid_speed: f32,
+    /// Added to the sizes of the spawned cuboids.
+    pub cuboid_sizes: u8,
+    /// Most lives the ship starts with.
+    pub max_lives: Option<u8>,
+    /// Multiplier of the weapon's cooldown.
+    pub weapon_cooldown: f32,
+    /// Left and right are swapped for the player.
+    pub mirror_controls: bool,
+    /// Multiplier of the game's speed.
+    pub game_speed: f32,
+    /// Downward acceleration of the cuboids.
+    pub gravity: f32,
+    /// Restitution of the walls, instead of the default one.
+    pub wall_restitution: Option<f32>,
+    /// Cuboids pass through the side walls and come back on the other side.
+    pub wrap_arena: bool,
+}
+
+impl Default for Effects {
+    fn default() -> Self {
+        Self {
+            spawn_cooldown: 1.0,
+            cuboid_speed: 1.0,
+            cuboid_sizes: 0,
+            max_lives: None,
+            weapon_cooldown: 1.0,
+            mirror_controls: false,
+            game_speed: 1.0,
+            gravity: 0.0,
+            wall_restitution: None,
+            wrap_arena: false,
+        }
+    }
+}
+
+impl Effects {
+    /// Effects of both, e.g. of two mutators.
+    pub fn combine(&self, other: &Effects) -> Self {
+        let max_lives = match (self.max_lives, other.max_lives) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        let wall_restitution = match (self.wall_restitution, other.wall_restitution) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        Self {
+            spawn_cooldown: self.spawn_cooldown * other.spawn_cooldown,
+            cuboid_speed: self.cuboid_speed * other.cuboid_speed,
+            cuboid_sizes: self.cuboid_sizes.saturating_add(other.cuboid_sizes),
+            max_lives,
+            weapon_cooldown: self.weapon_cooldown * other.weapon_cooldown,
+            mirror_controls: self.mirror_controls || other.mirror_controls,
+            game_speed: self.game_speed * other.game_speed,
+            gravity: self.gravity + other.gravity,
+            wall_restitution,
+            wrap_arena: self.wrap_arena || other.wrap_arena,
+        }
+    }
+
+    /// Changes the spawner of the level, keeping the cuboids in the generated `sizes`.
+    pub fn apply_spawner(&self, spawner: &mut SpawnerConfig, sizes: &Range<u8>) {
+        let defaults = Spawner::default();
+        spawner.cooldown *= self.spawn_cooldown;
+        if self.cuboid_speed != 1.0 {
+            let speed = spawner
+                .speed_range
+                .clone()
+                .unwrap_or_else(|| defaults.speed_range());
+            let scale = |speed: u8| (speed as f32 * self.cuboid_speed).min(u8::MAX as f32) as u8;
+            // Range stays non-empty, when both ends saturate
+            let start = scale(speed.start).min(u8::MAX - 1);
+            spawner.speed_range = Some(start..scale(speed.end).max(start + 1));
+        }
+        if self.cuboid_sizes > 0 {
+            let size = spawner
+                .size_range
+                .clone()
+                .unwrap_or_else(|| defaults.size_range());
+            let shift = |size: u8| size.saturating_add(self.cuboid_sizes);
+            // Ends are exclusive, the range stays non-empty when both are clamped
+            let start = shift(size.start).min(sizes.end.saturating_sub(1));
+            spawner.size_range = Some(start..shift(size.end).min(sizes.end).max(start + 1));
+        }
+    }
+
+    /// Lives at the beginning of the run.
+    pub fn lives(&self, lives: u8) -> u8 {
+        self.max_lives.map_or(lives, |max| lives.min(max))
+    }
+}
+
+/// Optional rule, which can be combined with the others in any mode except the daily challenge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mutator {
+    LowGravity,
+    BouncyWalls,
+    GiantCuboids,
+    /// Single life and faster weapon.
+    GlassCannon,
+    MirrorControls,
+    DoubleSpeed,
+}
+
+impl Mutator {
+    pub const ALL: [Mutator; 6] = [
+        Mutator::LowGravity,
+        Mutator::BouncyWalls,
+        Mutator::GiantCuboids,
+        Mutator::GlassCannon,
+        Mutator::MirrorControls,
+        Mutator::DoubleSpeed,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mutator::LowGravity => "Low gravity",
+            Mutator::BouncyWalls => "Bouncy walls",
+            Mutator::GiantCuboids => "Giant cuboids",
+            Mutator::GlassCannon => "Glass cannon",
+            Mutator::MirrorControls => "Mirror controls",
+            Mutator::DoubleSpeed => "Double speed",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Mutator::LowGravity => "Cuboids slowly fall towards the ship",
+            Mutator::BouncyWalls => "Walls throw the cuboids back faster",
+            Mutator::GiantCuboids => "Cuboids are bigger",
+            Mutator::GlassCannon => "One life, faster weapon",
+            Mutator::MirrorControls => "Left and right are swapped",
+            Mutator::DoubleSpeed => "Everything moves twice as fast",
+        }
+    }
+
+    pub fn effects(&self, config: &MutatorsConfig) -> Effects {
+        let identity = Effects::default();
+        match self {
+            Mutator::LowGravity => Effects {
+                gravity: config.low_gravity,
+                ..identity
+            },
+            Mutator::BouncyWalls => Effects {
+                wall_restitution: Some(config.wall_restitution),
+                ..identity
+            },
+            Mutator::GiantCuboids => Effects {
+                cuboid_sizes: config.giant_sizes,
+                ..identity
+            },
+            Mutator::GlassCannon => Effects {
+                max_lives: Some(1),
+                weapon_cooldown: config.glass_cannon_cooldown,
+                ..identity
+            },
+            Mutator::MirrorControls => Effects {
+                mirror_controls: true,
+                ..identity
+            },
+            Mutator::DoubleSpeed => Effects {
+                game_speed: config.speed,
+                ..identity
+            },
+        }
+    }
+}
+
+/// Mutators chosen for the next runs.
+#[derive(Debug, Clone, Default)]
+pub struct Mutators {
+    pub enabled: Vec<Mutator>,
+}
+
+impl Mutators {
+    pub fn is_enabled(&self, mutator: Mutator) -> bool {
+        self.enabled.contains(&mutator)
+    }
+
+    pub fn toggle(&mut self, mutator: Mutator) {
+        if self.is_enabled(mutator) {
+            self.enabled.retain(|other| *other != mutator);
+        } else {
+            self.enabled.push(mutator);
+        }
+    }
+}
+
+/// Effects of the current run and names of the mutators or modifiers, which caused them.
+#[derive(Debug, Clone, Default)]
+pub struct RunEffects {
+    pub effects: Effects,
+    pub names: Vec<&'static str>,
+}
+
+fn apply_mutators(
+    config: Res<GameConfig>,
+    mode: Res<CurrentMode>,
+    mutators: Res<Mutators>,
+    daily: Res<DailyChallenge>,
+    mut run_effects: ResMut<RunEffects>,
+    mut speed: ResMut<SimulationSpeed>,
+    mut physics_config: ResMut<RapierConfiguration>,
+) {
+    let mut effects = Effects::default();
+    let mut names = Vec::new();
+    // Daily challenge is the same for everybody
+    if mode.0 == GameMode::Daily {
+        for modifier in daily.modifiers.iter() {
+            effects = effects.combine(&modifier.effects());
+            names.push(modifier.name());
+        }
+    } else {
+        for mutator in mutators.enabled.iter() {
+            effects = effects.combine(&mutator.effects(&config.mutators));
+            names.push(mutator.name());
+        }
+    }
+
+    speed.run_speed = effects.game_speed;
+    physics_config.gravity = vector![0.0, -effects.gravity];
+    *run_effects = RunEffects { effects, names };
+}
+
+/// Sets the restitution of the walls, which are rebuilt with the default one.
+fn bounce_walls(
+    run_effects: Res<RunEffects>,
+    mut walls: Query<&mut ColliderMaterialComponent, With<Wall>>,
+) {
+    let restitution = run_effects
+        .effects
+        .wall_restitution
+        .unwrap_or_else(|| ColliderMaterial::default().restitution);
+    for mut material in walls.iter_mut() {
+        if material.0.restitution != restitution {
+            material.0.restitution = restitution;
+        }
+    }
+}
+
+/// Moves the cuboids, which left the arena on one side, to the other one.
+fn wrap_arena(
+    run_effects: Res<RunEffects>,
+    arena: Res<Arena>,
+    mut cuboids: Query<
+        (&mut ColliderFlagsComponent, &mut RigidBodyPositionComponent),
+        With<Cuboid>,
+    >,
+) {
+    if !run_effects.effects.wrap_arena {
+        return;
+    }
+    let width = arena.half_size.x;
+    for (mut flags, mut position) in cuboids.iter_mut() {
+        // Cuboids get the default groups, whenever they are spawned
+        if flags.0.collision_groups != physics_layers::WRAPPING {
+            flags.0.collision_groups = physics_layers::WRAPPING;
+        }
+        let x = position.0.position.translation.x;
+        if x.abs() > width {
+            let mut wrapped = position.0.position;
+            wrapped.translation.x = x - 2.0 * width * x.signum();
+            position.0.position = wrapped;
+            position.0.next_position = wrapped;
+        }
+    }
+}
+
+/// Computes [`RunEffects`] on entering a new run, before the ship and spawners are created.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct MutatorSystem;
+
+/// Combinable [`Mutator`]s changing the spawners, ship and physics of the run.
+#[derive(Default)]
+pub struct MutatorsPlugin;
+
+impl Plugin for MutatorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<SimulationSpeed>()
+            .init_resource::<CurrentMode>()
+            .init_resource::<DailyChallenge>()
+            .init_resource::<Mutators>()
+            .init_resource::<RunEffects>()
+            .init_resource::<Arena>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::NewRun)
+                    .with_system(apply_mutators.label(MutatorSystem).after(SeedSystem)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(bounce_walls)
+                    .with_system(wrap_arena),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_effects_are_the_identity() {
+        let effects = Mutator::GlassCannon.effects(&MutatorsConfig::default());
+        assert_eq!(effects.combine(&Effects::default()), effects);
+        assert_eq!(Effects::default().combine(&effects), effects);
+    }
+
+    #[test]
+    fn combine_stacks_the_effects() {
+        let a = Effects {
+            spawn_cooldown: 0.5,
+            cuboid_sizes: 1,
+            max_lives: Some(3),
+            wall_restitution: Some(1.5),
+            mirror_controls: true,
+            gravity: 5.0,
+            ..Default::default()
+        };
+        let b = Effects {
+            spawn_cooldown: 0.5,
+            cuboid_sizes: 2,
+            max_lives: Some(1),
+            wall_restitution: Some(2.5),
+            wrap_arena: true,
+            gravity: 10.0,
+            ..Default::default()
+        };
+        let combined = a.combine(&b);
+        assert_eq!(combined.spawn_cooldown, 0.25);
+        assert_eq!(combined.cuboid_sizes, 3);
+        assert_eq!(combined.max_lives, Some(1));
+        assert_eq!(combined.wall_restitution, Some(2.5));
+        assert!(combined.mirror_controls);
+        assert!(combined.wrap_arena);
+        assert_eq!(combined.gravity, 15.0);
+        assert_eq!(combined.lives(3), 1);
+    }
+
+    #[test]
+    fn apply_spawner_scales_the_cooldown_and_speed() {
+        let effects = Effects {
+            spawn_cooldown: 0.5,
+            cuboid_speed: 2.0,
+            ..Default::default()
+        };
+        let mut spawner = SpawnerConfig::new(Vec2::ZERO, 2.0);
+        effects.apply_spawner(&mut spawner, &(1..5));
+        assert_eq!(spawner.cooldown, 1.0);
+        assert_eq!(spawner.speed_range, Some(20..100));
+        assert_eq!(spawner.size_range, None);
+    }
+
+    #[test]
+    fn apply_spawner_keeps_saturated_speeds_non_empty() {
+        let mut spawner = SpawnerConfig {
+            speed_range: Some(200..250),
+            ..SpawnerConfig::new(Vec2::ZERO, 2.0)
+        };
+        Effects {
+            cuboid_speed: 2.0,
+            ..Default::default()
+        }
+        .apply_spawner(&mut spawner, &(1..5));
+        assert_eq!(spawner.speed_range, Some(254..255));
+    }
+
+    #[test]
+    fn apply_spawner_keeps_the_sizes_generated() {
+        let mut spawner = SpawnerConfig::new(Vec2::ZERO, 2.0);
+        Effects {
+            cuboid_sizes: 2,
+            ..Default::default()
+        }
+        .apply_spawner(&mut spawner, &(1..5));
+        assert_eq!(spawner.size_range, Some(3..5));
+
+        let mut spawner = SpawnerConfig::new(Vec2::ZERO, 2.0);
+        Effects {
+            cuboid_sizes: 10,
+            ..Default::default()
+        }
+        .apply_spawner(&mut spawner, &(1..5));
+        assert_eq!(spawner.size_range, Some(4..5));
+    }
+}
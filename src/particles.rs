@@ -0,0 +1,351 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::ops::Range;
+
+use super::{
+    assets::{Materials, Meshes},
+    config::GameConfig,
+    cuboids::CuboidDestroyed,
+    settings::{Settings, SimulationSpeed},
+    ship::{Ship, ShipHit, ShipSystem},
+    state::GameState,
+    weapon::WeaponSystem,
+};
+
+/// How particles of a single effect are emitted.
+#[derive(Debug, Clone)]
+pub struct EmitterConfig {
+    /// Number of particles emitted at once.
+    pub count: usize,
+    pub speed: Range<f32>,
+    /// Seconds, after which particle disappears.
+    pub lifetime: Range<f32>,
+    /// Scale of the particle's mesh.
+    pub scale: Range<f32>,
+    /// Part of the source's velocity added to particles.
+    pub inherit_velocity: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParticlesConfig {
+    /// Maximal number of particles alive at the same time.
+    pub max_particles: usize,
+    /// Shards of destroyed cuboid, per unit of its size.
+    pub shards: EmitterConfig,
+    /// Sparks of the cuboid hitting the ship.
+    pub sparks: EmitterConfig,
+    /// Trail of moving ship, emitted every `thruster_interval`.
+    pub thruster: EmitterConfig,
+    pub thruster_interval: f32,
+    /// Explosion of the ship losing its last life.
+    pub explosion: EmitterConfig,
+}
+
+impl Default for ParticlesConfig {
+    fn default() -> Self {
+        Self {
+            max_particles: 512,
+            shards: EmitterConfig {
+                count: 3,
+                speed: 5.0..25.0,
+                lifetime: 0.6..1.2,
+                scale: 0.15..0.3,
+                inherit_velocity: 0.8,
+            },
+            sparks: EmitterConfig {
+                count: 12,
+                speed: 20.0..50.0,
+                lifetime: 0.2..0.5,
+                scale: 0.4..0.8,
+                inherit_velocity: 0.0,
+            },
+            thruster: EmitterConfig {
+                count: 1,
+                speed: 2.0..6.0,
+                lifetime: 0.3..0.6,
+                scale: 0.5..1.0,
+                inherit_velocity: -0.3,
+            },
+            thruster_interval: 0.05,
+            explosion: EmitterConfig {
+                count: 60,
+                speed: 10.0..60.0,
+                lifetime: 0.8..2.0,
+                scale: 0.5..1.5,
+                inherit_velocity: 0.0,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Component)]
+pub struct Particle {
+    velocity: Vec3,
+    spin: Vec3,
+    age: f32,
+    lifetime: f32,
+    scale: f32,
+}
+
+/// Hidden particle entities, reused instead of spawning new ones.
+#[derive(Debug, Default)]
+pub struct ParticlePool {
+    free: Vec<Entity>,
+}
+
+impl ParticlePool {
+    /// Number of particles, which can be emitted.
+    pub fn available(&self) -> usize {
+        self.free.len()
+    }
+}
+
+struct ParticlesRng(StdRng);
+
+/// Emits particles of a single effect.
+struct Emitter<'a> {
+    config: &'a EmitterConfig,
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+    position: Vec2,
+    velocity: Vec2,
+}
+
+impl<'a> Emitter<'a> {
+    fn emit(
+        &self,
+        count: usize,
+        commands: &mut Commands,
+        pool: &mut ParticlePool,
+        rng: &mut StdRng,
+    ) {
+        for _ in 0..count {
+            let entity = match pool.free.pop() {
+                Some(entity) => entity,
+                None => return,
+            };
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let speed = rng.gen_range(self.config.speed.clone());
+            let velocity = speed * Vec2::new(angle.cos(), angle.sin())
+                + self.config.inherit_velocity * self.velocity;
+            let scale = rng.gen_range(self.config.scale.clone());
+            let spin = Vec3::new(rng.gen(), rng.gen(), rng.gen()) * 10.0;
+            commands
+                .entity(entity)
+                .insert(Particle {
+                    velocity: velocity.extend(rng.gen_range(-5.0..5.0)),
+                    spin,
+                    age: 0.0,
+                    lifetime: rng.gen_range(self.config.lifetime.clone()),
+                    scale,
+                })
+                .insert(self.mesh.clone())
+                .insert(self.material.clone())
+                .insert(Transform {
+                    translation: self.position.extend(0.0),
+                    scale: Vec3::splat(scale),
+                    ..Default::default()
+                })
+                .insert(Visibility { is_visible: true });
+        }
+    }
+}
+
+fn setup_particles(
+    mut commands: Commands,
+    meshes: Res<Meshes>,
+    materials: Res<Materials>,
+    config: Res<GameConfig>,
+) {
+    let pool = ParticlePool {
+        free: (0..config.particles.max_particles)
+            .map(|_| {
+                commands
+                    .spawn_bundle(PbrBundle {
+                        mesh: meshes.bullet.clone(),
+                        material: materials.spark.clone(),
+                        visibility: Visibility { is_visible: false },
+                        ..Default::default()
+                    })
+                    .id()
+            })
+            .collect(),
+    };
+    commands.insert_resource(pool);
+}
+
+fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    speed: Res<SimulationSpeed>,
+    mut pool: ResMut<ParticlePool>,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Visibility)>,
+) {
+    let delta = speed.game_delta(&time, &settings).as_secs_f32();
+    for (entity, mut particle, mut transform, mut visibility) in particles.iter_mut() {
+        particle.age += delta;
+        if particle.age >= particle.lifetime {
+            visibility.is_visible = false;
+            commands.entity(entity).remove::<Particle>();
+            pool.free.push(entity);
+            continue;
+        }
+        // Particles share materials, so they fade out by shrinking
+        let remaining = 1.0 - particle.age / particle.lifetime;
+        transform.translation += particle.velocity * delta;
+        transform.rotate(Quat::from_euler(
+            EulerRot::XYZ,
+            particle.spin.x * delta,
+            particle.spin.y * delta,
+            particle.spin.z * delta,
+        ));
+        transform.scale = Vec3::splat(particle.scale * remaining);
+    }
+}
+
+fn clear_particles(
+    mut commands: Commands,
+    mut pool: ResMut<ParticlePool>,
+    mut particles: Query<(Entity, &mut Visibility), With<Particle>>,
+) {
+    for (entity, mut visibility) in particles.iter_mut() {
+        visibility.is_visible = false;
+        commands.entity(entity).remove::<Particle>();
+        pool.free.push(entity);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn emit_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    speed: Res<SimulationSpeed>,
+    meshes: Res<Meshes>,
+    materials: Res<Materials>,
+    mut pool: ResMut<ParticlePool>,
+    mut rng: ResMut<ParticlesRng>,
+    mut thruster_cooldown: Local<f32>,
+    mut destroyed: EventReader<CuboidDestroyed>,
+    mut hits: EventReader<ShipHit>,
+    ships: Query<(&Transform, &RigidBodyVelocityComponent), With<Ship>>,
+) {
+    let config = &config.particles;
+    let rng = &mut rng.0;
+
+    for event in destroyed.iter() {
+        let material = match materials.cuboid.get(&event.size) {
+            Some(material) => material.clone(),
+            None => continue,
+        };
+        let shards = &meshes.shards;
+        if shards.is_empty() {
+            continue;
+        }
+        Emitter {
+            config: &config.shards,
+            mesh: shards[rng.gen_range(0..shards.len())].clone(),
+            material,
+            position: event.position,
+            velocity: event.velocity,
+        }
+        .emit(
+            config.shards.count * event.size as usize,
+            &mut commands,
+            &mut pool,
+            rng,
+        );
+    }
+
+    let ship = ships.iter().next();
+    for hit in hits.iter() {
+        let (transform, _) = match ship {
+            Some(ship) => ship,
+            None => continue,
+        };
+        let position = transform.translation.truncate();
+        let reduced_motion = settings.accessibility.reduced_motion;
+        let (effect, material, count) = if hit.lives == 0 {
+            // Smaller explosion without the bright flash of sparks
+            let count = if reduced_motion {
+                config.explosion.count / 4
+            } else {
+                config.explosion.count
+            };
+            (&config.explosion, materials.ship.clone(), count)
+        } else if reduced_motion {
+            continue;
+        } else {
+            (&config.sparks, materials.spark.clone(), config.sparks.count)
+        };
+        Emitter {
+            config: effect,
+            mesh: meshes.bullet.clone(),
+            material,
+            position,
+            velocity: Vec2::ZERO,
+        }
+        .emit(count, &mut commands, &mut pool, rng);
+    }
+
+    *thruster_cooldown -= speed.game_delta(&time, &settings).as_secs_f32();
+    if *thruster_cooldown > 0.0 {
+        return;
+    }
+    *thruster_cooldown = config.thruster_interval;
+    for (transform, velocity) in ships.iter() {
+        let velocity = Vec2::new(velocity.0.linvel.x, velocity.0.linvel.y);
+        if velocity.length_squared() < f32::EPSILON {
+            continue;
+        }
+        Emitter {
+            config: &config.thruster,
+            mesh: meshes.bullet.clone(),
+            material: materials.thruster.clone(),
+            position: transform.translation.truncate(),
+            velocity,
+        }
+        .emit(config.thruster.count, &mut commands, &mut pool, rng);
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct ParticlesSystem;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+enum ParticlesSystemLabels {
+    /// Releases expired particles, whose components are removed by commands.
+    Update,
+}
+
+#[derive(Default)]
+pub struct ParticlesPlugin;
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<SimulationSpeed>()
+            .init_resource::<ParticlePool>()
+            .insert_resource(ParticlesRng(StdRng::from_entropy()))
+            .add_startup_system_to_stage(StartupStage::PostStartup, setup_particles)
+            .add_system_set(SystemSet::on_enter(GameState::NewRun).with_system(clear_particles))
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .label(ParticlesSystem)
+                    .after(ShipSystem)
+                    .after(WeaponSystem)
+                    .with_system(update_particles.label(ParticlesSystemLabels::Update))
+                    // Reused particles get their components after the released ones lose them
+                    .with_system(emit_particles.after(ParticlesSystemLabels::Update)),
+            )
+            // Let the ship's explosion finish behind the game over screen
+            .add_system_set(
+                SystemSet::on_update(GameState::GameOver)
+                    .label(ParticlesSystem)
+                    .with_system(update_particles),
+            );
+    }
+}
@@ -0,0 +1,122 @@
+use bevy::{app::AppExit, prelude::*};
+
+use super::{
+    config::GameConfig,
+    menu::{
+        activated_item, despawn_screen, spawn_item, spawn_menu, MenuCursor, MenuInput, MenuItem,
+        MenuStyle, MenuSystem,
+    },
+    mode::CurrentMode,
+    settings::Settings,
+    state::GameState,
+    theme::Theme,
+};
+
+#[derive(Debug, Component)]
+struct PauseScreen;
+
+#[derive(Debug, Clone, Copy, Component)]
+enum PauseAction {
+    Resume,
+    Restart,
+    /// Ends the run of a mode, which the player cannot lose.
+    EndRun,
+    Settings,
+    MainMenu,
+    Quit,
+}
+
+fn pause_game(mut input: ResMut<MenuInput>, mut state: ResMut<State<GameState>>) {
+    if input.pause {
+        let _ = state.push(GameState::Paused);
+        input.clear();
+    }
+}
+
+fn spawn_pause_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    mode: Res<CurrentMode>,
+) {
+    let style = MenuStyle::new(&asset_server, &config, &settings, &theme);
+    let items = [
+        ("Resume", PauseAction::Resume, true),
+        ("Restart", PauseAction::Restart, true),
+        ("End run", PauseAction::EndRun, !mode.0.damages_ship()),
+        ("Settings", PauseAction::Settings, true),
+        ("Main menu", PauseAction::MainMenu, true),
+        ("Quit", PauseAction::Quit, true),
+    ];
+    spawn_menu(&mut commands, &style, PauseScreen, "Paused", |parent| {
+        for (index, (label, action, enabled)) in items.into_iter().enumerate() {
+            spawn_item(parent, &style, label, index, enabled, action);
+        }
+    });
+}
+
+fn pause_menu_actions(
+    mut input: ResMut<MenuInput>,
+    cursor: Res<MenuCursor>,
+    items: Query<(&MenuItem, &PauseAction)>,
+    mut state: ResMut<State<GameState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let resume = input.take_back() || std::mem::take(&mut input.pause);
+    match activated_item(&mut input, &cursor, &items) {
+        Some(PauseAction::Resume) => {
+            let _ = state.pop();
+        }
+        // Replacing the whole stack leaves the paused game as well
+        Some(PauseAction::Restart) => {
+            let _ = state.replace(GameState::NewRun);
+        }
+        Some(PauseAction::EndRun) => {
+            let _ = state.replace(GameState::GameOver);
+        }
+        Some(PauseAction::Settings) => {
+            let _ = state.push(GameState::Settings);
+        }
+        Some(PauseAction::MainMenu) => {
+            let _ = state.replace(GameState::MainMenu);
+        }
+        Some(PauseAction::Quit) => exit.send(AppExit),
+        None if resume => {
+            let _ = state.pop();
+        }
+        None => return,
+    }
+    input.clear();
+}
+
+#[derive(Default)]
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<CurrentMode>()
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .label(MenuSystem::Actions)
+                    .after(MenuSystem::Navigation)
+                    .with_system(pause_game),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::Paused).with_system(spawn_pause_menu))
+            .add_system_set(SystemSet::on_resume(GameState::Paused).with_system(spawn_pause_menu))
+            .add_system_set(
+                SystemSet::on_update(GameState::Paused)
+                    .label(MenuSystem::Actions)
+                    .after(MenuSystem::Navigation)
+                    .with_system(pause_menu_actions),
+            )
+            .add_system_set(
+                SystemSet::on_pause(GameState::Paused).with_system(despawn_screen::<PauseScreen>),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Paused).with_system(despawn_screen::<PauseScreen>),
+            );
+    }
+}
@@ -0,0 +1,368 @@
+use bevy::{
+    app::AppExit,
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics},
+    prelude::*,
+};
+use bevy_rapier2d::physics::PhysicsStages;
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    fs,
+    path::PathBuf,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use super::{
+    config::GameConfig,
+    console::{ConsoleAppExt, ConsoleCommand, ConsoleCommands, ConsoleOutput},
+    cuboids::Cuboid,
+    particles::Particle,
+    pool::EntityPool,
+    weapon::Bullet,
+};
+
+pub const ENTITIES: DiagnosticId =
+    DiagnosticId::from_u128(0x2f0c_5a2e_7d4b_4c1e_9a51_8e3f_0b6d_1001);
+pub const CUBOIDS: DiagnosticId =
+    DiagnosticId::from_u128(0x2f0c_5a2e_7d4b_4c1e_9a51_8e3f_0b6d_1002);
+pub const BULLETS: DiagnosticId =
+    DiagnosticId::from_u128(0x2f0c_5a2e_7d4b_4c1e_9a51_8e3f_0b6d_1003);
+pub const PARTICLES: DiagnosticId =
+    DiagnosticId::from_u128(0x2f0c_5a2e_7d4b_4c1e_9a51_8e3f_0b6d_1004);
+/// Milliseconds spent in the physics stages.
+pub const PHYSICS_TIME: DiagnosticId =
+    DiagnosticId::from_u128(0x2f0c_5a2e_7d4b_4c1e_9a51_8e3f_0b6d_1005);
+pub const SPAWNS_PER_SECOND: DiagnosticId =
+    DiagnosticId::from_u128(0x2f0c_5a2e_7d4b_4c1e_9a51_8e3f_0b6d_1006);
+/// Hidden cuboids and bullets waiting for reuse.
+pub const POOLED: DiagnosticId = DiagnosticId::from_u128(0x2f0c_5a2e_7d4b_4c1e_9a51_8e3f_0b6d_1007);
+/// Percentage of the spawned cuboids, which were reused from the pool.
+pub const CUBOIDS_REUSED: DiagnosticId =
+    DiagnosticId::from_u128(0x2f0c_5a2e_7d4b_4c1e_9a51_8e3f_0b6d_1008);
+
+/// Directory in the storage with the exported recordings.
+const EXPORT_DIRECTORY: &str = "perf";
+
+#[derive(Debug, Clone)]
+pub struct PerfConfig {
+    /// Records samples from the start, otherwise recording is started with `perf start`.
+    pub record: bool,
+    /// Samples kept in the recording, the oldest are dropped first.
+    pub max_samples: usize,
+    /// Physics time per frame in milliseconds, above which a warning is shown.
+    pub physics_budget_ms: f32,
+    /// Seconds between repeated budget warnings.
+    pub warning_interval: f32,
+    /// Exports the recording as CSV and JSON, when the game is closed.
+    pub export_on_exit: bool,
+}
+
+impl Default for PerfConfig {
+    fn default() -> Self {
+        Self {
+            record: false,
+            max_samples: 36_000,
+            physics_budget_ms: 4.0,
+            warning_interval: 1.0,
+            export_on_exit: true,
+        }
+    }
+}
+
+/// Measurements of a single frame.
+#[derive(Debug, Clone, Default)]
+pub struct PerfSample {
+    pub frame: u64,
+    /// Seconds since the start of the game.
+    pub time: f64,
+    pub frame_time_ms: f32,
+    pub physics_time_ms: f32,
+    pub entities: u32,
+    pub cuboids: u32,
+    pub bullets: u32,
+    pub particles: u32,
+    pub spawns_per_second: f32,
+    pub pooled: u32,
+    pub cuboids_reused: f32,
+}
+
+impl PerfSample {
+    const FIELDS: [&'static str; 11] = [
+        "frame",
+        "time",
+        "frame_time_ms",
+        "physics_time_ms",
+        "entities",
+        "cuboids",
+        "bullets",
+        "particles",
+        "spawns_per_second",
+        "pooled",
+        "cuboids_reused",
+    ];
+
+    fn values(&self) -> [String; 11] {
+        [
+            self.frame.to_string(),
+            format!("{:.4}", self.time),
+            format!("{:.3}", self.frame_time_ms),
+            format!("{:.3}", self.physics_time_ms),
+            self.entities.to_string(),
+            self.cuboids.to_string(),
+            self.bullets.to_string(),
+            self.particles.to_string(),
+            format!("{:.2}", self.spawns_per_second),
+            self.pooled.to_string(),
+            format!("{:.2}", self.cuboids_reused),
+        ]
+    }
+}
+
+/// Samples recorded every frame, while recording is enabled.
+#[derive(Debug, Default)]
+pub struct PerfRecording {
+    pub recording: bool,
+    pub samples: VecDeque<PerfSample>,
+}
+
+impl PerfRecording {
+    pub fn to_csv(&self) -> String {
+        let mut csv = PerfSample::FIELDS.join(",");
+        csv.push('\n');
+        for sample in self.samples.iter() {
+            csv.push_str(&sample.values().join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("[\n");
+        for (index, sample) in self.samples.iter().enumerate() {
+            let fields = PerfSample::FIELDS
+                .iter()
+                .zip(sample.values())
+                .map(|(name, value)| format!("\"{}\": {}", name, value))
+                .collect::<Vec<_>>();
+            let separator = if index + 1 < self.samples.len() {
+                ","
+            } else {
+                ""
+            };
+            let _ = writeln!(json, "  {{{}}}{}", fields.join(", "), separator);
+        }
+        json.push(']');
+        json
+    }
+
+    /// Writes the recording into the storage, returns path of the files without extension.
+    pub fn export(&self, config: &GameConfig) -> Option<PathBuf> {
+        let directory = config.storage.directory.as_ref()?.join(EXPORT_DIRECTORY);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let path = directory.join(format!("session-{}", timestamp));
+        let result = fs::create_dir_all(&directory)
+            .and_then(|_| fs::write(path.with_extension("csv"), self.to_csv()))
+            .and_then(|_| fs::write(path.with_extension("json"), self.to_json()));
+        match result {
+            Ok(()) => Some(path),
+            Err(error) => {
+                warn!(
+                    "Could not export performance recording {:?}: {}",
+                    path, error
+                );
+                None
+            }
+        }
+    }
+}
+
+/// Start of the physics stages in the current frame.
+#[derive(Debug, Default)]
+struct PhysicsTimer {
+    start: Option<Instant>,
+    /// Duration of the last physics update in milliseconds.
+    last_ms: f32,
+}
+
+fn setup_diagnostics(
+    config: Res<GameConfig>,
+    mut diagnostics: ResMut<Diagnostics>,
+    mut recording: ResMut<PerfRecording>,
+) {
+    diagnostics.add(Diagnostic::new(ENTITIES, "entities", 20));
+    diagnostics.add(Diagnostic::new(CUBOIDS, "cuboids", 20));
+    diagnostics.add(Diagnostic::new(BULLETS, "bullets", 20));
+    diagnostics.add(Diagnostic::new(PARTICLES, "particles", 20));
+    diagnostics.add(Diagnostic::new(PHYSICS_TIME, "physics_time_ms", 20));
+    diagnostics.add(Diagnostic::new(SPAWNS_PER_SECOND, "spawns_per_second", 60));
+    diagnostics.add(Diagnostic::new(POOLED, "pooled", 20));
+    diagnostics.add(Diagnostic::new(
+        CUBOIDS_REUSED,
+        "cuboids_reused_percent",
+        20,
+    ));
+    recording.recording = config.perf.record;
+}
+
+fn start_physics_timer(mut timer: ResMut<PhysicsTimer>) {
+    timer.start = Some(Instant::now());
+}
+
+fn stop_physics_timer(mut timer: ResMut<PhysicsTimer>) {
+    if let Some(start) = timer.start.take() {
+        timer.last_ms = start.elapsed().as_secs_f32() * 1000.0;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn measure(
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    timer: Res<PhysicsTimer>,
+    mut diagnostics: ResMut<Diagnostics>,
+    mut recording: ResMut<PerfRecording>,
+    mut frame: Local<u64>,
+    entities: Query<Entity>,
+    cuboids: Query<(), With<Cuboid>>,
+    spawned: Query<(), Added<Cuboid>>,
+    bullets: Query<(), With<Bullet>>,
+    particles: Query<(), With<Particle>>,
+    cuboid_pool: Res<EntityPool<Cuboid>>,
+    bullet_pool: Res<EntityPool<Bullet>>,
+) {
+    let delta = time.delta_seconds();
+    let spawns_per_second = if delta > 0.0 {
+        spawned.iter().count() as f32 / delta
+    } else {
+        0.0
+    };
+    let sample = PerfSample {
+        frame: *frame,
+        time: time.seconds_since_startup(),
+        frame_time_ms: delta * 1000.0,
+        physics_time_ms: timer.last_ms,
+        entities: entities.iter().count() as u32,
+        cuboids: cuboids.iter().count() as u32,
+        bullets: bullets.iter().count() as u32,
+        particles: particles.iter().count() as u32,
+        spawns_per_second,
+        pooled: (cuboid_pool.available() + bullet_pool.available()) as u32,
+        cuboids_reused: cuboid_pool.stats.reuse_ratio() * 100.0,
+    };
+    *frame += 1;
+
+    diagnostics.add_measurement(ENTITIES, sample.entities as f64);
+    diagnostics.add_measurement(CUBOIDS, sample.cuboids as f64);
+    diagnostics.add_measurement(BULLETS, sample.bullets as f64);
+    diagnostics.add_measurement(PARTICLES, sample.particles as f64);
+    diagnostics.add_measurement(PHYSICS_TIME, sample.physics_time_ms as f64);
+    diagnostics.add_measurement(SPAWNS_PER_SECOND, sample.spawns_per_second as f64);
+    diagnostics.add_measurement(POOLED, sample.pooled as f64);
+    diagnostics.add_measurement(CUBOIDS_REUSED, sample.cuboids_reused as f64);
+
+    if recording.recording {
+        recording.samples.push_back(sample);
+        while recording.samples.len() > config.perf.max_samples {
+            recording.samples.pop_front();
+        }
+    }
+}
+
+/// Warns, when physics takes more of the frame than its budget.
+fn check_budget(
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    timer: Res<PhysicsTimer>,
+    mut output: EventWriter<ConsoleOutput>,
+    mut last_warning: Local<Option<f64>>,
+) {
+    let budget = config.perf.physics_budget_ms;
+    if timer.last_ms <= budget {
+        return;
+    }
+    let now = time.seconds_since_startup();
+    if matches!(*last_warning, Some(last) if now - last < config.perf.warning_interval as f64) {
+        return;
+    }
+    *last_warning = Some(now);
+    let message = format!(
+        "Physics took {:.2}ms, over the budget of {:.2}ms",
+        timer.last_ms, budget
+    );
+    warn!("{}", message);
+    output.send(ConsoleOutput(message));
+}
+
+fn console_commands(
+    config: Res<GameConfig>,
+    mut entered: EventReader<ConsoleCommand>,
+    mut output: EventWriter<ConsoleOutput>,
+    registered: Res<ConsoleCommands>,
+    mut recording: ResMut<PerfRecording>,
+) {
+    for command in entered.iter().filter(|command| command.name == "perf") {
+        let message = match command.args.first().map(String::as_str) {
+            Some("start") => {
+                recording.recording = true;
+                "Recording started".to_string()
+            }
+            Some("stop") => {
+                recording.recording = false;
+                format!("Recorded {} frames", recording.samples.len())
+            }
+            Some("clear") => {
+                recording.samples.clear();
+                "Recording cleared".to_string()
+            }
+            Some("export") => match recording.export(&config) {
+                Some(path) => format!("Exported to {}.{{csv,json}}", path.display()),
+                None => "Nothing was exported".to_string(),
+            },
+            _ => {
+                output.send(ConsoleOutput::usage(&registered, "perf"));
+                continue;
+            }
+        };
+        output.send(ConsoleOutput(message));
+    }
+}
+
+fn export_on_exit(
+    config: Res<GameConfig>,
+    recording: Res<PerfRecording>,
+    mut exit: EventReader<AppExit>,
+) {
+    if exit.iter().next().is_some() && config.perf.export_on_exit && !recording.samples.is_empty() {
+        recording.export(&config);
+    }
+}
+
+/// Records custom diagnostics every frame and exports them, see [`PerfConfig`].
+#[derive(Default)]
+pub struct PerfPlugin;
+
+impl Plugin for PerfPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<Diagnostics>()
+            .init_resource::<EntityPool<Cuboid>>()
+            .init_resource::<EntityPool<Bullet>>()
+            .init_resource::<PerfRecording>()
+            .init_resource::<PhysicsTimer>()
+            .add_console_command(
+                "perf",
+                "start|stop|clear|export",
+                "Records and exports performance samples",
+            )
+            .add_startup_system(setup_diagnostics)
+            .add_system_to_stage(PhysicsStages::SyncTransforms, start_physics_timer)
+            .add_system_to_stage(PhysicsStages::Writeback, stop_physics_timer)
+            .add_system_to_stage(CoreStage::Last, measure)
+            .add_system_to_stage(CoreStage::Last, check_budget)
+            .add_system(console_commands)
+            .add_system_to_stage(CoreStage::Last, export_on_exit);
+    }
+}
@@ -0,0 +1,197 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use std::{collections::HashSet, marker::PhantomData};
+
+use super::{
+    config::GameConfig,
+    console::{ConsoleAppExt, ConsoleCommand, ConsoleOutput},
+    cuboids::Cuboid,
+    state::GameState,
+    weapon::Bullet,
+};
+
+/// Height where the released bodies wait, far outside of the arena.
+const PARKING_Y: f32 = -10_000.0;
+
+/// Counters of a single pool.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PoolStats {
+    /// Entities created, because the pool was empty.
+    pub spawned: u64,
+    /// Entities taken from the pool instead of spawning new ones.
+    pub reused: u64,
+    /// Entities returned to the pool.
+    pub released: u64,
+    /// Entities despawned, because the pool was full.
+    pub despawned: u64,
+}
+
+impl PoolStats {
+    /// Share of the acquired entities, which were reused, between 0 and 1.
+    pub fn reuse_ratio(&self) -> f32 {
+        let acquired = self.spawned + self.reused;
+        if acquired == 0 {
+            0.0
+        } else {
+            self.reused as f32 / acquired as f32
+        }
+    }
+}
+
+/// Released entities with the marker `T`, reused instead of spawning new ones.
+///
+/// Pooled entities keep their bodies and colliders, which are disabled and parked outside of
+/// the arena, and only lose the marker component, so they are not matched by the game's queries.
+#[derive(Debug)]
+pub struct EntityPool<T> {
+    free: Vec<Entity>,
+    pub stats: PoolStats,
+    marker: PhantomData<T>,
+}
+
+impl<T> Default for EntityPool<T> {
+    fn default() -> Self {
+        Self {
+            free: Vec::new(),
+            stats: PoolStats::default(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Component> EntityPool<T> {
+    /// Number of hidden entities, which can be reused.
+    pub fn available(&self) -> usize {
+        self.free.len()
+    }
+
+    /// Entity to be enabled again, `None` if a new one has to be spawned.
+    pub fn acquire(&mut self) -> Option<Entity> {
+        let entity = self.free.pop();
+        if entity.is_some() {
+            self.stats.reused += 1;
+        } else {
+            self.stats.spawned += 1;
+        }
+        entity
+    }
+
+    /// Disables and hides the `entity`, or despawns it when the pool already holds `capacity`.
+    fn release(&mut self, commands: &mut Commands, entity: Entity, capacity: usize) {
+        if self.free.contains(&entity) {
+            return;
+        }
+        if self.free.len() >= capacity {
+            commands.entity(entity).despawn();
+            self.stats.despawned += 1;
+            return;
+        }
+        commands
+            .entity(entity)
+            .remove::<T>()
+            .insert(Visibility { is_visible: false })
+            .insert(RigidBodyPositionComponent::from(Vec2::new(0.0, PARKING_Y)))
+            .insert(RigidBodyVelocityComponent(RigidBodyVelocity::zero()))
+            .insert(RigidBodyActivationComponent(RigidBodyActivation::inactive()))
+            .insert(ColliderFlagsComponent(ColliderFlags {
+                collision_groups: InteractionGroups::none(),
+                solver_groups: InteractionGroups::none(),
+                active_events: ActiveEvents::empty(),
+                ..Default::default()
+            }));
+        self.free.push(entity);
+        self.stats.released += 1;
+    }
+}
+
+/// Removes the cuboid or bullet from the game, returning it to its pool.
+#[derive(Debug, Clone, Copy)]
+pub struct Release(pub Entity);
+
+#[allow(clippy::too_many_arguments)]
+fn release_entities(
+    mut commands: Commands,
+    config: Res<GameConfig>,
+    mut released: EventReader<Release>,
+    mut cuboid_pool: ResMut<EntityPool<Cuboid>>,
+    mut bullet_pool: ResMut<EntityPool<Bullet>>,
+    cuboids: Query<(), With<Cuboid>>,
+    bullets: Query<(), With<Bullet>>,
+) {
+    // An entity can be released by multiple systems in the same frame
+    let mut handled = HashSet::new();
+    for Release(entity) in released.iter().copied() {
+        if !handled.insert(entity) {
+            continue;
+        }
+        if cuboids.get(entity).is_ok() {
+            cuboid_pool.release(&mut commands, entity, config.cuboids.pool_size);
+        } else if bullets.get(entity).is_ok() {
+            bullet_pool.release(&mut commands, entity, config.weapon.pool_size);
+        }
+    }
+}
+
+fn release_all(
+    mut commands: Commands,
+    config: Res<GameConfig>,
+    mut cuboid_pool: ResMut<EntityPool<Cuboid>>,
+    mut bullet_pool: ResMut<EntityPool<Bullet>>,
+    cuboids: Query<Entity, With<Cuboid>>,
+    bullets: Query<Entity, With<Bullet>>,
+) {
+    for entity in cuboids.iter() {
+        cuboid_pool.release(&mut commands, entity, config.cuboids.pool_size);
+    }
+    for entity in bullets.iter() {
+        bullet_pool.release(&mut commands, entity, config.weapon.pool_size);
+    }
+}
+
+fn console_commands(
+    mut entered: EventReader<ConsoleCommand>,
+    mut output: EventWriter<ConsoleOutput>,
+    cuboid_pool: Res<EntityPool<Cuboid>>,
+    bullet_pool: Res<EntityPool<Bullet>>,
+) {
+    for _ in entered.iter().filter(|command| command.name == "pool") {
+        for (name, available, stats) in [
+            ("Cuboids", cuboid_pool.available(), cuboid_pool.stats),
+            ("Bullets", bullet_pool.available(), bullet_pool.stats),
+        ] {
+            output.send(ConsoleOutput(format!(
+                "{}: {} pooled, {} spawned, {} reused ({:.0}%), {} released, {} despawned",
+                name,
+                available,
+                stats.spawned,
+                stats.reused,
+                stats.reuse_ratio() * 100.0,
+                stats.released,
+                stats.despawned,
+            )));
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct PoolSystem;
+
+/// Recycles cuboids and bullets sent with [`Release`], see [`EntityPool`].
+///
+/// Pool sizes are configured with `CuboidsConfig::pool_size` and `WeaponConfig::pool_size`.
+#[derive(Default)]
+pub struct PoolPlugin;
+
+impl Plugin for PoolPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<EntityPool<Cuboid>>()
+            .init_resource::<EntityPool<Bullet>>()
+            .add_event::<Release>()
+            .add_console_command("pool", "", "Shows reuse of the pooled cuboids and bullets")
+            .add_system(console_commands)
+            .add_system(release_entities.label(PoolSystem))
+            .add_system_set(SystemSet::on_enter(GameState::NewRun).with_system(release_all))
+            .add_system_set(SystemSet::on_enter(GameState::Editor).with_system(release_all));
+    }
+}
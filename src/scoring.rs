@@ -1,15 +1,266 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use super::config::GameConfig;
+use super::console::{ConsoleAppExt, ConsoleCommand, ConsoleCommands, ConsoleOutput};
+use super::cuboids::{CuboidDestroyed, DestructionCause, Wave};
+use super::mode::{CurrentMode, GameMode};
+use super::settings::{Settings, SimulationSpeed};
+use super::ship::ShipSystem;
+use super::state::GameState;
+use super::storage;
+use super::weapon::{ShotFired, ShotMissed, WeaponSystem};
 
 #[derive(Debug, Clone)]
 pub struct Score {
     pub score: u32,
 }
 
+/// Statistics of the current run, shown in the game over summary.
+#[derive(Debug, Clone, Default)]
+pub struct RunStats {
+    pub shots_fired: u32,
+    pub shots_hit: u32,
+    /// Number of destroyed cuboids by their size.
+    pub destroyed: BTreeMap<u8, u32>,
+    /// Seconds spent in game, without time spent in menus.
+    pub time_survived: f32,
+    /// Hits in a row, without a missed shot.
+    pub combo: u32,
+    pub best_combo: u32,
+    pub wave: u32,
+}
+
+impl RunStats {
+    /// Ratio of hits to fired shots, `None` if nothing was fired.
+    pub fn accuracy(&self) -> Option<f32> {
+        if self.shots_fired == 0 {
+            None
+        } else {
+            Some(self.shots_hit as f32 / self.shots_fired as f32)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u32,
+    pub wave: u32,
+}
+
+/// Best scores of a single mode, ordered from the highest one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    pub entries: Vec<HighScore>,
+}
+
+impl HighScores {
+    pub const MAX_ENTRIES: usize = 10;
+    const STORAGE_NAME: &'static str = "high_scores";
+
+    /// Endless mode keeps the name from before the modes were added.
+    fn storage_name(mode: GameMode) -> String {
+        match mode {
+            GameMode::Endless => Self::STORAGE_NAME.to_string(),
+            _ => format!("{}_{}", Self::STORAGE_NAME, mode.id()),
+        }
+    }
+
+    /// Whether the score is good enough to enter the table.
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0
+            && (self.entries.len() < Self::MAX_ENTRIES
+                || self.entries.iter().any(|entry| entry.score < score))
+    }
+
+    /// Inserts the score and returns its position in the table.
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        let position = self
+            .entries
+            .iter()
+            .position(|other| other.score < entry.score)
+            .unwrap_or(self.entries.len());
+        if position >= Self::MAX_ENTRIES {
+            return None;
+        }
+        self.entries.insert(position, entry);
+        self.entries.truncate(Self::MAX_ENTRIES);
+        Some(position)
+    }
+
+    pub fn load(config: &GameConfig, mode: GameMode) -> Self {
+        storage::load(&config.storage, &Self::storage_name(mode)).unwrap_or_default()
+    }
+
+    pub fn save(&self, config: &GameConfig, mode: GameMode) {
+        storage::save(&config.storage, &Self::storage_name(mode), self);
+    }
+}
+
+/// [`HighScores`] of every [`GameMode`].
+#[derive(Debug, Clone)]
+pub struct HighScoreTables(HashMap<GameMode, HighScores>);
+
+impl Default for HighScoreTables {
+    fn default() -> Self {
+        Self(
+            GameMode::ALL
+                .iter()
+                .map(|mode| (*mode, HighScores::default()))
+                .collect(),
+        )
+    }
+}
+
+impl HighScoreTables {
+    pub fn load(config: &GameConfig) -> Self {
+        Self(
+            GameMode::ALL
+                .iter()
+                .map(|mode| (*mode, HighScores::load(config, *mode)))
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, mode: GameMode) -> &HighScores {
+        &self.0[&mode]
+    }
+
+    pub fn get_mut(&mut self, mode: GameMode) -> &mut HighScores {
+        self.0.entry(mode).or_default()
+    }
+}
+
+fn load_high_scores(mut commands: Commands, config: Res<GameConfig>) {
+    commands.insert_resource(HighScoreTables::load(&config));
+}
+
+fn reset_score(mut score: ResMut<Score>, mut stats: ResMut<RunStats>) {
+    score.score = 0;
+    *stats = RunStats::default();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_stats(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    speed: Res<SimulationSpeed>,
+    wave: Res<Wave>,
+    config: Res<GameConfig>,
+    mode: Res<CurrentMode>,
+    mut shots: EventReader<ShotFired>,
+    mut misses: EventReader<ShotMissed>,
+    mut destroyed: EventReader<CuboidDestroyed>,
+    mut score: ResMut<Score>,
+    mut stats: ResMut<RunStats>,
+) {
+    stats.time_survived += speed.game_delta(&time, &settings).as_secs_f32();
+    stats.wave = wave.number;
+    stats.shots_fired += shots.iter().count() as u32;
+    if misses.iter().count() > 0 {
+        stats.combo = 0;
+    }
+    for event in destroyed.iter() {
+        *stats.destroyed.entry(event.size).or_default() += 1;
+        if event.cause == DestructionCause::Shot {
+            stats.shots_hit += 1;
+            stats.combo += 1;
+            stats.best_combo = stats.best_combo.max(stats.combo);
+            let points = config.cuboids.points_per_size * event.size as u32;
+            score.score += mode.0.points(points, wave.number);
+        }
+    }
+}
+
+fn console_commands(
+    mut entered: EventReader<ConsoleCommand>,
+    mut output: EventWriter<ConsoleOutput>,
+    registered: Res<ConsoleCommands>,
+    mut score: ResMut<Score>,
+) {
+    for command in entered.iter().filter(|command| command.name == "score") {
+        match command.arg::<u32>(0) {
+            Some(value) => score.score = value,
+            None => output.send(ConsoleOutput::usage(&registered, "score")),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct ScoringPlugin;
 
 impl Plugin for ScoringPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(Score { score: 0 });
+        app.init_resource::<GameConfig>()
+            .init_resource::<Settings>()
+            .init_resource::<SimulationSpeed>()
+            .insert_resource(Score { score: 0 })
+            .init_resource::<RunStats>()
+            .init_resource::<CurrentMode>()
+            .init_resource::<HighScoreTables>()
+            .add_console_command("score", "<n>", "Sets the score")
+            .add_startup_system(load_high_scores)
+            .add_system(console_commands)
+            .add_system_set(SystemSet::on_enter(GameState::NewRun).with_system(reset_score))
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .after(ShipSystem)
+                    .after(WeaponSystem)
+                    .with_system(update_stats),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: u32) -> HighScore {
+        HighScore {
+            name: "AAA".to_string(),
+            score,
+            wave: 1,
+        }
+    }
+
+    #[test]
+    fn insert_keeps_entries_ordered() {
+        let mut high_scores = HighScores::default();
+        assert_eq!(high_scores.insert(entry(10)), Some(0));
+        assert_eq!(high_scores.insert(entry(30)), Some(0));
+        assert_eq!(high_scores.insert(entry(20)), Some(1));
+        let scores = high_scores
+            .entries
+            .iter()
+            .map(|entry| entry.score)
+            .collect::<Vec<_>>();
+        assert_eq!(scores, vec![30, 20, 10]);
+    }
+
+    #[test]
+    fn full_table_drops_the_lowest_score() {
+        let mut high_scores = HighScores::default();
+        for score in 1..=HighScores::MAX_ENTRIES as u32 {
+            high_scores.insert(entry(score * 10));
+        }
+        assert!(!high_scores.qualifies(10));
+        assert_eq!(high_scores.insert(entry(5)), None);
+        assert!(high_scores.qualifies(15));
+        assert_eq!(
+            high_scores.insert(entry(15)),
+            Some(HighScores::MAX_ENTRIES - 1)
+        );
+        assert_eq!(high_scores.entries.len(), HighScores::MAX_ENTRIES);
+        assert_eq!(
+            high_scores.entries.last().map(|entry| entry.score),
+            Some(15)
+        );
+    }
+
+    #[test]
+    fn zero_score_does_not_qualify() {
+        assert!(!HighScores::default().qualifies(0));
     }
 }
@@ -0,0 +1,518 @@
+use bevy::{prelude::*, window::WindowMode};
+use serde::{Deserialize, Serialize};
+use std::{ops::RangeInclusive, time::Duration};
+
+use super::{config::GameConfig, state::GameState, storage};
+
+/// Values of [`AccessibilitySettings::text_scale`] offered by the settings menu.
+const TEXT_SCALES: RangeInclusive<f32> = 0.5..=2.0;
+/// Values of [`AccessibilitySettings::game_speed`] offered by the settings menu.
+const GAME_SPEEDS: RangeInclusive<f32> = 0.5..=1.0;
+
+/// Keys, which steer the ship.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ControlScheme {
+    /// Both arrows and A/D keys.
+    Both,
+    Arrows,
+    Wasd,
+}
+
+impl ControlScheme {
+    /// Keys moving the ship left and right.
+    pub fn keys(&self) -> (&'static [KeyCode], &'static [KeyCode]) {
+        match self {
+            ControlScheme::Both => (&[KeyCode::A, KeyCode::Left], &[KeyCode::D, KeyCode::Right]),
+            ControlScheme::Arrows => (&[KeyCode::Left], &[KeyCode::Right]),
+            ControlScheme::Wasd => (&[KeyCode::A], &[KeyCode::D]),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ControlScheme::Both => "Arrows + A/D",
+            ControlScheme::Arrows => "Arrows",
+            ControlScheme::Wasd => "A/D",
+        }
+    }
+
+    fn cycle(&self, delta: i8) -> Self {
+        const ALL: [ControlScheme; 3] = [
+            ControlScheme::Both,
+            ControlScheme::Arrows,
+            ControlScheme::Wasd,
+        ];
+        cycle(&ALL, self, delta)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// Lives at the beginning of the game for given base number of lives.
+    pub fn lives(&self, base: u8) -> u8 {
+        match self {
+            Difficulty::Easy => base.saturating_add(2),
+            Difficulty::Normal => base,
+            Difficulty::Hard => base.saturating_sub(1).max(1),
+        }
+    }
+
+    /// Multiplier of spawners' cooldowns.
+    pub fn cooldown_scale(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 1.5,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 0.7,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    fn cycle(&self, delta: i8) -> Self {
+        const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+        cycle(&ALL, self, delta)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    /// Sounds are played. Bevy 0.6 audio cannot change their volume, so there are no sliders.
+    pub sound: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self { sound: true }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub fullscreen: bool,
+    pub vsync: bool,
+    pub show_fps: bool,
+    /// Name of the selected [`crate::theme::Theme`].
+    pub theme: String,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            fullscreen: false,
+            vsync: true,
+            show_fps: true,
+            theme: "Default".to_string(),
+        }
+    }
+}
+
+/// Palette of cuboids, which stay distinguishable with colour vision deficiency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorBlindMode {
+    /// Colours of the selected theme.
+    Off,
+    /// Safe for red-green deficiencies.
+    Deuteranopia,
+    Protanopia,
+    /// Safe for blue-yellow deficiency.
+    Tritanopia,
+}
+
+impl ColorBlindMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorBlindMode::Off => "Off",
+            ColorBlindMode::Deuteranopia => "Deuteranopia",
+            ColorBlindMode::Protanopia => "Protanopia",
+            ColorBlindMode::Tritanopia => "Tritanopia",
+        }
+    }
+
+    fn cycle(&self, delta: i8) -> Self {
+        const ALL: [ColorBlindMode; 4] = [
+            ColorBlindMode::Off,
+            ColorBlindMode::Deuteranopia,
+            ColorBlindMode::Protanopia,
+            ColorBlindMode::Tritanopia,
+        ];
+        cycle(&ALL, self, delta)
+    }
+}
+
+/// Help with aiming and steering for players, who can use only some buttons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Assist {
+    Off,
+    /// Weapon fires whenever it is ready.
+    AutoFire,
+    /// Ship moves on its own and fires automatically, a single button reverses it.
+    OneButton,
+}
+
+impl Assist {
+    pub fn auto_fire(&self) -> bool {
+        *self != Assist::Off
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Assist::Off => "Off",
+            Assist::AutoFire => "Auto-fire",
+            Assist::OneButton => "One button",
+        }
+    }
+
+    fn cycle(&self, delta: i8) -> Self {
+        const ALL: [Assist; 3] = [Assist::Off, Assist::AutoFire, Assist::OneButton];
+        cycle(&ALL, self, delta)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    /// Multiplier of all UI font sizes.
+    pub text_scale: f32,
+    /// Disables screen shake and flashes.
+    pub reduced_motion: bool,
+    pub color_blind: ColorBlindMode,
+    /// Draws a pattern with the number of stripes growing with the cuboid's size.
+    pub size_patterns: bool,
+    /// Multiplier of the game's speed in range [`GAME_SPEEDS`].
+    pub game_speed: f32,
+    pub assist: Assist,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            text_scale: 1.0,
+            reduced_motion: false,
+            color_blind: ColorBlindMode::Off,
+            size_patterns: false,
+            game_speed: 1.0,
+            assist: Assist::Off,
+        }
+    }
+}
+
+impl AccessibilitySettings {
+    /// Time, which passed in the game during the last frame.
+    pub fn game_delta(&self, time: &Time) -> Duration {
+        time.delta().mul_f32(self.game_speed)
+    }
+
+    /// Brings hand-edited values back into the ranges of the settings menu.
+    fn validate(&mut self) {
+        let defaults = Self::default();
+        let clamp = |value: f32, range: &RangeInclusive<f32>, default: f32| {
+            if value.is_nan() {
+                default
+            } else {
+                value.clamp(*range.start(), *range.end())
+            }
+        };
+        self.text_scale = clamp(self.text_scale, &TEXT_SCALES, defaults.text_scale);
+        self.game_speed = clamp(self.game_speed, &GAME_SPEEDS, defaults.game_speed);
+    }
+}
+
+/// Longest step of the game time, so a stalled frame or a high game speed does not tunnel
+/// cuboids through walls. Physics and gameplay timers advance by the same capped step.
+const MAX_GAME_STEP: Duration = Duration::from_millis(50);
+
+/// Speed of the game changed while it runs, kept apart from the saved [`Settings`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationSpeed {
+    /// Multiplier set in the developer console, not saved so debugging does not leak into
+    /// the next session.
+    pub time_scale: f32,
+    /// Multiplier set by the mutators of the run.
+    pub run_speed: f32,
+    /// Game time advanced every frame instead of the real time, e.g. in headless simulations.
+    pub fixed_step: Option<Duration>,
+}
+
+impl Default for SimulationSpeed {
+    fn default() -> Self {
+        Self {
+            time_scale: 1.0,
+            run_speed: 1.0,
+            fixed_step: None,
+        }
+    }
+}
+
+impl SimulationSpeed {
+    /// Time, which passed in the game during the last frame, scaled by the game speed and
+    /// capped at [`MAX_GAME_STEP`].
+    pub fn game_delta(&self, time: &Time, settings: &Settings) -> Duration {
+        let delta = match self.fixed_step {
+            Some(step) => step.mul_f32(settings.accessibility.game_speed),
+            None => settings.accessibility.game_delta(time),
+        };
+        delta
+            .mul_f32(self.time_scale * self.run_speed)
+            .min(MAX_GAME_STEP)
+    }
+}
+
+/// Player's preferences, edited in the settings menu.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub audio: AudioSettings,
+    pub controls: ControlScheme,
+    pub difficulty: Difficulty,
+    pub display: DisplaySettings,
+    pub accessibility: AccessibilitySettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            audio: AudioSettings::default(),
+            controls: ControlScheme::Both,
+            difficulty: Difficulty::Normal,
+            display: DisplaySettings::default(),
+            accessibility: AccessibilitySettings::default(),
+        }
+    }
+}
+
+/// Single adjustable entry of the [`Settings`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    Sound,
+    Controls,
+    Difficulty,
+    Fullscreen,
+    Vsync,
+    ShowFps,
+    Theme,
+    TextScale,
+    ReducedMotion,
+    ColorBlind,
+    SizePatterns,
+    GameSpeed,
+    Assist,
+}
+
+impl Setting {
+    pub const ALL: [Setting; 13] = [
+        Setting::Sound,
+        Setting::Controls,
+        Setting::Difficulty,
+        Setting::Fullscreen,
+        Setting::Vsync,
+        Setting::ShowFps,
+        Setting::Theme,
+        Setting::TextScale,
+        Setting::ReducedMotion,
+        Setting::ColorBlind,
+        Setting::SizePatterns,
+        Setting::GameSpeed,
+        Setting::Assist,
+    ];
+}
+
+impl Settings {
+    /// Human readable label of the setting with its current value.
+    pub fn label(&self, setting: Setting) -> String {
+        let on_off = |value: bool| if value { "On" } else { "Off" };
+        let percent = |value: f32| format!("{:.0}%", value * 100.0);
+        match setting {
+            Setting::Sound => format!("Sound: {}", on_off(self.audio.sound)),
+            Setting::Controls => format!("Controls: {}", self.controls.name()),
+            Setting::Difficulty => format!("Difficulty: {}", self.difficulty.name()),
+            Setting::Fullscreen => format!("Fullscreen: {}", on_off(self.display.fullscreen)),
+            Setting::Vsync => format!("V-Sync: {}", on_off(self.display.vsync)),
+            Setting::ShowFps => format!("Show FPS: {}", on_off(self.display.show_fps)),
+            Setting::Theme => format!("Theme: {}", self.display.theme),
+            Setting::TextScale => {
+                format!("Text size: {}", percent(self.accessibility.text_scale))
+            }
+            Setting::ReducedMotion => format!(
+                "Reduced motion: {}",
+                on_off(self.accessibility.reduced_motion)
+            ),
+            Setting::ColorBlind => {
+                format!(
+                    "Colour-blind mode: {}",
+                    self.accessibility.color_blind.name()
+                )
+            }
+            Setting::SizePatterns => format!(
+                "Size patterns: {}",
+                on_off(self.accessibility.size_patterns)
+            ),
+            Setting::GameSpeed => {
+                format!("Game speed: {}", percent(self.accessibility.game_speed))
+            }
+            Setting::Assist => format!("Assist: {}", self.accessibility.assist.name()),
+        }
+    }
+
+    /// Changes the setting by `delta` steps (negative values decrease it).
+    pub fn adjust(&mut self, setting: Setting, delta: i8) {
+        let step = |value: f32, range: RangeInclusive<f32>| {
+            ((value * 10.0).round() + delta as f32).clamp(range.start() * 10.0, range.end() * 10.0)
+                / 10.0
+        };
+        match setting {
+            Setting::Sound => self.audio.sound = !self.audio.sound,
+            Setting::Controls => self.controls = self.controls.cycle(delta),
+            Setting::Difficulty => self.difficulty = self.difficulty.cycle(delta),
+            Setting::Fullscreen => self.display.fullscreen = !self.display.fullscreen,
+            Setting::Vsync => self.display.vsync = !self.display.vsync,
+            Setting::ShowFps => self.display.show_fps = !self.display.show_fps,
+            // Themes are cycled by the settings menu, which knows all of them
+            Setting::Theme => {}
+            Setting::TextScale => {
+                self.accessibility.text_scale = step(self.accessibility.text_scale, TEXT_SCALES)
+            }
+            Setting::ReducedMotion => {
+                self.accessibility.reduced_motion = !self.accessibility.reduced_motion
+            }
+            Setting::ColorBlind => {
+                self.accessibility.color_blind = self.accessibility.color_blind.cycle(delta)
+            }
+            Setting::SizePatterns => {
+                self.accessibility.size_patterns = !self.accessibility.size_patterns
+            }
+            Setting::GameSpeed => {
+                self.accessibility.game_speed = step(self.accessibility.game_speed, GAME_SPEEDS)
+            }
+            Setting::Assist => self.accessibility.assist = self.accessibility.assist.cycle(delta),
+        }
+    }
+
+    const STORAGE_NAME: &'static str = "settings";
+
+    /// Saved settings, with their values kept in the ranges of the settings menu.
+    pub fn load(config: &GameConfig) -> Option<Self> {
+        let mut settings: Self = storage::load(&config.storage, Self::STORAGE_NAME)?;
+        settings.accessibility.validate();
+        Some(settings)
+    }
+
+    pub fn save(&self, config: &GameConfig) {
+        storage::save(&config.storage, Self::STORAGE_NAME, self);
+    }
+}
+
+fn cycle<T: Copy + PartialEq>(all: &[T], current: &T, delta: i8) -> T {
+    let index = all.iter().position(|v| v == current).unwrap_or(0) as i32;
+    let count = all.len() as i32;
+    all[(index + delta as i32).rem_euclid(count) as usize]
+}
+
+fn apply_display_settings(settings: Res<Settings>, mut windows: ResMut<Windows>) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Some(window) = windows.get_primary_mut() {
+        let mode = if settings.display.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        };
+        if window.mode() != mode {
+            window.set_mode(mode);
+        }
+        if window.vsync() != settings.display.vsync {
+            window.set_vsync(settings.display.vsync);
+        }
+    }
+}
+
+fn load_settings(config: Res<GameConfig>, mut settings: ResMut<Settings>) {
+    if let Some(saved) = Settings::load(&config) {
+        *settings = saved;
+    }
+}
+
+fn save_settings(config: Res<GameConfig>, settings: Res<Settings>) {
+    settings.save(&config);
+}
+
+/// Loads [`Settings`] at startup and saves them after leaving the settings menu.
+#[derive(Default)]
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<Settings>()
+            .init_resource::<SimulationSpeed>()
+            .add_startup_system(load_settings)
+            .add_system_set(SystemSet::on_exit(GameState::Settings).with_system(save_settings))
+            .add_system(apply_display_settings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hand_edited_values_are_clamped() {
+        let mut accessibility = AccessibilitySettings {
+            text_scale: 10.0,
+            game_speed: -1.0,
+            ..Default::default()
+        };
+        accessibility.validate();
+        assert_eq!(accessibility.text_scale, 2.0);
+        assert_eq!(accessibility.game_speed, 0.5);
+
+        accessibility.text_scale = f32::NAN;
+        accessibility.game_speed = 0.0;
+        accessibility.validate();
+        assert_eq!(accessibility.text_scale, 1.0);
+        assert_eq!(accessibility.game_speed, 0.5);
+    }
+
+    #[test]
+    fn game_steps_are_capped() {
+        let time = Time::default();
+        let mut speed = SimulationSpeed {
+            fixed_step: Some(Duration::from_millis(20)),
+            ..Default::default()
+        };
+        let settings = Settings::default();
+        let seconds = |speed: &SimulationSpeed| speed.game_delta(&time, &settings).as_secs_f32();
+        assert!((seconds(&speed) - 0.02).abs() < 1e-6);
+        speed.time_scale = 2.0;
+        assert!((seconds(&speed) - 0.04).abs() < 1e-6);
+        speed.run_speed = 2.0;
+        assert_eq!(speed.game_delta(&time, &settings), MAX_GAME_STEP);
+    }
+
+    #[test]
+    fn adjusting_stays_in_the_menu_ranges() {
+        let mut settings = Settings::default();
+        settings.adjust(Setting::GameSpeed, 1);
+        assert_eq!(settings.accessibility.game_speed, 1.0);
+        for _ in 0..10 {
+            settings.adjust(Setting::GameSpeed, -1);
+        }
+        assert_eq!(settings.accessibility.game_speed, 0.5);
+        settings.adjust(Setting::TextScale, 3);
+        assert_eq!(settings.accessibility.text_scale, 1.3);
+    }
+}
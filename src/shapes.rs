@@ -0,0 +1,190 @@
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+};
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use std::ops::Range;
+
+/// How irregular the generated cuboids are.
+///
+/// All lengths are fractions of the half of the cuboid's edge.
+#[derive(Debug, Clone)]
+pub struct CuboidShapeConfig {
+    /// Most shapes kept for reuse by cuboids of the same seed, e.g. when a run is retried.
+    pub cache_size: usize,
+    /// Number of shapes generated at startup for the shards of destroyed cuboids.
+    pub shards: usize,
+    /// Seed of the shards' shapes, every cuboid gets its own seed from the run's generator.
+    pub seed: u64,
+    /// Maximal random shift of every corner.
+    pub jitter: f32,
+    /// Probability, that a corner is chipped off.
+    pub chip_chance: f32,
+    /// How far from a chipped corner the cut starts.
+    pub chip_size: Range<f32>,
+    /// Width of the beveled edges of the front and back faces.
+    pub bevel: Range<f32>,
+}
+
+impl Default for CuboidShapeConfig {
+    fn default() -> Self {
+        Self {
+            cache_size: 256,
+            shards: 8,
+            seed: 0x5eed_c0b0,
+            jitter: 0.15,
+            chip_chance: 0.5,
+            chip_size: 0.2..0.5,
+            bevel: 0.05..0.2,
+        }
+    }
+}
+
+/// Outline and mesh of a single irregular cuboid.
+pub struct CuboidShape {
+    /// Convex collider matching the outline of the mesh.
+    pub collider: ColliderShape,
+    pub mesh: Mesh,
+}
+
+impl CuboidShape {
+    /// Generates a deformed box with edges of about `2 * extent`.
+    pub fn generate(rng: &mut impl Rng, extent: f32, config: &CuboidShapeConfig) -> Self {
+        let corners = [
+            Vec2::new(-extent, -extent),
+            Vec2::new(extent, -extent),
+            Vec2::new(extent, extent),
+            Vec2::new(-extent, extent),
+        ];
+        let mut points = Vec::new();
+        for (i, &corner) in corners.iter().enumerate() {
+            if rng.gen_bool(config.chip_chance as f64) {
+                // Replace the corner with a cut between its two edges
+                let previous = corners[(i + corners.len() - 1) % corners.len()];
+                let next = corners[(i + 1) % corners.len()];
+                let cut = rng.gen_range(config.chip_size.clone()) * extent;
+                points.push(corner + cut * (previous - corner).normalize());
+                points.push(corner + cut * (next - corner).normalize());
+            } else {
+                points.push(corner);
+            }
+        }
+        let jitter = config.jitter * extent;
+        let points = points
+            .into_iter()
+            .map(|point| {
+                let shift = if jitter > 0.0 {
+                    Vec2::new(
+                        rng.gen_range(-jitter..=jitter),
+                        rng.gen_range(-jitter..=jitter),
+                    )
+                } else {
+                    Vec2::ZERO
+                };
+                let point = point + shift;
+                Point::new(point.x, point.y)
+            })
+            .collect::<Vec<_>>();
+
+        // Jittered corners of a box are never collinear, so the hull always exists
+        let collider = ColliderShape::convex_hull(&points)
+            .unwrap_or_else(|| ColliderShape::cuboid(extent, extent));
+        let outline = match collider.as_convex_polygon() {
+            Some(polygon) => polygon
+                .points()
+                .iter()
+                .map(|point| Vec2::new(point.x, point.y))
+                .collect(),
+            None => corners.to_vec(),
+        };
+        let bevel = rng.gen_range(config.bevel.clone()) * extent;
+        let mesh = extrude(&outline, extent, bevel);
+        Self { collider, mesh }
+    }
+}
+
+/// Extrudes counter-clockwise convex outline to the depth of `2 * extent`,
+/// beveling edges of the front and back faces.
+fn extrude(outline: &[Vec2], extent: f32, bevel: f32) -> Mesh {
+    let center = outline.iter().copied().sum::<Vec2>() / outline.len() as f32;
+    let inset = |point: Vec2| {
+        let offset = point - center;
+        center + offset * (1.0 - bevel / offset.length().max(bevel + f32::EPSILON))
+    };
+    // Rings of vertices from the back to the front face
+    let ring = |z: f32, beveled: bool| {
+        outline
+            .iter()
+            .map(|&point| {
+                let point = if beveled { inset(point) } else { point };
+                point.extend(z)
+            })
+            .collect::<Vec<_>>()
+    };
+    let rings = [
+        ring(-extent, true),
+        ring(bevel - extent, false),
+        ring(extent - bevel, false),
+        ring(extent, true),
+    ];
+
+    let mut triangles = Vec::new();
+    for (back, front) in rings.iter().zip(rings.iter().skip(1)) {
+        for i in 0..outline.len() {
+            let j = (i + 1) % outline.len();
+            triangles.push([back[i], back[j], front[j]]);
+            triangles.push([back[i], front[j], front[i]]);
+        }
+    }
+    let (back, front) = (&rings[0], &rings[rings.len() - 1]);
+    for i in 0..outline.len() {
+        let j = (i + 1) % outline.len();
+        triangles.push([center.extend(extent), front[i], front[j]]);
+        triangles.push([center.extend(-extent), back[j], back[i]]);
+    }
+
+    // Separate vertices for every triangle give the debris flat shading
+    let mut positions = Vec::with_capacity(3 * triangles.len());
+    let mut normals = Vec::with_capacity(3 * triangles.len());
+    let mut uvs = Vec::with_capacity(3 * triangles.len());
+    for [a, b, c] in triangles {
+        let normal = (b - a).cross(c - a).normalize_or_zero();
+        for vertex in [a, b, c] {
+            positions.push(vertex.to_array());
+            normals.push(normal.to_array());
+            uvs.push([0.5 + 0.5 * vertex.x / extent, 0.5 - 0.5 * vertex.y / extent]);
+        }
+    }
+    let indices = (0..positions.len() as u32).collect();
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn outline(seed: u64) -> Vec<Point<Real>> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let shape = CuboidShape::generate(&mut rng, 5.0, &CuboidShapeConfig::default());
+        shape
+            .collider
+            .as_convex_polygon()
+            .unwrap()
+            .points()
+            .to_vec()
+    }
+
+    #[test]
+    fn shapes_are_generated_from_their_seed() {
+        assert_eq!(outline(1), outline(1));
+        assert_ne!(outline(1), outline(2));
+    }
+}
@@ -1,13 +1,18 @@
-use crate::cuboids::Cuboid;
+use crate::cuboids::{Cuboid, CuboidDestroyed, DestructionCause};
 
 use super::{
     assets::{Materials, Meshes},
+    config::GameConfig,
+    console::{ConsoleAppExt, ConsoleCommand, ConsoleCommands, ConsoleOutput},
+    controller::ShipAction,
+    mode::CurrentMode,
+    mutators::{MutatorSystem, RunEffects},
     physics_layers,
+    pool::{PoolSystem, Release},
+    settings::Settings,
+    state::{GameState, RunEntity},
 };
-use bevy::{
-    input::{keyboard::KeyCode, Input},
-    prelude::*,
-};
+use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
 #[derive(Debug, Component)]
@@ -17,22 +22,48 @@ pub struct Ship {
 
 impl Ship {}
 
-fn create_ship(mut commands: Commands, meshes: Res<Meshes>, materials: Res<Materials>) {
-    const SHIP_SIZE: f32 = 8.0;
-    let extent = 0.5 * SHIP_SIZE;
+/// Ship is not damaged by cuboids, toggled from the console.
+#[derive(Debug, Default)]
+pub struct GodMode(pub bool);
+
+/// Sent, when a cuboid hits the ship.
+#[derive(Debug, Clone)]
+pub struct ShipHit {
+    /// Lives left after the hit.
+    pub lives: u8,
+}
+
+fn create_ship(
+    mut commands: Commands,
+    meshes: Res<Meshes>,
+    materials: Res<Materials>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    mode: Res<CurrentMode>,
+    run_effects: Res<RunEffects>,
+) {
+    let config = &config.ship;
+    let lives = mode.0.lives(settings.difficulty.lives(config.lives));
+    let extent = 0.5 * config.size;
     let body = RigidBodyBundle {
-        position: vector![0.0, -50.0].into(),
+        position: config.position.into(),
+        // Ship stays on its line with the low gravity mutator
+        forces: RigidBodyForces {
+            gravity_scale: 0.0,
+            ..Default::default()
+        }
+        .into(),
         ..Default::default()
     };
     let collider = ColliderBundle {
         shape: ColliderShape::cuboid(extent, extent).into(),
         material: ColliderMaterial {
-            restitution: 1.5,
+            restitution: config.restitution,
             ..Default::default()
         }
         .into(),
         flags: ColliderFlags {
-            collision_groups: physics_layers::ALL, // TODO: Do not collide with bullets
+            collision_groups: physics_layers::SHIP,
             active_events: ActiveEvents::CONTACT_EVENTS,
             ..Default::default()
         }
@@ -42,72 +73,122 @@ fn create_ship(mut commands: Commands, meshes: Res<Meshes>, materials: Res<Mater
 
     commands
         .spawn()
-        .insert(Ship { lives: 3 })
+        .insert(Ship {
+            lives: run_effects.effects.lives(lives),
+        })
+        .insert(ShipAction::default())
+        .insert(RunEntity)
         .insert_bundle(body)
         .insert_bundle(collider)
         .insert_bundle(PbrBundle {
             mesh: meshes.ship.clone(),
             material: materials.ship.clone(),
-            transform: Transform::from_translation(Vec3::new(0.0, -50.0, 0.0)),
+            transform: Transform::from_translation(config.position.extend(0.0)),
             ..Default::default()
         })
         .insert(RigidBodyPositionSync::Discrete);
 }
 
-fn ship_input_handling(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut ships: Query<&mut RigidBodyVelocityComponent, With<Ship>>,
+/// Moves the ships as chosen by their pilots.
+fn move_ships(
+    config: Res<GameConfig>,
+    mut ships: Query<(&ShipAction, &mut RigidBodyVelocityComponent), With<Ship>>,
 ) {
-    let mut speed = 0.0;
-
-    if keyboard_input.pressed(KeyCode::A) || keyboard_input.pressed(KeyCode::Left) {
-        speed = -25.0;
-    } else if keyboard_input.pressed(KeyCode::D) || keyboard_input.pressed(KeyCode::Right) {
-        speed = 25.0;
-    }
-
-    let velocity = vector![speed, 0.0];
-    for mut body_handle in ships.iter_mut() {
-        body_handle.0.linvel = velocity;
-        body_handle.0.angvel = -speed * 5.0 / 25.0;
+    for (action, mut body_handle) in ships.iter_mut() {
+        let direction = action.direction.clamp(-1.0, 1.0);
+        body_handle.0.linvel = vector![direction * config.ship.speed, 0.0];
+        body_handle.0.angvel = -direction * config.ship.spin;
     }
 }
 
-fn collisions(
-    mut commands: Commands,
+/// Takes a life from the ship and destroys the cuboid, which hit it.
+pub fn collisions(
     mut event_reader: EventReader<ContactEvent>,
-    cuboids: Query<&Cuboid>,
+    cuboids: Query<(
+        &Cuboid,
+        &RigidBodyPositionComponent,
+        &RigidBodyVelocityComponent,
+    )>,
     mut ships: Query<&mut Ship>,
+    god_mode: Res<GodMode>,
+    mode: Res<CurrentMode>,
+    mut hits: EventWriter<ShipHit>,
+    mut destroyed: EventWriter<CuboidDestroyed>,
+    mut released: EventWriter<Release>,
 ) {
-    let is_cuboid = |e: Entity| cuboids.get(e).is_ok();
-
     for event in event_reader.iter() {
         if let ContactEvent::Started(collider1, collider2) = event {
             let entity1 = collider1.entity();
             let entity2 = collider2.entity();
+            let (ship_entity, other) = if ships.get(entity1).is_ok() {
+                (entity1, entity2)
+            } else {
+                (entity2, entity1)
+            };
 
-            if let Ok(mut ship) = ships.get_mut(entity1) {
-                if is_cuboid(entity2) {
-                    // TODO: mark cuboid for brekage, add end game system and do not check for collisions, when lives == 0
-                    ship.lives -= 1;
-                    commands.entity(entity2).despawn();
-                    dbg!(format!("Hit: {:?} with {:?}", ship, entity2));
+            if let (Ok(mut ship), Ok((cuboid, position, velocity))) =
+                (ships.get_mut(ship_entity), cuboids.get(other))
+            {
+                // Game is over and the ship is not destroyed yet
+                if ship.lives == 0 {
+                    continue;
                 }
-            } else if let Ok(mut ship) = ships.get_mut(entity2) {
-                if is_cuboid(entity1) {
-                    // TODO: mark cuboid for brekage, add end game system and do not check for collisions, when lives == 0
+                released.send(Release(other));
+                if !god_mode.0 && mode.0.damages_ship() {
                     ship.lives -= 1;
-                    commands.entity(entity1).despawn();
-                    dbg!(format!("Hit: {:?} with {:?}", ship, entity1));
+                    hits.send(ShipHit { lives: ship.lives });
+                }
+                destroyed.send(CuboidDestroyed::new(
+                    cuboid,
+                    position,
+                    velocity,
+                    DestructionCause::ShipCollision,
+                ));
+            }
+        }
+    }
+}
+
+fn console_commands(
+    mut entered: EventReader<ConsoleCommand>,
+    mut output: EventWriter<ConsoleOutput>,
+    registered: Res<ConsoleCommands>,
+    mut god_mode: ResMut<GodMode>,
+    mut ships: Query<&mut Ship>,
+) {
+    for command in entered.iter() {
+        match command.name.as_str() {
+            "lives" => match command.arg::<u8>(0) {
+                Some(lives) if lives > 0 => {
+                    for mut ship in ships.iter_mut() {
+                        ship.lives = lives;
+                    }
                 }
+                _ => output.send(ConsoleOutput::usage(&registered, "lives")),
+            },
+            "god" => {
+                god_mode.0 = !god_mode.0;
+                let state = if god_mode.0 { "on" } else { "off" };
+                output.send(ConsoleOutput(format!("God mode {}", state)));
             }
+            _ => {}
         }
     }
 }
 
+fn game_over(ships: Query<&Ship>, mut state: ResMut<State<GameState>>) {
+    if ships.iter().any(|ship| ship.lives == 0) {
+        let _ = state.set(GameState::GameOver);
+    }
+}
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
 pub struct ShipSystem;
 
+/// Ends the run, when the ship has no lives left.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct ShipDestroyedSystem;
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
 enum ShipStagesSystem {
     Movement,
@@ -119,14 +200,32 @@ pub struct ShipPlugin;
 
 impl Plugin for ShipPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system_to_stage(StartupStage::PostStartup, create_ship)
+        app.init_resource::<GameConfig>()
+            .init_resource::<Settings>()
+            .init_resource::<GodMode>()
+            .init_resource::<CurrentMode>()
+            .init_resource::<RunEffects>()
+            .add_event::<ShipHit>()
+            .add_console_command("lives", "<n>", "Sets the ship's lives")
+            .add_console_command("god", "", "Toggles invulnerability")
+            .add_system(console_commands)
             .add_system_set(
-                SystemSet::new()
+                SystemSet::on_enter(GameState::NewRun)
+                    .with_system(create_ship.after(MutatorSystem)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
                     .label(ShipSystem)
-                    .with_system(ship_input_handling)
+                    .before(PoolSystem)
+                    .with_system(move_ships)
                     .label(ShipStagesSystem::Movement)
                     .with_system(collisions)
                     .label(ShipStagesSystem::CollisionsHandler),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .after(ShipSystem)
+                    .with_system(game_over.label(ShipDestroyedSystem)),
             );
     }
 }
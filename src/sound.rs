@@ -0,0 +1,439 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::collections::HashMap;
+
+use super::{
+    config::GameConfig,
+    cuboids::{CuboidDestroyed, Wave},
+    settings::{AudioSettings, Settings},
+    ship::ShipHit,
+    state::GameState,
+    weapon::ShotFired,
+};
+
+/// Gameplay events, which can be heard.
+///
+/// Plugins send them to [`Events<SoundEvent>`] and [`SoundPlugin`] routes
+/// them to the configured sounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundEvent {
+    Fire,
+    Hit,
+    CuboidBreak { size: u8 },
+    WaveStart,
+    GameOver,
+}
+
+/// Key of the sound in [`SoundsConfig`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundKind {
+    Fire,
+    Hit,
+    /// Breaking cuboids of sizes 1 to 3.
+    BreakSmall,
+    /// Breaking cuboids of sizes 4 to 6.
+    BreakMedium,
+    /// Breaking cuboids of size 7 and bigger.
+    BreakLarge,
+    WaveStart,
+    GameOver,
+}
+
+impl From<SoundEvent> for SoundKind {
+    fn from(event: SoundEvent) -> Self {
+        match event {
+            SoundEvent::Fire => SoundKind::Fire,
+            SoundEvent::Hit => SoundKind::Hit,
+            SoundEvent::CuboidBreak { size } if size <= 3 => SoundKind::BreakSmall,
+            SoundEvent::CuboidBreak { size } if size <= 6 => SoundKind::BreakMedium,
+            SoundEvent::CuboidBreak { .. } => SoundKind::BreakLarge,
+            SoundEvent::WaveStart => SoundKind::WaveStart,
+            SoundEvent::GameOver => SoundKind::GameOver,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SoundConfig {
+    /// Path of the sound, relative to the assets directory.
+    pub path: String,
+    /// Volume in range `0.0..=1.0`.
+    pub volume: f32,
+    /// Maximal random change of the pitch, e.g. `0.1` plays the sound at 90% to 110% speed.
+    pub pitch_variation: f32,
+    /// How many instances of the sound can play at the same time.
+    pub max_voices: usize,
+    /// Length of the sound in seconds, used to count playing voices.
+    pub duration: f32,
+}
+
+impl SoundConfig {
+    pub fn effect(path: &str, max_voices: usize, duration: f32) -> Self {
+        Self {
+            path: path.to_string(),
+            volume: 1.0,
+            pitch_variation: 0.1,
+            max_voices,
+            duration,
+        }
+    }
+}
+
+/// Sounds played for the [`SoundEvent`]s. Events without a sound are silent.
+///
+/// The game ships without sounds, so the default configuration is empty.
+#[derive(Debug, Clone, Default)]
+pub struct SoundsConfig {
+    pub sounds: HashMap<SoundKind, SoundConfig>,
+}
+
+impl SoundsConfig {
+    /// Sounds named after their kinds (`fire.ogg`, `break_small.ogg`, ...) in the `directory`
+    /// of the assets.
+    pub fn from_directory(directory: &str) -> Self {
+        let path = |name: &str| format!("{}/{}.ogg", directory, name);
+        let sounds = [
+            (SoundKind::Fire, SoundConfig::effect(&path("fire"), 4, 0.2)),
+            (SoundKind::Hit, SoundConfig::effect(&path("hit"), 2, 0.5)),
+            (
+                SoundKind::BreakSmall,
+                SoundConfig::effect(&path("break_small"), 4, 0.3),
+            ),
+            (
+                SoundKind::BreakMedium,
+                SoundConfig::effect(&path("break_medium"), 3, 0.4),
+            ),
+            (
+                SoundKind::BreakLarge,
+                SoundConfig::effect(&path("break_large"), 2, 0.6),
+            ),
+            (
+                SoundKind::WaveStart,
+                SoundConfig::effect(&path("wave_start"), 1, 1.0),
+            ),
+            (
+                SoundKind::GameOver,
+                SoundConfig {
+                    pitch_variation: 0.0,
+                    ..SoundConfig::effect(&path("game_over"), 1, 3.0)
+                },
+            ),
+        ];
+        Self {
+            sounds: sounds.into_iter().collect(),
+        }
+    }
+}
+
+/// Sound, which should be played now.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaySound {
+    pub kind: SoundKind,
+    pub path: String,
+    /// Volume in range `0.0..=1.0`.
+    pub volume: f32,
+    /// Playback speed multiplier.
+    pub pitch: f32,
+}
+
+/// Output of the sounds, e.g. audio device or a recorder in tests.
+pub trait AudioSink: Send + Sync + 'static {
+    fn play(&mut self, sound: &PlaySound);
+
+    /// Called once per frame with bevy's audio output, after all sounds are played.
+    fn flush(&mut self, _audio: &Audio) {}
+}
+
+/// Plays sounds with bevy's [`Audio`].
+///
+/// Bevy 0.6 audio plays sounds at their original volume and pitch, so this
+/// sink ignores both and only skips silent sounds.
+pub struct BevyAudioSink {
+    asset_server: AssetServer,
+    queue: Vec<Handle<AudioSource>>,
+}
+
+impl BevyAudioSink {
+    pub fn new(asset_server: AssetServer) -> Self {
+        Self {
+            asset_server,
+            queue: Vec::new(),
+        }
+    }
+}
+
+impl AudioSink for BevyAudioSink {
+    fn play(&mut self, sound: &PlaySound) {
+        if sound.volume > 0.0 {
+            self.queue.push(self.asset_server.load(sound.path.as_str()));
+        }
+    }
+
+    fn flush(&mut self, audio: &Audio) {
+        for handle in self.queue.drain(..) {
+            audio.play(handle);
+        }
+    }
+}
+
+/// Sink receiving all played sounds. Replace it to redirect the game's audio.
+pub struct AudioOutput(pub Box<dyn AudioSink>);
+
+/// Maps [`SoundEvent`]s to sounds, limiting the number of simultaneous voices.
+pub struct SoundRouter {
+    /// End times of playing voices per sound.
+    voices: HashMap<SoundKind, Vec<f64>>,
+    rng: StdRng,
+}
+
+impl Default for SoundRouter {
+    fn default() -> Self {
+        Self::new(StdRng::from_entropy())
+    }
+}
+
+impl SoundRouter {
+    pub fn new(rng: StdRng) -> Self {
+        Self {
+            voices: HashMap::new(),
+            rng,
+        }
+    }
+
+    /// Returns the sound for the event at time `now` (in seconds), if it should be played.
+    pub fn route(
+        &mut self,
+        event: SoundEvent,
+        config: &SoundsConfig,
+        settings: &AudioSettings,
+        now: f64,
+    ) -> Option<PlaySound> {
+        if !settings.sound {
+            return None;
+        }
+        let kind = SoundKind::from(event);
+        let sound = config.sounds.get(&kind)?;
+
+        let voices = self.voices.entry(kind).or_default();
+        voices.retain(|end| *end > now);
+        if voices.len() >= sound.max_voices {
+            return None;
+        }
+        voices.push(now + sound.duration as f64);
+
+        let pitch = if sound.pitch_variation > 0.0 {
+            1.0 + self
+                .rng
+                .gen_range(-sound.pitch_variation..=sound.pitch_variation)
+        } else {
+            1.0
+        };
+        Some(PlaySound {
+            kind,
+            path: sound.path.clone(),
+            volume: sound.volume.clamp(0.0, 1.0),
+            pitch,
+        })
+    }
+
+    /// Routes the `events` to the `sink`.
+    pub fn play(
+        &mut self,
+        events: impl IntoIterator<Item = SoundEvent>,
+        config: &SoundsConfig,
+        settings: &AudioSettings,
+        now: f64,
+        sink: &mut dyn AudioSink,
+    ) {
+        for event in events {
+            if let Some(sound) = self.route(event, config, settings, now) {
+                sink.play(&sound);
+            }
+        }
+    }
+}
+
+fn gameplay_sounds(
+    wave: Res<Wave>,
+    mut last_wave: Local<u32>,
+    mut shots: EventReader<ShotFired>,
+    mut hits: EventReader<ShipHit>,
+    mut destroyed: EventReader<CuboidDestroyed>,
+    mut sounds: EventWriter<SoundEvent>,
+) {
+    sounds.send_batch(shots.iter().map(|_| SoundEvent::Fire));
+    sounds.send_batch(hits.iter().map(|_| SoundEvent::Hit));
+    sounds.send_batch(
+        destroyed
+            .iter()
+            .map(|event| SoundEvent::CuboidBreak { size: event.size }),
+    );
+    if wave.number != *last_wave {
+        // First wave starts together with the run
+        if wave.number > 1 {
+            sounds.send(SoundEvent::WaveStart);
+        }
+        *last_wave = wave.number;
+    }
+}
+
+fn game_over_sound(mut sounds: EventWriter<SoundEvent>) {
+    sounds.send(SoundEvent::GameOver);
+}
+
+fn play_sounds(
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    audio: Res<Audio>,
+    mut events: EventReader<SoundEvent>,
+    mut router: ResMut<SoundRouter>,
+    mut output: ResMut<AudioOutput>,
+) {
+    router.play(
+        events.iter().copied(),
+        &config.sounds,
+        &settings.audio,
+        time.seconds_since_startup(),
+        output.0.as_mut(),
+    );
+    output.0.flush(&audio);
+}
+
+fn setup_audio_output(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(AudioOutput(Box::new(BevyAudioSink::new(
+        asset_server.clone(),
+    ))));
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct SoundSystem;
+
+/// Plays sounds for [`SoundEvent`]s.
+///
+/// Uses [`BevyAudioSink`], unless [`AudioOutput`] is inserted before the plugin.
+#[derive(Default)]
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world.contains_resource::<AudioOutput>() {
+            app.add_startup_system(setup_audio_output);
+        }
+        app.init_resource::<GameConfig>()
+            .init_resource::<SoundRouter>()
+            .add_event::<SoundEvent>()
+            .add_system_set(SystemSet::on_update(GameState::InGame).with_system(gameplay_sounds))
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(game_over_sound))
+            .add_system(play_sounds.label(SoundSystem));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keeps the played sounds instead of playing them.
+    #[derive(Default)]
+    struct Recorder(Vec<PlaySound>);
+
+    impl AudioSink for Recorder {
+        fn play(&mut self, sound: &PlaySound) {
+            self.0.push(sound.clone());
+        }
+    }
+
+    fn router() -> SoundRouter {
+        SoundRouter::new(StdRng::seed_from_u64(7))
+    }
+
+    fn config() -> SoundsConfig {
+        SoundsConfig::from_directory("sounds")
+    }
+
+    #[test]
+    fn voices_are_limited_until_they_end() {
+        let config = config();
+        let settings = AudioSettings::default();
+        let mut router = router();
+        let mut recorder = Recorder::default();
+        let limit = config.sounds[&SoundKind::Fire].max_voices;
+        let shots = vec![SoundEvent::Fire; limit + 2];
+        router.play(shots, &config, &settings, 0.0, &mut recorder);
+        assert_eq!(recorder.0.len(), limit);
+
+        let duration = config.sounds[&SoundKind::Fire].duration as f64;
+        router.play(
+            [SoundEvent::Fire],
+            &config,
+            &settings,
+            duration,
+            &mut recorder,
+        );
+        assert_eq!(recorder.0.len(), limit + 1);
+    }
+
+    #[test]
+    fn sounds_are_silent_when_turned_off() {
+        let config = config();
+        let mut recorder = Recorder::default();
+        router().play(
+            [SoundEvent::Hit, SoundEvent::GameOver],
+            &config,
+            &AudioSettings { sound: false },
+            0.0,
+            &mut recorder,
+        );
+        assert!(recorder.0.is_empty());
+
+        router().play(
+            [SoundEvent::Hit, SoundEvent::GameOver],
+            &config,
+            &AudioSettings::default(),
+            0.0,
+            &mut recorder,
+        );
+        let kinds = recorder
+            .0
+            .iter()
+            .map(|sound| sound.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, vec![SoundKind::Hit, SoundKind::GameOver]);
+    }
+
+    #[test]
+    fn pitch_stays_in_the_variation_range() {
+        let config = config();
+        let settings = AudioSettings::default();
+        let variation = config.sounds[&SoundKind::BreakSmall].pitch_variation;
+        let mut router = router();
+        for step in 0..100 {
+            let sound = router
+                .route(
+                    SoundEvent::CuboidBreak { size: 1 },
+                    &config,
+                    &settings,
+                    step as f64 * 10.0,
+                )
+                .unwrap();
+            assert!((1.0 - variation..=1.0 + variation).contains(&sound.pitch));
+        }
+        let game_over = router
+            .route(SoundEvent::GameOver, &config, &settings, 0.0)
+            .unwrap();
+        assert_eq!(game_over.pitch, 1.0);
+    }
+
+    #[test]
+    fn events_without_sound_are_silent() {
+        let mut recorder = Recorder::default();
+        router().play(
+            [SoundEvent::Fire, SoundEvent::WaveStart],
+            &SoundsConfig::default(),
+            &AudioSettings::default(),
+            0.0,
+            &mut recorder,
+        );
+        assert!(recorder.0.is_empty());
+    }
+}
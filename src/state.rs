@@ -0,0 +1,117 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::console::{ConsoleAppExt, ConsoleCommand, ConsoleOutput};
+
+/// Screens of the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameState {
+    MainMenu,
+    /// Settings screen, pushed on top of the screen it was opened from.
+    Settings,
+    /// High scores table, pushed on top of the main menu.
+    HighScores,
+    /// Choice of the game mode, entered from the main menu.
+    Modes,
+    /// Transient state, in which the previous run is cleared and a new one is set up.
+    NewRun,
+    InGame,
+    /// Pause menu, pushed on top of the game.
+    Paused,
+    GameOver,
+    /// Level editor, entered from the main menu.
+    Editor,
+}
+
+/// Marks entities belonging to a single run of the game, which are removed, when a new one starts.
+#[derive(Debug, Default, Component)]
+pub struct RunEntity;
+
+/// Whether there is a run, which can be continued from the main menu.
+#[derive(Debug, Default)]
+pub struct CurrentRun {
+    pub active: bool,
+    /// Seed of the random generator of the current run.
+    pub seed: u64,
+    /// Seed for the next run, e.g. when retrying the previous one.
+    pub next_seed: Option<u64>,
+    /// Run does not enter the high scores, e.g. a repeated daily challenge.
+    pub practice: bool,
+}
+
+/// Random numbers generator of the run, seeded with [`CurrentRun::seed`].
+pub struct RunRng(pub StdRng);
+
+impl Default for RunRng {
+    fn default() -> Self {
+        Self(StdRng::from_entropy())
+    }
+}
+
+fn despawn_run_entities(mut commands: Commands, entities: Query<Entity, With<RunEntity>>) {
+    for entity in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Seeds the [`RunRng`] on entering a new run, [`CurrentRun::next_seed`] is set before it.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct SeedSystem;
+
+fn seed_run(mut run: ResMut<CurrentRun>, mut rng: ResMut<RunRng>) {
+    run.seed = run
+        .next_seed
+        .take()
+        .unwrap_or_else(|| StdRng::from_entropy().gen());
+    rng.0 = StdRng::seed_from_u64(run.seed);
+}
+
+fn start_run(mut state: ResMut<State<GameState>>, mut run: ResMut<CurrentRun>) {
+    run.active = true;
+    let _ = state.set(GameState::InGame);
+}
+
+fn end_run(mut run: ResMut<CurrentRun>) {
+    run.active = false;
+}
+
+fn console_commands(
+    mut entered: EventReader<ConsoleCommand>,
+    mut output: EventWriter<ConsoleOutput>,
+    mut run: ResMut<CurrentRun>,
+) {
+    for command in entered.iter().filter(|command| command.name == "seed") {
+        match command.arg::<u64>(0) {
+            Some(seed) => {
+                run.next_seed = Some(seed);
+                output.send(ConsoleOutput(format!("Next run uses seed {}", seed)));
+            }
+            None => output.send(ConsoleOutput(format!("Seed {}", run.seed))),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct StatePlugin;
+
+impl Plugin for StatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state(GameState::MainMenu)
+            .init_resource::<CurrentRun>()
+            .init_resource::<RunRng>()
+            .add_console_command("seed", "[n]", "Shows the run's seed or sets the next one")
+            .add_system(console_commands)
+            .add_system_set(
+                SystemSet::on_enter(GameState::NewRun)
+                    .with_system(despawn_run_entities)
+                    .with_system(seed_run.label(SeedSystem)),
+            )
+            .add_system_set(SystemSet::on_update(GameState::NewRun).with_system(start_run))
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(end_run))
+            .add_system_set(
+                SystemSet::on_enter(GameState::Editor)
+                    .with_system(despawn_run_entities)
+                    .with_system(end_run),
+            );
+    }
+}
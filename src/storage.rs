@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::config::StorageConfig;
+
+fn path(config: &StorageConfig, name: &str) -> Option<PathBuf> {
+    config
+        .directory
+        .as_ref()
+        .map(|directory| directory.join(format!("{}.ron", name)))
+}
+
+/// Loads `name` saved with [`save`]. Returns `None`, if it was never saved or storage is disabled.
+pub fn load<T: DeserializeOwned>(config: &StorageConfig, name: &str) -> Option<T> {
+    parse(&path(config, name)?)
+}
+
+fn parse<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let content = fs::read_to_string(path).ok()?;
+    match ron::from_str(&content) {
+        Ok(value) => Some(value),
+        Err(error) => {
+            warn!("Could not parse {:?}: {}", path, error);
+            None
+        }
+    }
+}
+
+/// Loads all values saved in the `directory` inside the storage, skipping invalid ones.
+pub fn load_all<T: DeserializeOwned>(config: &StorageConfig, directory: &str) -> Vec<T> {
+    let entries = match config
+        .directory
+        .as_ref()
+        .and_then(|storage| fs::read_dir(storage.join(directory)).ok())
+    {
+        Some(entries) => entries,
+        None => return Vec::new(),
+    };
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .map_or(false, |extension| extension == "ron")
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths.iter().filter_map(|path| parse(path)).collect()
+}
+
+/// Saves `value` as `name` in the storage directory.
+pub fn save<T: Serialize>(config: &StorageConfig, name: &str, value: &T) {
+    let path = match path(config, name) {
+        Some(path) => path,
+        None => return,
+    };
+    let result = ron::ser::to_string_pretty(value, Default::default())
+        .map_err(|error| error.to_string())
+        .and_then(|content| {
+            if let Some(directory) = path.parent() {
+                fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+            }
+            fs::write(&path, content).map_err(|error| error.to_string())
+        });
+    if let Err(error) = result {
+        warn!("Could not save {:?}: {}", path, error);
+    }
+}
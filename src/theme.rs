@@ -0,0 +1,230 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    config::GameConfig,
+    settings::{ColorBlindMode, Settings},
+    storage,
+};
+
+/// Themes shipped with the game, embedded so they are always available.
+const BUILT_IN_THEMES: [&str; 4] = [
+    include_str!("../assets/themes/default.ron"),
+    include_str!("../assets/themes/classic_vector.ron"),
+    include_str!("../assets/themes/neon.ron"),
+    include_str!("../assets/themes/high_contrast.ron"),
+];
+
+/// Directory in the storage with the player's own themes.
+const CUSTOM_THEMES_DIRECTORY: &str = "themes";
+
+/// Colours of a single kind of object.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThemeMaterial {
+    pub color: Color,
+    #[serde(default = "no_emission")]
+    pub emissive: Color,
+    /// Ignores lights, for the flat look of vector graphics.
+    #[serde(default)]
+    pub unlit: bool,
+}
+
+fn no_emission() -> Color {
+    Color::BLACK
+}
+
+impl ThemeMaterial {
+    pub fn material(&self) -> StandardMaterial {
+        let mut material = StandardMaterial::default();
+        self.apply(&mut material);
+        material
+    }
+
+    pub fn apply(&self, material: &mut StandardMaterial) {
+        material.base_color = self.color;
+        material.emissive = self.emissive;
+        material.unlit = self.unlit;
+    }
+}
+
+/// Colours and font of the game, loaded from RON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    pub background: Color,
+    /// Materials of cuboids from size 1, the last one is used for all bigger sizes.
+    pub cuboids: Vec<ThemeMaterial>,
+    pub ship: ThemeMaterial,
+    pub bullet: ThemeMaterial,
+    pub spark: ThemeMaterial,
+    pub thruster: ThemeMaterial,
+    /// Font of the HUD and menus, [`crate::config::UiConfig::font`] if not set.
+    #[serde(default)]
+    pub font: Option<String>,
+    pub text: Color,
+    /// Titles and selected menu items.
+    pub highlight: Color,
+    pub disabled: Color,
+    /// Background of the menus.
+    pub overlay: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Themes::default().themes.remove(0)
+    }
+}
+
+impl Theme {
+    pub fn cuboid(&self, size: u8) -> &ThemeMaterial {
+        let index = (size.max(1) as usize - 1).min(self.cuboids.len() - 1);
+        &self.cuboids[index]
+    }
+
+    /// Replaces colours of cuboids with a palette safe for the colour-blind `mode`.
+    pub fn with_palette(mut self, mode: ColorBlindMode) -> Self {
+        if let Some(palette) = palette(mode) {
+            let unlit = self.cuboids.iter().all(|cuboid| cuboid.unlit);
+            self.cuboids = palette
+                .iter()
+                .map(|&color| ThemeMaterial {
+                    color,
+                    emissive: Color::BLACK,
+                    unlit,
+                })
+                .collect();
+        }
+        self
+    }
+
+    pub fn font<'a>(&'a self, config: &'a GameConfig) -> &'a str {
+        self.font.as_deref().unwrap_or(&config.ui.font)
+    }
+}
+
+/// Colours of cuboids from size 1 for the colour-blind `mode`.
+///
+/// Based on the Okabe-Ito palette, the colours also differ in lightness.
+fn palette(mode: ColorBlindMode) -> Option<&'static [Color]> {
+    const RED_GREEN_SAFE: [Color; 6] = [
+        Color::rgb(0.95, 0.9, 0.25),
+        Color::rgb(0.35, 0.7, 0.9),
+        Color::rgb(0.9, 0.6, 0.0),
+        Color::rgb(0.8, 0.6, 0.7),
+        Color::rgb(0.0, 0.45, 0.7),
+        Color::rgb(0.8, 0.4, 0.0),
+    ];
+    const BLUE_YELLOW_SAFE: [Color; 6] = [
+        Color::rgb(0.95, 0.95, 0.95),
+        Color::rgb(1.0, 0.6, 0.65),
+        Color::rgb(0.0, 0.75, 0.75),
+        Color::rgb(0.85, 0.15, 0.15),
+        Color::rgb(0.0, 0.45, 0.5),
+        Color::rgb(0.5, 0.0, 0.1),
+    ];
+    match mode {
+        ColorBlindMode::Off => None,
+        ColorBlindMode::Deuteranopia | ColorBlindMode::Protanopia => Some(&RED_GREEN_SAFE),
+        ColorBlindMode::Tritanopia => Some(&BLUE_YELLOW_SAFE),
+    }
+}
+
+/// All themes, which can be selected in the settings.
+#[derive(Debug, Clone)]
+pub struct Themes {
+    pub themes: Vec<Theme>,
+}
+
+impl Default for Themes {
+    fn default() -> Self {
+        let themes = BUILT_IN_THEMES
+            .iter()
+            .map(|theme| ron::from_str(theme).expect("Built-in theme is valid"))
+            .collect();
+        Self { themes }
+    }
+}
+
+impl Themes {
+    pub fn get(&self, name: &str) -> Option<&Theme> {
+        self.themes.iter().find(|theme| theme.name == name)
+    }
+
+    /// Name of the theme `delta` positions after the `current` one.
+    pub fn cycle(&self, current: &str, delta: i8) -> String {
+        let index = self
+            .themes
+            .iter()
+            .position(|theme| theme.name == current)
+            .unwrap_or(0) as i32;
+        let count = self.themes.len() as i32;
+        self.themes[(index + delta as i32).rem_euclid(count) as usize]
+            .name
+            .clone()
+    }
+}
+
+fn load_custom_themes(config: Res<GameConfig>, mut themes: ResMut<Themes>) {
+    for theme in storage::load_all::<Theme>(&config.storage, CUSTOM_THEMES_DIRECTORY) {
+        if theme.cuboids.is_empty() {
+            warn!("Theme {:?} has no cuboid colours", theme.name);
+        } else if themes.get(&theme.name).is_some() {
+            warn!("Theme {:?} is already defined", theme.name);
+        } else {
+            themes.themes.push(theme);
+        }
+    }
+}
+
+fn select_theme(
+    settings: Res<Settings>,
+    themes: Res<Themes>,
+    mut theme: ResMut<Theme>,
+    mut selected: Local<Option<(String, ColorBlindMode)>>,
+) {
+    if !settings.is_changed() && !themes.is_changed() {
+        return;
+    }
+    let name = match themes.get(&settings.display.theme) {
+        Some(_) => settings.display.theme.clone(),
+        // Saved theme could be removed, keep the current one
+        None => theme.name.clone(),
+    };
+    let mode = settings.accessibility.color_blind;
+    if selected.as_ref() == Some(&(name.clone(), mode)) {
+        return;
+    }
+    if let Some(base) = themes.get(&name) {
+        *theme = base.clone().with_palette(mode);
+    }
+    *selected = Some((name, mode));
+}
+
+fn apply_background(theme: Res<Theme>, mut clear_color: ResMut<ClearColor>) {
+    if theme.is_changed() {
+        clear_color.0 = theme.background;
+    }
+}
+
+/// Label of the system replacing [`Theme`] after the player selects another one.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct ThemeSystem;
+
+/// Keeps [`Theme`] resource in sync with the theme selected in the [`Settings`].
+///
+/// Plugins read colours from [`Theme`] and update their entities when it changes.
+#[derive(Default)]
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<Settings>()
+            .init_resource::<Themes>()
+            .init_resource::<Theme>()
+            .init_resource::<ClearColor>()
+            .add_startup_system(load_custom_themes)
+            .add_system(select_theme.label(ThemeSystem))
+            .add_system(apply_background.after(ThemeSystem));
+    }
+}
@@ -1,26 +1,32 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
+
+use super::{
+    pool::{PoolSystem, Release},
+    state::GameState,
+};
 // use bevy_rapier2d::rapier::geometry::{ColliderHandle, ColliderSet};
 
 #[derive(Debug, Default, Component)]
 pub struct Trap;
 
-fn traps(
-    mut commands: Commands,
+/// Releases cuboids and bullets, which entered a trap.
+pub fn traps(
     mut intersection_events: EventReader<IntersectionEvent>,
+    mut released: EventWriter<Release>,
     traps: Query<&Trap>,
 ) {
-    let mut despawn_if_trap = |trap, other| {
+    let mut release_if_trap = |trap, other| {
         if traps.get(trap).is_ok() {
-            commands.entity(other).despawn();
+            released.send(Release(other));
         }
     };
     for event in intersection_events.iter() {
         if event.intersecting {
             let entity_1 = event.collider1.entity();
             let entity_2 = event.collider2.entity();
-            despawn_if_trap(entity_1, entity_2);
-            despawn_if_trap(entity_2, entity_1);
+            release_if_trap(entity_1, entity_2);
+            release_if_trap(entity_2, entity_1);
         }
     }
 }
@@ -30,6 +36,8 @@ pub struct TrapsPlugin;
 
 impl Plugin for TrapsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(traps);
+        app.add_system_set(
+            SystemSet::on_update(GameState::InGame).with_system(traps.before(PoolSystem)),
+        );
     }
 }
@@ -1,10 +1,23 @@
+use super::config::GameConfig;
+use super::debug::DebugOverlay;
+use super::menu::{despawn_screen, ScaledText};
+use super::mode::{CurrentMode, ModeClock};
+use super::mutators::RunEffects;
+use super::perf;
 use super::scoring::Score;
+use super::settings::Settings;
 use super::ship::Ship;
+use super::state::GameState;
+use super::theme::Theme;
 use bevy::{
-    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    diagnostic::{DiagnosticId, Diagnostics, FrameTimeDiagnosticsPlugin},
     prelude::*,
 };
 
+const FPS_FONT_SIZE: f32 = 16.0;
+const HUD_FONT_SIZE: f32 = 36.0;
+const MODE_FONT_SIZE: f32 = 20.0;
+
 #[derive(Debug, Component)]
 struct PointsText;
 
@@ -14,16 +27,73 @@ fn update_points_text(score: Res<Score>, mut query: Query<&mut Text, With<Points
     }
 }
 
+#[derive(Debug, Component)]
+struct ModeText;
+
+/// Shows the mode, the time left in it and the mutators of the run.
+fn update_mode_text(
+    config: Res<GameConfig>,
+    mode: Res<CurrentMode>,
+    clock: Res<ModeClock>,
+    run_effects: Res<RunEffects>,
+    mut query: Query<&mut Text, With<ModeText>>,
+) {
+    let mut value = match clock.remaining(mode.0, &config.modes) {
+        Some(remaining) => {
+            let seconds = remaining.ceil() as u32;
+            format!("{} {}:{:02}", mode.0.name(), seconds / 60, seconds % 60)
+        }
+        None => mode.0.name().to_string(),
+    };
+    if !run_effects.names.is_empty() {
+        value = format!("{}\n{}", value, run_effects.names.join(", "));
+    }
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
 #[derive(Debug, Component)]
 struct FpsText;
 
-fn update_fps_text(diagnostics: Res<Diagnostics>, mut query: Query<&mut Text, With<FpsText>>) {
+/// Diagnostics shown with the debug overlay.
+const OVERLAY_DIAGNOSTICS: [(&str, DiagnosticId); 5] = [
+    ("Entities", perf::ENTITIES),
+    ("Cuboids", perf::CUBOIDS),
+    ("Bullets", perf::BULLETS),
+    ("Particles", perf::PARTICLES),
+    ("Pooled", perf::POOLED),
+];
+
+fn update_fps_text(
+    diagnostics: Res<Diagnostics>,
+    settings: Res<Settings>,
+    overlay: Res<DebugOverlay>,
+    mut query: Query<&mut Text, With<FpsText>>,
+) {
     for mut text in query.iter_mut() {
-        if let Some(fps) = diagnostics.get(FrameTimeDiagnosticsPlugin::FPS) {
+        let mut lines = Vec::new();
+        if let (true, Some(fps)) = (
+            settings.display.show_fps,
+            diagnostics.get(FrameTimeDiagnosticsPlugin::FPS),
+        ) {
             if let Some(average) = fps.average() {
-                text.sections[0].value = format!("FPS: {:.2}", average).to_string();
+                lines.push(format!("FPS: {:.2}", average));
             }
         }
+        if overlay.enabled {
+            let counts = OVERLAY_DIAGNOSTICS
+                .iter()
+                .filter_map(|(label, id)| {
+                    let value = diagnostics.get(*id)?.value()?;
+                    Some(format!("{}: {:.0}", label, value))
+                })
+                .collect::<Vec<_>>();
+            lines.push(counts.join("  "));
+        }
+        text.sections[0].value = lines.join("\n");
     }
 }
 
@@ -31,14 +101,32 @@ fn update_fps_text(diagnostics: Res<Diagnostics>, mut query: Query<&mut Text, Wi
 struct LivesText;
 
 fn update_lives_text(ship_query: Query<&Ship>, mut text_query: Query<&mut Text, With<LivesText>>) {
-    let ship = ship_query.single();
+    let ship = match ship_query.get_single() {
+        Ok(ship) => ship,
+        Err(_) => return,
+    };
     for mut text in text_query.iter_mut() {
         text.sections[0].value = format!("Lives: {}", ship.lives).to_string();
     }
 }
 
-fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
+/// Root of the heads-up display shown during the game.
+#[derive(Debug, Component)]
+struct Hud;
+
+fn setup_ui(mut commands: Commands) {
     commands.spawn_bundle(UiCameraBundle::default());
+}
+
+fn spawn_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+) {
+    let font: Handle<Font> = asset_server.load(theme.font(&config));
+    let text_scale = settings.accessibility.text_scale;
     commands
         // FPS text field
         .spawn_bundle(TextBundle {
@@ -54,15 +142,19 @@ fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
             text: Text::with_section(
                 "FPS: ".to_string(),
                 TextStyle {
-                    font: asset_server.load("galaxy-monkey/galax___.ttf"),
-                    font_size: 16.0,
-                    color: Color::WHITE,
+                    font: font.clone(),
+                    font_size: FPS_FONT_SIZE * text_scale,
+                    color: theme.text,
                 },
                 Default::default(),
             ),
             ..Default::default()
         })
-        .insert(FpsText);
+        .insert(FpsText)
+        .insert(ScaledText {
+            size: FPS_FONT_SIZE,
+        })
+        .insert(Hud);
     commands
         // Points text field
         .spawn_bundle(TextBundle {
@@ -78,15 +170,47 @@ fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
             text: Text::with_section(
                 "Points: ".to_string(),
                 TextStyle {
-                    font: asset_server.load("galaxy-monkey/galax___.ttf"),
-                    font_size: 36.0,
-                    color: Color::WHITE,
+                    font: font.clone(),
+                    font_size: HUD_FONT_SIZE * text_scale,
+                    color: theme.text,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(PointsText)
+        .insert(ScaledText {
+            size: HUD_FONT_SIZE,
+        })
+        .insert(Hud);
+    commands
+        // Mode text field
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(2.0 + HUD_FONT_SIZE * text_scale),
+                    left: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                String::new(),
+                TextStyle {
+                    font: font.clone(),
+                    font_size: MODE_FONT_SIZE * text_scale,
+                    color: theme.text,
                 },
                 Default::default(),
             ),
             ..Default::default()
         })
-        .insert(PointsText);
+        .insert(ModeText)
+        .insert(ScaledText {
+            size: MODE_FONT_SIZE,
+        })
+        .insert(Hud);
     commands
         // Lives text field
         .spawn_bundle(TextBundle {
@@ -102,15 +226,19 @@ fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
             text: Text::with_section(
                 "Lives: ".to_string(),
                 TextStyle {
-                    font: asset_server.load("galaxy-monkey/galax___.ttf"),
-                    font_size: 36.0,
-                    color: Color::WHITE,
+                    font: font.clone(),
+                    font_size: HUD_FONT_SIZE * text_scale,
+                    color: theme.text,
                 },
                 Default::default(),
             ),
             ..Default::default()
         })
-        .insert(LivesText);
+        .insert(LivesText)
+        .insert(ScaledText {
+            size: HUD_FONT_SIZE,
+        })
+        .insert(Hud);
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
@@ -120,6 +248,7 @@ pub struct UiSystem;
 enum UiSystemLabels {
     Score,
     Lives,
+    Mode,
     Fps,
 }
 
@@ -128,8 +257,19 @@ pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(FrameTimeDiagnosticsPlugin)
+        app.init_resource::<GameConfig>()
+            .add_plugin(FrameTimeDiagnosticsPlugin)
+            .init_resource::<Settings>()
+            .init_resource::<Theme>()
+            .init_resource::<DebugOverlay>()
+            .init_resource::<CurrentMode>()
+            .init_resource::<ModeClock>()
+            .init_resource::<RunEffects>()
             .add_startup_system(setup_ui)
+            .add_system_set(SystemSet::on_enter(GameState::InGame).with_system(spawn_hud))
+            .add_system_set(
+                SystemSet::on_exit(GameState::InGame).with_system(despawn_screen::<Hud>),
+            )
             .add_system_set(
                 SystemSet::new()
                     .label(UiSystem)
@@ -143,6 +283,11 @@ impl Plugin for UiPlugin {
                         update_lives_text
                             .label(UiSystemLabels::Lives)
                             .after(UiSystemLabels::Score),
+                    )
+                    .with_system(
+                        update_mode_text
+                            .label(UiSystemLabels::Mode)
+                            .after(UiSystemLabels::Lives),
                     ),
             );
     }
@@ -0,0 +1,221 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use std::collections::HashSet;
+
+use super::{
+    assets::{Materials, Meshes},
+    config::GameConfig,
+    controller::ShipAction,
+    cuboids::{Cuboid, CuboidDestroyed, DestructionCause},
+    mutators::RunEffects,
+    physics_layers,
+    pool::{EntityPool, PoolSystem, Release},
+    settings::{Settings, SimulationSpeed},
+    ship::Ship,
+    state::GameState,
+    trap::Trap,
+};
+
+#[derive(Debug, Default, Component)]
+pub struct Bullet;
+
+/// Sent, when the ship fires a bullet.
+#[derive(Debug, Clone)]
+pub struct ShotFired {
+    pub position: Vec2,
+}
+
+/// Sent, when a bullet leaves the arena without hitting any cuboid.
+#[derive(Debug, Clone)]
+pub struct ShotMissed;
+
+/// Seconds left until the ship can fire again.
+#[derive(Debug, Default)]
+struct WeaponCooldown(f32);
+
+fn reset_cooldown(mut cooldown: ResMut<WeaponCooldown>) {
+    cooldown.0 = 0.0;
+}
+
+fn spawn_bullet(
+    commands: &mut Commands,
+    pool: &mut EntityPool<Bullet>,
+    meshes: &Meshes,
+    materials: &Materials,
+    config: &GameConfig,
+    position: Vec2,
+) {
+    let extent = 0.5 * config.weapon.bullet_size;
+    let velocity = RigidBodyVelocity {
+        linvel: vector![0.0, config.weapon.bullet_speed],
+        ..Default::default()
+    };
+    let flags = ColliderFlags {
+        collision_groups: physics_layers::BULLETS,
+        active_events: ActiveEvents::INTERSECTION_EVENTS,
+        ..Default::default()
+    };
+
+    let mut entity = match pool.acquire() {
+        Some(entity) => {
+            let mut entity = commands.entity(entity);
+            entity
+                .insert(RigidBodyPositionComponent::from(position))
+                .insert(RigidBodyVelocityComponent(velocity))
+                .insert(RigidBodyActivationComponent(RigidBodyActivation::active()))
+                .insert(ColliderFlagsComponent(flags))
+                .insert(Visibility { is_visible: true });
+            entity
+        }
+        None => {
+            let mut entity = commands.spawn();
+            entity
+                .insert_bundle(RigidBodyBundle {
+                    position: position.into(),
+                    velocity: velocity.into(),
+                    forces: RigidBodyForces {
+                        gravity_scale: 0.0,
+                        ..Default::default()
+                    }
+                    .into(),
+                    ccd: RigidBodyCcd {
+                        ccd_enabled: true,
+                        ..Default::default()
+                    }
+                    .into(),
+                    ..Default::default()
+                })
+                .insert_bundle(ColliderBundle {
+                    collider_type: ColliderType::Sensor.into(),
+                    shape: ColliderShape::cuboid(extent, extent).into(),
+                    flags: flags.into(),
+                    ..Default::default()
+                })
+                .insert_bundle(PbrBundle {
+                    mesh: meshes.bullet.clone(),
+                    material: materials.bullet.clone(),
+                    ..Default::default()
+                })
+                .insert(RigidBodyPositionSync::Discrete);
+            entity
+        }
+    };
+    entity
+        .insert(Bullet)
+        .insert(Transform::from_translation(position.extend(0.0)));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn fire(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    speed: Res<SimulationSpeed>,
+    run_effects: Res<RunEffects>,
+    meshes: Res<Meshes>,
+    materials: Res<Materials>,
+    mut cooldown: ResMut<WeaponCooldown>,
+    mut pool: ResMut<EntityPool<Bullet>>,
+    mut shots: EventWriter<ShotFired>,
+    ships: Query<(&Transform, &ShipAction), With<Ship>>,
+) {
+    let delta = speed.game_delta(&time, &settings).as_secs_f32();
+    cooldown.0 = (cooldown.0 - delta).max(0.0);
+    if cooldown.0 > 0.0 {
+        return;
+    }
+
+    for (transform, _) in ships.iter().filter(|(_, action)| action.fire) {
+        let position = Vec2::new(
+            transform.translation.x,
+            transform.translation.y + config.ship.size,
+        );
+        spawn_bullet(
+            &mut commands,
+            &mut pool,
+            &meshes,
+            &materials,
+            &config,
+            position,
+        );
+        shots.send(ShotFired { position });
+        cooldown.0 = config.weapon.cooldown * run_effects.effects.weapon_cooldown;
+    }
+}
+
+fn bullet_hits(
+    mut intersection_events: EventReader<IntersectionEvent>,
+    bullets: Query<&Bullet>,
+    traps: Query<&Trap>,
+    cuboids: Query<(
+        &Cuboid,
+        &RigidBodyPositionComponent,
+        &RigidBodyVelocityComponent,
+    )>,
+    mut destroyed: EventWriter<CuboidDestroyed>,
+    mut missed: EventWriter<ShotMissed>,
+    mut released: EventWriter<Release>,
+) {
+    // A bullet or cuboid can intersect with multiple colliders in the same step.
+    let mut removed = HashSet::new();
+    for event in intersection_events.iter() {
+        if !event.intersecting {
+            continue;
+        }
+        let entity_1 = event.collider1.entity();
+        let entity_2 = event.collider2.entity();
+        let (bullet, other) = if bullets.get(entity_1).is_ok() {
+            (entity_1, entity_2)
+        } else if bullets.get(entity_2).is_ok() {
+            (entity_2, entity_1)
+        } else {
+            continue;
+        };
+        if removed.contains(&bullet) || removed.contains(&other) {
+            continue;
+        }
+
+        if let Ok((cuboid, position, velocity)) = cuboids.get(other) {
+            released.send(Release(bullet));
+            released.send(Release(other));
+            removed.insert(bullet);
+            removed.insert(other);
+            destroyed.send(CuboidDestroyed::new(
+                cuboid,
+                position,
+                velocity,
+                DestructionCause::Shot,
+            ));
+        } else if traps.get(other).is_ok() {
+            // Trap system releases the bullet
+            removed.insert(bullet);
+            missed.send(ShotMissed);
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct WeaponSystem;
+
+#[derive(Default)]
+pub struct WeaponPlugin;
+
+impl Plugin for WeaponPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<SimulationSpeed>()
+            .init_resource::<WeaponCooldown>()
+            .init_resource::<RunEffects>()
+            .add_event::<ShotFired>()
+            .add_event::<ShotMissed>()
+            .add_system_set(SystemSet::on_enter(GameState::NewRun).with_system(reset_cooldown))
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .label(WeaponSystem)
+                    .before(PoolSystem)
+                    .with_system(fire)
+                    .with_system(bullet_hits),
+            );
+    }
+}
@@ -1,17 +1,37 @@
-use bevy::app::StartupStage;
 use bevy::prelude::*;
+use bevy::window::Windows;
 use bevy_rapier2d::prelude::*;
 
-use super::{cuboids::Spawner, physics_layers, trap::Trap};
+use super::{
+    background::{parallax, spawn_background},
+    camera::{CameraController, CameraSystem},
+    config::{GameConfig, SpawnerConfig},
+    console::{ConsoleAppExt, ConsoleCommand, ConsoleCommands, ConsoleOutput},
+    cuboids::Spawner,
+    level::{Block, CurrentLevel},
+    mode::CurrentMode,
+    mutators::{MutatorSystem, RunEffects},
+    physics_layers,
+    settings::{Settings, SimulationSpeed},
+    state::{GameState, RunEntity},
+    trap::Trap,
+};
 
 fn setup_3d_world(
     mut commands: Commands,
     mut physics_config: ResMut<bevy_rapier2d::physics::RapierConfiguration>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    config: Res<GameConfig>,
 ) {
     // camera
     let mut camera = PerspectiveCameraBundle::new_3d();
-    camera.transform = Transform::from_xyz(0.0, 1.0, 150.0).looking_at(Vec3::ZERO, Vec3::Y);
-    commands.spawn_bundle(camera);
+    camera.transform =
+        Transform::from_translation(config.world.camera_position).looking_at(Vec3::ZERO, Vec3::Y);
+    commands
+        .spawn_bundle(camera)
+        .insert(CameraController::default());
 
     // light
     commands.spawn_bundle(PointLightBundle {
@@ -21,27 +41,228 @@ fn setup_3d_world(
             color: Color::rgb(1.0, 1.0, 1.0),
             ..Default::default()
         },
-        transform: Transform::from_xyz(4.0, -4.0, 50.0),
+        transform: Transform::from_translation(config.world.light_position),
         ..Default::default()
     });
 
-    physics_config.gravity = vector![0.0, 0.0]
+    // background
+    spawn_background(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        &mut images,
+        &config,
+    );
+
+    physics_config.gravity = vector![0.0, 0.0];
+    // Step length is set every frame by `scale_physics_time`
+    physics_config.timestep_mode = bevy_rapier2d::physics::TimestepMode::FixedTimestep;
+}
+
+/// How the arena adapts to windows with another aspect ratio than [`CameraConfig::view`].
+///
+/// [`CameraConfig::view`]: crate::config::CameraConfig::view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArenaFit {
+    /// Keeps the arena's size and covers the rest of the window with black bars.
+    Letterbox,
+    /// Keeps the arena's size and shows the background around it.
+    Open,
+    /// Widens or narrows the arena, so the walls stay at the window's edges.
+    Expand,
+}
+
+impl Default for ArenaFit {
+    fn default() -> Self {
+        ArenaFit::Letterbox
+    }
+}
+
+/// The narrowest arena with [`ArenaFit::Expand`], relative to the configured one.
+const MIN_EXPAND: f32 = 0.5;
+
+/// Bounds of the playfield fitted to the window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arena {
+    /// Distances of the walls and traps from the arena's center.
+    pub half_size: Vec2,
+    /// Scale from positions in [`crate::config::WorldConfig`] to the world.
+    pub scale: Vec2,
+    /// Area, which the camera keeps visible.
+    pub view: Vec2,
+    /// Width of the letterbox bars on each side of the window, in logical pixels.
+    pub bars: Vec2,
+}
+
+impl Arena {
+    /// Fits the configured arena to the window of the `window` size.
+    pub fn fit(config: &GameConfig, window: Vec2) -> Self {
+        let half_size = Vec2::new(config.world.wall_position, config.world.trap_position);
+        let view = config.camera.view;
+        let window_aspect = window.x / window.y.max(1.0);
+        let view_aspect = view.x / view.y;
+
+        match config.world.arena_fit {
+            ArenaFit::Expand => {
+                let scale = Vec2::new((window_aspect / view_aspect).max(MIN_EXPAND), 1.0);
+                Self {
+                    half_size: half_size * scale,
+                    scale,
+                    view: view * scale,
+                    bars: Vec2::ZERO,
+                }
+            }
+            ArenaFit::Letterbox | ArenaFit::Open => {
+                let bars = if config.world.arena_fit == ArenaFit::Open {
+                    Vec2::ZERO
+                } else if window_aspect > view_aspect {
+                    Vec2::new(0.5 * (window.x - window.y * view_aspect), 0.0)
+                } else {
+                    Vec2::new(0.0, 0.5 * (window.y - window.x / view_aspect))
+                };
+                Self {
+                    half_size,
+                    scale: Vec2::ONE,
+                    view,
+                    bars,
+                }
+            }
+        }
+    }
+
+    /// Moves the configured `position` into the fitted arena.
+    pub fn place(&self, position: Vec2) -> Vec2 {
+        position * self.scale
+    }
+}
+
+impl FromWorld for Arena {
+    fn from_world(world: &mut World) -> Self {
+        let config = world.get_resource_or_insert_with(GameConfig::default);
+        Self::fit(&config, config.camera.view)
+    }
 }
 
+/// Configured position of the entity, which moves with the arena's bounds.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct ArenaPosition(pub Vec2);
+
+/// Configured half size of the collider, which is scaled with the arena.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct ArenaExtent(pub Vec2);
+
+/// Refits the arena, when the window's size changes.
+fn fit_arena(config: Res<GameConfig>, windows: Option<Res<Windows>>, mut arena: ResMut<Arena>) {
+    let window = windows
+        .as_ref()
+        .and_then(|windows| windows.get_primary())
+        .map(|window| Vec2::new(window.width(), window.height()))
+        .unwrap_or(config.camera.view);
+    let fitted = Arena::fit(&config, window);
+    // Avoid marking the arena changed every frame
+    if *arena != fitted {
+        *arena = fitted;
+    }
+}
+
+/// Keeps walls, traps and spawners at the edges of the refitted arena.
+fn align_arena(
+    arena: Res<Arena>,
+    mut bodies: Query<(
+        &ArenaPosition,
+        &ArenaExtent,
+        &mut RigidBodyPositionComponent,
+        &mut ColliderShapeComponent,
+    )>,
+    mut spawners: Query<(&ArenaPosition, &mut Transform), Without<RigidBodyPositionComponent>>,
+) {
+    if !arena.is_changed() {
+        return;
+    }
+    for (position, extent, mut body_position, mut shape) in bodies.iter_mut() {
+        let position = arena.place(position.0);
+        *body_position = vector![position.x, position.y].into();
+        *shape = arena_shape(&arena, extent.0).into();
+    }
+    for (position, mut transform) in spawners.iter_mut() {
+        transform.translation = arena.place(position.0).extend(0.0);
+    }
+}
+
+/// Bar covering the window outside of the arena with [`ArenaFit::Letterbox`].
 #[derive(Debug, Component)]
-pub struct Wall;
+enum LetterboxBar {
+    /// Left or top bar.
+    Start,
+    /// Right or bottom bar.
+    End,
+}
 
-fn setup_walls(mut commands: Commands) {
-    const POSITION: f32 = 110.0;
+fn setup_letterbox(mut commands: Commands) {
+    for bar in [LetterboxBar::Start, LetterboxBar::End] {
+        commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    ..Default::default()
+                },
+                color: Color::BLACK.into(),
+                ..Default::default()
+            })
+            .insert(bar);
+    }
+}
 
-    let mut spawn_wall = |pos| {
-        let body = RigidBodyBundle {
-            body_type: RigidBodyType::Static.into(),
-            position: vector![pos, 0.0].into(),
-            ..Default::default()
+fn resize_letterbox(arena: Res<Arena>, mut bars: Query<(&LetterboxBar, &mut Style)>) {
+    if !arena.is_changed() {
+        return;
+    }
+    let pillarbox = arena.bars.x > 0.0;
+    for (bar, mut style) in bars.iter_mut() {
+        style.size = if pillarbox {
+            Size::new(Val::Px(arena.bars.x), Val::Percent(100.0))
+        } else {
+            Size::new(Val::Percent(100.0), Val::Px(arena.bars.y))
         };
+        style.position = match bar {
+            LetterboxBar::Start => Rect {
+                left: Val::Px(0.0),
+                top: Val::Px(0.0),
+                ..Default::default()
+            },
+            LetterboxBar::End => Rect {
+                right: Val::Px(0.0),
+                bottom: Val::Px(0.0),
+                ..Default::default()
+            },
+        };
+    }
+}
+
+#[derive(Debug, Component)]
+pub struct Wall;
+
+/// Wall or trap of the [`CurrentLevel`], replaced when the level changes.
+#[derive(Debug, Component)]
+pub struct LevelBlock;
+
+/// Rebuilds walls and traps, when the [`CurrentLevel`] changes.
+fn build_level(
+    mut commands: Commands,
+    arena: Res<Arena>,
+    level: Res<CurrentLevel>,
+    blocks: Query<Entity, With<LevelBlock>>,
+) {
+    if !level.is_changed() {
+        return;
+    }
+    for entity in blocks.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    for wall in level.0.walls.iter() {
         let collider = ColliderBundle {
-            shape: ColliderShape::cuboid(1.0, 150.0).into(),
+            shape: arena_shape(&arena, wall.half_size).into(),
             flags: ColliderFlags {
                 collision_groups: physics_layers::WALLS,
                 ..Default::default()
@@ -52,26 +273,14 @@ fn setup_walls(mut commands: Commands) {
         commands
             .spawn()
             .insert(Wall)
-            .insert_bundle(body)
+            .insert_bundle(block_body(&arena, wall))
             .insert_bundle(collider);
-    };
-
-    spawn_wall(-POSITION);
-    spawn_wall(POSITION);
-}
-
-fn setup_traps(mut commands: Commands) {
-    const POSITION: f32 = 100.0;
+    }
 
-    let mut spawn_trap = |pos| {
-        let body = RigidBodyBundle {
-            body_type: RigidBodyType::Static.into(),
-            position: vector![0.0, pos].into(),
-            ..Default::default()
-        };
+    for trap in level.0.traps.iter() {
         let collider = ColliderBundle {
             collider_type: ColliderType::Sensor.into(),
-            shape: ColliderShape::cuboid(110.0, 1.0).into(),
+            shape: arena_shape(&arena, trap.half_size).into(),
             flags: ColliderFlags {
                 collision_groups: physics_layers::TRAPS,
                 active_events: ActiveEvents::INTERSECTION_EVENTS,
@@ -83,42 +292,191 @@ fn setup_traps(mut commands: Commands) {
         commands
             .spawn()
             .insert(Trap)
-            .insert_bundle(body)
+            .insert_bundle(block_body(&arena, trap))
             .insert_bundle(collider);
-    };
+    }
+}
 
-    spawn_trap(-POSITION);
-    spawn_trap(POSITION);
+fn arena_shape(arena: &Arena, half_size: Vec2) -> ColliderShape {
+    let half_size = arena.place(half_size);
+    ColliderShape::cuboid(half_size.x, half_size.y)
 }
 
-fn setup_cubes_spawners(mut commands: Commands) {
-    const POSITION: f32 = 75.0;
+fn block_body(
+    arena: &Arena,
+    block: &Block,
+) -> (LevelBlock, ArenaPosition, ArenaExtent, RigidBodyBundle) {
+    let position = arena.place(block.position);
+    let body = RigidBodyBundle {
+        body_type: RigidBodyType::Static.into(),
+        position: vector![position.x, position.y].into(),
+        ..Default::default()
+    };
+    (
+        LevelBlock,
+        ArenaPosition(block.position),
+        ArenaExtent(block.half_size),
+        body,
+    )
+}
 
-    let mut spawn_spawner = |pos, seconds| {
+fn setup_cubes_spawners(
+    mut commands: Commands,
+    arena: Res<Arena>,
+    level: Res<CurrentLevel>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    mode: Res<CurrentMode>,
+    run_effects: Res<RunEffects>,
+) {
+    let scale = settings.difficulty.cooldown_scale() * mode.0.cooldown_scale(&config.modes);
+    for spawner in level.0.spawners.iter() {
+        let mut spawner_config = SpawnerConfig {
+            cooldown: spawner.cooldown * scale,
+            ..spawner.clone()
+        };
+        run_effects
+            .effects
+            .apply_spawner(&mut spawner_config, &config.cuboids.sizes);
         commands.spawn_bundle((
-            Spawner::new(
-                Timer::from_seconds(seconds, true),
-                Some(240..300),
-                None,
-                None,
-            ),
-            Transform::from_translation(Vec3::new(pos, 70.0, 0.0)),
+            Spawner::from(&spawner_config),
+            Transform::from_translation(arena.place(spawner.position).extend(0.0)),
+            ArenaPosition(spawner.position),
+            RunEntity,
         ));
-    };
+    }
+}
+
+fn resume_physics(mut physics_config: ResMut<bevy_rapier2d::physics::RapierConfiguration>) {
+    physics_config.physics_pipeline_active = true;
+}
 
-    spawn_spawner(-POSITION, 3.13);
-    spawn_spawner(0.0, 1.5);
-    spawn_spawner(POSITION, 2.79);
+fn freeze_physics(mut physics_config: ResMut<bevy_rapier2d::physics::RapierConfiguration>) {
+    physics_config.physics_pipeline_active = false;
 }
 
+/// Advances physics by the frame's time, slowed down by the game speed setting.
+fn scale_physics_time(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    speed: Res<SimulationSpeed>,
+    mut integration_parameters: ResMut<IntegrationParameters>,
+) {
+    let dt = speed.game_delta(&time, &settings).as_secs_f32();
+    if dt > 0.0 {
+        integration_parameters.dt = dt;
+    }
+}
+
+fn console_commands(
+    mut entered: EventReader<ConsoleCommand>,
+    mut output: EventWriter<ConsoleOutput>,
+    registered: Res<ConsoleCommands>,
+    mut speed: ResMut<SimulationSpeed>,
+) {
+    for command in entered.iter().filter(|command| command.name == "timescale") {
+        match command.arg::<f32>(0) {
+            Some(scale) if scale > 0.0 && scale.is_finite() => speed.time_scale = scale,
+            _ => output.send(ConsoleOutput::usage(&registered, "timescale")),
+        }
+    }
+}
+
+/// Label of the system refitting the [`Arena`] to the window.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct ArenaSystem;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+struct AlignArenaSystem;
+
 #[derive(Default)]
 pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(setup_3d_world)
-            .add_startup_system_to_stage(StartupStage::PostStartup, setup_walls)
-            .add_startup_system_to_stage(StartupStage::PostStartup, setup_traps)
-            .add_startup_system_to_stage(StartupStage::PostStartup, setup_cubes_spawners);
+        app.init_resource::<GameConfig>()
+            .init_resource::<SimulationSpeed>()
+            .init_resource::<Arena>()
+            .init_resource::<CurrentLevel>()
+            .init_resource::<CurrentMode>()
+            .init_resource::<RunEffects>()
+            .add_console_command("timescale", "<f>", "Multiplies the game's speed")
+            .add_system(console_commands)
+            .add_startup_system(setup_3d_world)
+            .add_startup_system(fit_arena)
+            .add_startup_system(setup_letterbox)
+            .add_system(fit_arena.label(ArenaSystem))
+            .add_system(align_arena.label(AlignArenaSystem).after(ArenaSystem))
+            .add_system(resize_letterbox.after(ArenaSystem))
+            .add_system(parallax.after(CameraSystem))
+            .add_system(build_level.after(ArenaSystem).before(AlignArenaSystem))
+            .add_system_set(
+                SystemSet::on_enter(GameState::NewRun)
+                    .with_system(setup_cubes_spawners.after(MutatorSystem)),
+            )
+            .add_system_set(SystemSet::on_update(GameState::InGame).with_system(scale_physics_time))
+            .add_system_set(SystemSet::on_enter(GameState::InGame).with_system(resume_physics))
+            .add_system_set(SystemSet::on_resume(GameState::InGame).with_system(resume_physics))
+            .add_system_set(SystemSet::on_pause(GameState::InGame).with_system(freeze_physics))
+            .add_system_set(SystemSet::on_exit(GameState::InGame).with_system(freeze_physics));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-3;
+
+    fn config(arena_fit: ArenaFit) -> GameConfig {
+        let mut config = GameConfig::default();
+        config.world.arena_fit = arena_fit;
+        config
+    }
+
+    #[test]
+    fn letterbox_keeps_the_arena_and_adds_bars() {
+        let config = config(ArenaFit::Letterbox);
+        let view = config.camera.view;
+        let half_size = Vec2::new(config.world.wall_position, config.world.trap_position);
+
+        let wide = Arena::fit(&config, Vec2::new(2.0 * view.x, view.y));
+        assert_eq!(wide.half_size, half_size);
+        assert_eq!(wide.scale, Vec2::ONE);
+        assert!(wide.bars.abs_diff_eq(Vec2::new(0.5 * view.x, 0.0), EPSILON));
+
+        let tall = Arena::fit(&config, Vec2::new(view.x, 2.0 * view.y));
+        assert!(tall.bars.abs_diff_eq(Vec2::new(0.0, 0.5 * view.y), EPSILON));
+    }
+
+    #[test]
+    fn open_arena_has_no_bars() {
+        let config = config(ArenaFit::Open);
+        let view = config.camera.view;
+        let arena = Arena::fit(&config, Vec2::new(2.0 * view.x, view.y));
+        assert_eq!(arena.bars, Vec2::ZERO);
+        assert_eq!(arena.view, view);
+    }
+
+    #[test]
+    fn expand_widens_the_arena_to_the_window() {
+        let config = config(ArenaFit::Expand);
+        let view = config.camera.view;
+        let arena = Arena::fit(&config, Vec2::new(2.0 * view.x, view.y));
+        assert!(arena.scale.abs_diff_eq(Vec2::new(2.0, 1.0), EPSILON));
+        assert!((arena.half_size.x - 2.0 * config.world.wall_position).abs() < EPSILON);
+        assert_eq!(arena.half_size.y, config.world.trap_position);
+        assert_eq!(arena.bars, Vec2::ZERO);
+        assert!(arena
+            .place(Vec2::new(10.0, 10.0))
+            .abs_diff_eq(Vec2::new(20.0, 10.0), EPSILON));
+    }
+
+    #[test]
+    fn expand_does_not_narrow_below_the_limit() {
+        let config = config(ArenaFit::Expand);
+        let view = config.camera.view;
+        let arena = Arena::fit(&config, Vec2::new(0.1 * view.x, view.y));
+        assert_eq!(arena.scale, Vec2::new(MIN_EXPAND, 1.0));
     }
 }